@@ -0,0 +1,357 @@
+use crate::secret::owner_labels;
+use crate::types::ContextData;
+use crate::CONTROLLER_NAME;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{
+    api::{ObjectMeta, Patch, PatchParams},
+    Api,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub const BUDGET_CONFIGMAP_NAME: &str = "cert-manager-routes-controller-issuance-budget";
+const BUDGET_CONFIGMAP_KEY: &str = "state.json";
+
+/// The maximum number of `Certificate`s created per registered domain and `ClusterIssuer` over a sliding window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IssuanceBudget {
+    pub limit: usize,
+    pub window: Duration,
+}
+
+/// A [`Route`](crate::crd::route::Route) waiting for the [`IssuanceBudget`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedRoute {
+    /// The [`Route`](crate::crd::route::Route) in the `namespace/name` format.
+    pub route: String,
+    /// The last time the [`Route`](crate::crd::route::Route) asked for the budget, in seconds since the epoch.
+    pub seen: i64,
+}
+
+/// The consumption of the [`IssuanceBudget`], persisted across restarts in the [`BUDGET_CONFIGMAP_NAME`] `ConfigMap`.
+///
+/// Both maps are keyed by [`get_budget_key()`].
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetState {
+    /// The creation times of the `Certificate`s within the window, in seconds since the epoch.
+    #[serde(default)]
+    pub issued: BTreeMap<String, Vec<i64>>,
+    /// The [`QueuedRoute`]s, in order of arrival.
+    #[serde(default)]
+    pub queued: BTreeMap<String, Vec<QueuedRoute>>,
+}
+
+/// Get the registered domain of a host, made of its last two labels.
+///
+/// The public suffix list isn't used, so the hosts of a multi-label suffix such as `co.uk` share their budget.
+///
+/// ### Arguments
+///
+/// * `host` - The host, possibly a wildcard.
+///
+/// ### Returns
+///
+/// A [`String`] containing the registered domain.
+///
+/// ### Example
+///
+/// ```rust
+/// let domain = get_registered_domain("app.apps.example.com");
+/// println!("{}", domain); // example.com
+/// ```
+pub fn get_registered_domain(host: &str) -> String {
+    let labels = host
+        .trim_end_matches('.')
+        .split('.')
+        .filter(|label| !label.is_empty() && *label != "*")
+        .collect::<Vec<_>>();
+    labels[labels.len().saturating_sub(2)..].join(".")
+}
+
+#[test]
+fn test_get_registered_domain() {
+    assert_eq!(get_registered_domain("app.apps.example.com"), "example.com");
+    assert_eq!(get_registered_domain("*.example.com"), "example.com");
+    assert_eq!(get_registered_domain("example.com."), "example.com");
+    assert_eq!(get_registered_domain("localhost"), "localhost");
+}
+
+/// Get the key of the budget shared by a `ClusterIssuer` and the registered domain of a host.
+///
+/// ### Arguments
+///
+/// * `issuer` - The name of the `ClusterIssuer`.
+/// * `host` - The host of the `Certificate`.
+///
+/// ### Returns
+///
+/// A [`String`] in the format `issuer/domain`.
+///
+/// ### Example
+///
+/// ```rust
+/// let key = get_budget_key("letsencrypt-prod", "app.example.com");
+/// println!("{}", key); // letsencrypt-prod/example.com
+/// ```
+pub fn get_budget_key(issuer: &str, host: &str) -> String {
+    format!("{}/{}", issuer, get_registered_domain(host))
+}
+
+#[test]
+fn test_get_budget_key() {
+    assert_eq!(
+        get_budget_key("letsencrypt-prod", "app.example.com"),
+        "letsencrypt-prod/example.com"
+    );
+}
+
+/// Drop the issuances that left the window and the [`QueuedRoute`]s that stopped asking for the budget.
+///
+/// A [`QueuedRoute`] is requeued at the latest when the window slides, so it is stale after two windows.
+fn prune_budget_state(state: &mut BudgetState, budget: &IssuanceBudget, now: i64) {
+    let window = budget.window.as_secs() as i64;
+    state.issued.values_mut().for_each(|issued| {
+        issued.retain(|time| *time > now - window);
+        issued.sort();
+    });
+    state.issued.retain(|_, issued| !issued.is_empty());
+    state
+        .queued
+        .values_mut()
+        .for_each(|queue| queue.retain(|queued| queued.seen > now - 2 * window));
+    state.queued.retain(|_, queue| !queue.is_empty());
+}
+
+/// Ask the [`IssuanceBudget`] for the creation of a `Certificate` by a [`Route`](crate::crd::route::Route).
+///
+/// The [`Route`](crate::crd::route::Route)s are served in order of arrival: a [`Route`](crate::crd::route::Route)
+/// may only create its `Certificate` once the [`Route`](crate::crd::route::Route)s queued before it did.
+///
+/// ### Arguments
+///
+/// * `state` - The [`BudgetState`], updated with the issuance or the queued [`Route`](crate::crd::route::Route).
+/// * `budget` - The [`IssuanceBudget`].
+/// * `key` - The key of the budget, see [`get_budget_key()`].
+/// * `route` - The [`Route`](crate::crd::route::Route) in the `namespace/name` format.
+/// * `now` - The current time, in seconds since the epoch.
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` if the `Certificate` may be created,
+/// or the estimated time at which the [`Route`](crate::crd::route::Route) will get the budget.
+///
+/// ### Example
+///
+/// ```rust
+/// if let Err(ready_at) = acquire_issuance(&mut state, &budget, &key, "default/app", Utc::now().timestamp()) {
+///     println!("Queued until {}", ready_at);
+/// }
+/// ```
+pub fn acquire_issuance(
+    state: &mut BudgetState,
+    budget: &IssuanceBudget,
+    key: &str,
+    route: &str,
+    now: i64,
+) -> Result<(), i64> {
+    prune_budget_state(state, budget, now);
+    let window = budget.window.as_secs() as i64;
+    let issued = state.issued.entry(key.to_owned()).or_default();
+    let queue = state.queued.entry(key.to_owned()).or_default();
+    let position = match queue.iter().position(|queued| queued.route == route) {
+        Some(position) => {
+            queue[position].seen = now;
+            position
+        }
+        None => {
+            queue.push(QueuedRoute {
+                route: route.to_owned(),
+                seen: now,
+            });
+            queue.len() - 1
+        }
+    };
+    let available = budget.limit.saturating_sub(issued.len());
+    let result = if position < available {
+        queue.remove(position);
+        issued.push(now);
+        Ok(())
+    } else {
+        Err(issued
+            .get(position - available)
+            .or(issued.last())
+            .map(|time| time + window)
+            .unwrap_or(now))
+    };
+    state.issued.retain(|_, issued| !issued.is_empty());
+    state.queued.retain(|_, queue| !queue.is_empty());
+    result
+}
+
+#[test]
+fn test_acquire_issuance() {
+    let budget = IssuanceBudget {
+        limit: 2,
+        window: Duration::from_secs(100),
+    };
+    let mut state = BudgetState::default();
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/a", 0),
+        Ok(())
+    );
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/b", 10),
+        Ok(())
+    );
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "other", "ns/c", 10),
+        Ok(())
+    );
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/c", 20),
+        Err(100)
+    );
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/d", 30),
+        Err(110)
+    );
+    // The queue is served in order of arrival.
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/d", 101),
+        Err(110)
+    );
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/c", 101),
+        Ok(())
+    );
+    assert_eq!(
+        acquire_issuance(&mut state, &budget, "key", "ns/d", 111),
+        Ok(())
+    );
+    assert_eq!(state.queued, BTreeMap::new());
+    assert_eq!(state.issued.get("key"), Some(&vec![101, 111]));
+}
+
+/// Remove a [`Route`](crate::crd::route::Route) from the queues of the [`BudgetState`].
+///
+/// ### Arguments
+///
+/// * `state` - The [`BudgetState`].
+/// * `route` - The [`Route`](crate::crd::route::Route) in the `namespace/name` format.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the [`Route`](crate::crd::route::Route) was queued.
+///
+/// ### Example
+///
+/// ```rust
+/// if dequeue_route(&mut state, "default/app") {
+///     println!("Route removed from the queue");
+/// }
+/// ```
+pub fn dequeue_route(state: &mut BudgetState, route: &str) -> bool {
+    let mut dequeued = false;
+    state.queued.values_mut().for_each(|queue| {
+        let len = queue.len();
+        queue.retain(|queued| queued.route != route);
+        dequeued |= queue.len() != len;
+    });
+    state.queued.retain(|_, queue| !queue.is_empty());
+    dequeued
+}
+
+#[test]
+fn test_dequeue_route() {
+    let budget = IssuanceBudget {
+        limit: 0,
+        window: Duration::from_secs(100),
+    };
+    let mut state = BudgetState::default();
+    let _ = acquire_issuance(&mut state, &budget, "key", "ns/a", 0);
+    let _ = acquire_issuance(&mut state, &budget, "key", "ns/b", 0);
+    assert!(!dequeue_route(&mut state, "ns/c"));
+    assert!(dequeue_route(&mut state, "ns/a"));
+    assert_eq!(
+        state.queued.get("key"),
+        Some(&vec![QueuedRoute {
+            route: "ns/b".to_owned(),
+            seen: 0
+        }])
+    );
+    assert!(dequeue_route(&mut state, "ns/b"));
+    assert_eq!(state, BudgetState::default());
+}
+
+/// Load the [`BudgetState`] from the [`BUDGET_CONFIGMAP_NAME`] `ConfigMap`.
+///
+/// A missing or unreadable state is replaced by an empty one.
+///
+/// ### Arguments
+///
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`BudgetState`] or a [`kube::Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let state = load_budget_state(&ctx).await?;
+/// println!("{:?}", state);
+/// ```
+pub async fn load_budget_state(ctx: &ContextData) -> Result<BudgetState, kube::Error> {
+    let config_maps = Api::<ConfigMap>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace);
+    Ok(config_maps
+        .get_opt(BUDGET_CONFIGMAP_NAME)
+        .await?
+        .and_then(|config_map| config_map.data)
+        .and_then(|data| data.get(BUDGET_CONFIGMAP_KEY).cloned())
+        .and_then(|state| serde_json::from_str(&state).ok())
+        .unwrap_or_default())
+}
+
+/// Save the [`BudgetState`] to the [`BUDGET_CONFIGMAP_NAME`] `ConfigMap`.
+///
+/// ### Arguments
+///
+/// * `state` - The [`BudgetState`] to save.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`kube::Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// save_budget_state(&state, &ctx).await?;
+/// ```
+pub async fn save_budget_state(state: &BudgetState, ctx: &ContextData) -> Result<(), kube::Error> {
+    let config_maps = Api::<ConfigMap>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace);
+    let config_map = ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(BUDGET_CONFIGMAP_NAME.to_owned()),
+            namespace: Some(ctx.cert_manager_namespace.clone()),
+            labels: Some(owner_labels()),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([(
+            BUDGET_CONFIGMAP_KEY.to_owned(),
+            serde_json::to_string(state).map_err(kube::Error::SerdeError)?,
+        )])),
+        ..Default::default()
+    };
+    let _ = config_maps
+        .patch(
+            BUDGET_CONFIGMAP_NAME,
+            &PatchParams::apply(CONTROLLER_NAME).force(),
+            &Patch::Apply(&config_map),
+        )
+        .await?;
+    Ok(())
+}
@@ -1,18 +1,135 @@
 use crate::crd::{
-    certificate::{Certificate, CertificateIssuerRef, CertificateSpec},
+    certificate::{
+        Certificate, CertificateIssuerRef, CertificatePrivateKey, CertificatePrivateKeyAlgorithm,
+        CertificateSpec,
+    },
+    policy::RoutesCertificatePolicyCertificate,
     route::Route,
 };
+use crate::namespace::IssuerDefaults;
+use crate::route::{
+    format_dedicated_cert_name, get_certificate_mode, get_route_cluster_issuer, get_route_host,
+    CertificateMode,
+};
 use crate::tools::{
-    format_cert_annotation, format_cert_name, format_secret_name, resource_to_string,
+    api_span, format_cert_annotation, format_cert_name, format_secret_name, get_namespace,
+    parse_duration, resource_to_string,
+};
+use crate::types::{ContextData, Error};
+use crate::{
+    CERTIFICATE_GROUP_ANNOTATION_KEY, CERTIFICATE_GROUP_NAMESPACE_LABEL_KEY,
+    CERTIFICATE_MODE_ANNOTATION_KEY, CERT_ANNOTATION_KEY, CERT_DURATION_ANNOTATION_KEY,
+    CERT_PRIVATE_KEY_ALGORITHM_ANNOTATION_KEY, CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY,
+    CERT_RENEW_BEFORE_ANNOTATION_KEY,
 };
-use crate::types::ContextData;
-use crate::{CERT_ANNOTATION_KEY, CLUSTER_ISSUER_ANNOTATION_KEY};
+use k8s_openapi::api::core::v1::Secret;
 use kube::{
-    api::{ObjectMeta, Patch, PatchParams, PostParams},
-    Api,
+    api::{DeleteParams, ListParams, ObjectMeta, Patch, PatchParams, PostParams, Preconditions},
+    Api, ResourceExt,
 };
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use tracing::Instrument;
+
+const MAX_CERTIFICATE_GROUP_LEN: usize = 48;
+
+/// The settings of the [`Certificate`] created for a [`Route`],
+/// resolved from its `RoutesCertificatePolicy` rule and annotations.
+pub type CertificateSettings = RoutesCertificatePolicyCertificate;
+
+/// A certificate group, whose [`Route`]s share multi-SAN [`Certificate`]s.
+///
+/// The groups of the [`CERTIFICATE_GROUP_ANNOTATION_KEY`] annotation are chosen by the [`Route`]'s author,
+/// so they are scoped to the [`Route`]'s namespace. The groups of the `RoutesCertificatePolicy` rules are
+/// chosen by the cluster administrators and may span several namespaces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CertificateGroup {
+    /// The name of the group.
+    pub name: String,
+    /// The namespace the group is scoped to, [`None`] for the groups of the `RoutesCertificatePolicy` rules.
+    pub namespace: Option<String>,
+}
+
+impl CertificateGroup {
+    /// Get the labels of the [`Certificate`]s of the group.
+    pub fn labels(&self) -> BTreeMap<String, String> {
+        let mut labels = BTreeMap::from([(
+            CERTIFICATE_GROUP_ANNOTATION_KEY.to_owned(),
+            self.name.clone(),
+        )]);
+        if let Some(namespace) = &self.namespace {
+            labels.insert(
+                CERTIFICATE_GROUP_NAMESPACE_LABEL_KEY.to_owned(),
+                namespace.clone(),
+            );
+        }
+        labels
+    }
+
+    /// Get the label selector of the [`Certificate`]s of the group.
+    pub fn label_selector(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!(
+                "{}={},{}={}",
+                CERTIFICATE_GROUP_ANNOTATION_KEY,
+                self.name,
+                CERTIFICATE_GROUP_NAMESPACE_LABEL_KEY,
+                namespace
+            ),
+            None => format!(
+                "{}={},!{}",
+                CERTIFICATE_GROUP_ANNOTATION_KEY, self.name, CERTIFICATE_GROUP_NAMESPACE_LABEL_KEY
+            ),
+        }
+    }
+
+    /// Get the prefix of the names of the [`Certificate`]s of the group.
+    ///
+    /// Namespaces and group names can't contain a `.`, so the prefix `namespace.group` of a scoped group
+    /// never collides with the prefix of another group.
+    pub fn cert_name_prefix(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}.{}", namespace, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for CertificateGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}", resource_to_string(&self.name, namespace)),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[test]
+fn test_certificate_group() {
+    let group = CertificateGroup {
+        name: "web".to_owned(),
+        namespace: Some("shop".to_owned()),
+    };
+    assert_eq!(
+        group.label_selector(),
+        "cert-manager.io/certificate-group=web,cert-manager.io/certificate-group-namespace=shop"
+    );
+    assert_eq!(group.labels().len(), 2);
+    assert_eq!(group.cert_name_prefix(), "shop.web");
+    assert_eq!(group.to_string(), "shop/web");
+
+    let group = CertificateGroup {
+        name: "web".to_owned(),
+        namespace: None,
+    };
+    assert_eq!(
+        group.label_selector(),
+        "cert-manager.io/certificate-group=web,!cert-manager.io/certificate-group-namespace"
+    );
+    assert_eq!(group.labels().len(), 1);
+    assert_eq!(group.cert_name_prefix(), "web");
+    assert_eq!(group.to_string(), "web");
+}
 
 impl Certificate {
     /// Create a new [`Certificate`] with some default values.
@@ -22,6 +139,7 @@ impl Certificate {
     /// * `name` - The name of the [`Certificate`].
     /// * `hostname` - The dnsName of the [`Certificate`].
     /// * `issuer_name` - The `ClusterIssuer` to use for the [`Certificate`]
+    /// * `settings` - The [`CertificateSettings`] of the [`Certificate`].
     /// * `ctx` - The [`ContextData`].
     ///
     /// ### Returns
@@ -31,41 +149,52 @@ impl Certificate {
     /// ### Example
     ///
     /// ```rust
-    /// let cert = Certificate::new_default(&name, &hostname, &issuer_name, &ctx);
+    /// let cert = Certificate::new_default(&name, &hostname, &issuer_name, &settings, &ctx);
     /// println!("Created Certificate: {}", cert);
     /// ```
     fn new_default(
-        name: &String,
-        hostname: &String,
-        issuer_name: &String,
+        name: &str,
+        hostname: &str,
+        issuer_name: &str,
+        settings: &CertificateSettings,
         ctx: &ContextData,
     ) -> Self {
         Certificate {
             status: None,
             metadata: ObjectMeta {
-                name: Some(name.clone()),
+                name: Some(name.to_owned()),
                 namespace: Some(ctx.cert_manager_namespace.clone()),
                 ..Default::default()
             },
             spec: CertificateSpec {
-                secret_name: format_secret_name(&hostname),
-                dns_names: Some(vec![hostname.clone()]),
+                secret_name: format_secret_name(hostname),
+                dns_names: Some(vec![hostname.to_owned()]),
                 issuer_ref: CertificateIssuerRef {
-                    name: issuer_name.clone(),
+                    name: issuer_name.to_owned(),
                     kind: Some("ClusterIssuer".to_owned()),
                     group: Some("cert-manager.io".to_owned()),
                 },
                 is_ca: Some(false),
-                private_key: None,
+                private_key: match (&settings.private_key_algorithm, settings.private_key_size) {
+                    (None, None) => None,
+                    (algorithm, size) => Some(CertificatePrivateKey {
+                        algorithm: algorithm
+                            .as_ref()
+                            .and_then(|algorithm| parse_private_key_algorithm(algorithm)),
+                        encoding: None,
+                        rotation_policy: None,
+                        size,
+                    }),
+                },
                 additional_output_formats: None,
                 common_name: None,
-                duration: None,
+                duration: settings.duration.clone(),
                 email_addresses: None,
                 encode_usages_in_request: None,
                 ip_addresses: None,
                 keystores: None,
                 literal_subject: None,
-                renew_before: None,
+                renew_before: settings.renew_before.clone(),
                 revision_history_limit: None,
                 secret_template: None,
                 subject: None,
@@ -83,10 +212,7 @@ impl fmt::Display for Certificate {
         write!(
             f,
             "{}",
-            resource_to_string(
-                self.metadata.name.as_ref().unwrap(),
-                self.metadata.namespace.as_ref().unwrap()
-            )
+            resource_to_string(&self.name_any(), &self.namespace().unwrap_or_default())
         )
     }
 }
@@ -98,36 +224,39 @@ impl fmt::Display for Certificate {
 ///
 /// ### Arguments
 ///
+/// * `namespace` - The namespace of the [`Certificate`] to annotate.
 /// * `cert_name` - The name of the [`Certificate`] to annotate.
 /// * `route` - The [`Route`] that uses the [`Certificate`].
 /// * `ctx` - The [`ContextData`].
+/// * `add` - Whether the [`Route`] is added to or removed from the annotation.
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing the annotated [`Certificate`] or a [`kube::Error`].
+/// A [`Result`] containing the annotated [`Certificate`] or an [`Error`].
 ///
 /// ### Example
 ///
 /// ```rust
-/// let cert = annotate_cert(&cert_name, &route, &ctx).await?;
+/// let cert = annotate_cert(&namespace, &cert_name, &route, &ctx, true).await?;
 /// println!("Annotated Certificate: {}", cert);
 /// ```
 pub async fn annotate_cert(
-    cert_name: &String,
+    namespace: &str,
+    cert_name: &str,
     route: &Route,
     ctx: &ContextData,
     add: bool,
-) -> Result<Certificate, kube::Error> {
-    let mut annotations =
-        Api::<Certificate>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
-            .get(cert_name)
-            .await?
-            .metadata
-            .annotations
-            .unwrap_or_default();
-    let annotation = format_cert_annotation(annotations.get(CERT_ANNOTATION_KEY), &route, add);
+) -> Result<Certificate, Error> {
+    let mut annotations = Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
+        .get(cert_name)
+        .instrument(api_span("get", "Certificate", namespace, cert_name))
+        .await?
+        .metadata
+        .annotations
+        .unwrap_or_default();
+    let annotation = format_cert_annotation(annotations.get(CERT_ANNOTATION_KEY), route, add);
     let _ = annotations.insert(CERT_ANNOTATION_KEY.to_owned(), annotation);
-    let cert = Api::<Certificate>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
+    let cert = Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
         .patch(
             cert_name,
             &PatchParams::default(),
@@ -137,52 +266,853 @@ pub async fn annotate_cert(
                 }
             })),
         )
+        .instrument(api_span("patch", "Certificate", namespace, cert_name))
         .await?;
     Ok(cert)
 }
 
+/// Get the `ClusterIssuer` a [`Route`] requests its [`Certificate`] from (see [`get_route_cluster_issuer()`]),
+/// or an [`Error::InvalidRoute`] if it requests none.
+fn get_route_issuer(route: &Route, defaults: &IssuerDefaults) -> Result<String, Error> {
+    get_route_cluster_issuer(route, defaults).ok_or_else(|| {
+        Error::InvalidRoute(
+            route.to_string(),
+            "no ClusterIssuer is requested".to_owned(),
+        )
+    })
+}
+
 /// Create a [`Certificate`] for a [`Route`]'s hostname.
 ///
+/// In the [`CertificateMode::Dedicated`] mode, the [`Certificate`] is created in the [`Route`]'s namespace,
+/// named after the [`Route`] and labelled with the [`CERTIFICATE_MODE_ANNOTATION_KEY`].
+///
 /// ### Arguments
 ///
 /// * `route` - The [`Route`] that will use the [`Certificate`].
+/// * `settings` - The [`CertificateSettings`] of the [`Certificate`] (see [`get_certificate_settings()`]).
 /// * `ctx` - The [`ContextData`].
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing the created [`Certificate`] or a [`kube::Error`].
+/// A [`Result`] containing the created [`Certificate`] or an [`Error`].
 ///
 /// ### Example
 ///
 /// ```rust
-/// let cert = create_certificate(&route, &ctx).await?;
+/// let cert = create_certificate(&route, &settings, &ctx).await?;
 /// println!("Created Certificate: {}", cert);
 /// ```
 pub async fn create_certificate(
     route: &Route,
+    settings: &CertificateSettings,
+    ctx: &ContextData,
+) -> Result<Certificate, Error> {
+    let hostname = get_route_host(route)?;
+    let cert_name = format_cert_name(hostname);
+    let defaults = ctx.get_issuer_defaults(route);
+    let mut cert = Certificate::new_default(
+        &cert_name,
+        hostname,
+        &get_route_issuer(route, &defaults)?,
+        settings,
+        ctx,
+    );
+    if get_certificate_mode(route, &defaults) == Ok(CertificateMode::Dedicated) {
+        cert.metadata.name = Some(format_dedicated_cert_name(&route.name_any()));
+        cert.metadata.namespace = Some(get_namespace(route)?);
+        cert.metadata.labels = Some(BTreeMap::from([(
+            CERTIFICATE_MODE_ANNOTATION_KEY.to_owned(),
+            "dedicated".to_owned(),
+        )]));
+        cert.spec.secret_name = format_secret_name(&format!("{}-dedicated", route.name_any()));
+    }
+    let namespace = get_namespace(&cert)?;
+    let cert_api: Api<Certificate> = Api::namespaced(ctx.client.clone(), &namespace);
+    Ok(cert_api
+        .create(&PostParams::default(), &cert)
+        .instrument(api_span(
+            "create",
+            "Certificate",
+            &namespace,
+            &cert.name_any(),
+        ))
+        .await?)
+}
+
+/// Create a [`Certificate`] shared by the [`Route`]s of a certificate group, starting with a [`Route`]'s hostname.
+///
+/// The [`Certificate`] is labelled with its group, see [`CertificateGroup::labels()`].
+///
+/// ### Arguments
+///
+/// * `route` - The first [`Route`] using the [`Certificate`].
+/// * `cert_name` - The name of the [`Certificate`] (see [`select_group_certificate()`]).
+/// * `group` - The certificate group.
+/// * `settings` - The [`CertificateSettings`] of the [`Certificate`] (see [`get_certificate_settings()`]).
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the created [`Certificate`] or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let cert = create_group_certificate(&route, "shop.web-0-cert", &group, &settings, &ctx).await?;
+/// println!("Created Certificate: {}", cert);
+/// ```
+pub async fn create_group_certificate(
+    route: &Route,
+    cert_name: &str,
+    group: &CertificateGroup,
+    settings: &CertificateSettings,
     ctx: &ContextData,
-) -> Result<Certificate, kube::Error> {
-    let annotations = route.metadata.annotations.as_ref().unwrap();
-    let hostname = route.spec.host.as_ref().unwrap();
-    let cert_name = format_cert_name(&hostname);
+) -> Result<Certificate, Error> {
+    let hostname = get_route_host(route)?;
     let cert_api: Api<Certificate> =
         Api::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace);
-    let cert = Certificate::new_default(
-        &cert_name,
-        &hostname,
-        &annotations
-            .get(CLUSTER_ISSUER_ANNOTATION_KEY)
-            .unwrap()
-            .to_owned(),
-        &ctx,
+    let mut cert = Certificate::new_default(
+        cert_name,
+        hostname,
+        &get_route_issuer(route, &ctx.get_issuer_defaults(route))?,
+        settings,
+        ctx,
+    );
+    cert.spec.secret_name =
+        format_secret_name(cert_name.strip_suffix("-cert").unwrap_or(cert_name));
+    cert.metadata.labels = Some(group.labels());
+    Ok(cert_api
+        .create(&PostParams::default(), &cert)
+        .instrument(api_span(
+            "create",
+            "Certificate",
+            &ctx.cert_manager_namespace,
+            cert_name,
+        ))
+        .await?)
+}
+
+/// Get the certificate group of a [`Route`].
+///
+/// The [`CERTIFICATE_GROUP_ANNOTATION_KEY`] annotation of the [`Route`] overrides the group of its
+/// `RoutesCertificatePolicy` rule, and is scoped to the [`Route`]'s namespace (see [`CertificateGroup`]).
+/// The [`Route`]s of a group share multi-SAN [`Certificate`]s.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to get the group for.
+/// * `default` - The group of the [`Route`]'s policy rule, if any.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the group, [`None`] if the [`Route`] has its own [`Certificate`],
+/// or a [`String`] describing why the group is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let group = get_certificate_group(&route, rule.certificate_group.as_ref())?;
+/// println!("Certificate group: {:?}", group);
+/// ```
+pub fn get_certificate_group(
+    route: &Route,
+    default: Option<&String>,
+) -> Result<Option<CertificateGroup>, String> {
+    let (group, namespace) = match route.annotations().get(CERTIFICATE_GROUP_ANNOTATION_KEY) {
+        Some(group) => (group, Some(route.namespace().unwrap_or_default())),
+        None => match default {
+            Some(group) => (group, None),
+            None => return Ok(None),
+        },
+    };
+    if group.is_empty()
+        || group.len() > MAX_CERTIFICATE_GROUP_LEN
+        || group.starts_with('-')
+        || group.ends_with('-')
+        || !group
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(format!(
+            "Invalid certificate group `{}`, expected at most {} lowercase alphanumeric characters or `-`",
+            group, MAX_CERTIFICATE_GROUP_LEN
+        ));
+    }
+    Ok(Some(CertificateGroup {
+        name: group.clone(),
+        namespace,
+    }))
+}
+
+#[test]
+fn test_get_certificate_group() {
+    let mut route = Route::new_test_route("test", "test", "example.com", None, None);
+    assert_eq!(get_certificate_group(&route, None), Ok(None));
+    assert_eq!(
+        get_certificate_group(&route, Some(&"policy".to_owned())),
+        Ok(Some(CertificateGroup {
+            name: "policy".to_owned(),
+            namespace: None,
+        }))
     );
-    Ok(cert_api.create(&PostParams::default(), &cert).await?)
+    route.annotations_mut().insert(
+        CERTIFICATE_GROUP_ANNOTATION_KEY.to_owned(),
+        "web-apps".to_owned(),
+    );
+    assert_eq!(
+        get_certificate_group(&route, Some(&"policy".to_owned())),
+        Ok(Some(CertificateGroup {
+            name: "web-apps".to_owned(),
+            namespace: Some("test".to_owned()),
+        }))
+    );
+    for group in ["", "Web", "web_apps", "-web", "web-", &"a".repeat(49)] {
+        route.annotations_mut().insert(
+            CERTIFICATE_GROUP_ANNOTATION_KEY.to_owned(),
+            group.to_owned(),
+        );
+        assert!(get_certificate_group(&route, None).is_err());
+    }
+}
+
+/// Select the [`Certificate`] of a certificate group a hostname belongs to.
+///
+/// The [`Certificate`] of the group already covering the hostname is selected first,
+/// then the first one of the same `ClusterIssuer` with fewer than `max_dns_names` `dnsNames`.
+/// Otherwise a new [`Certificate`] name is allocated, in the format `prefix-index-cert`
+/// (see [`CertificateGroup::cert_name_prefix()`]).
+///
+/// ### Arguments
+///
+/// * `certs` - The [`Certificate`]s of the group.
+/// * `group` - The certificate group.
+/// * `issuer` - The `ClusterIssuer` of the hostname's [`Route`].
+/// * `hostname` - The hostname.
+/// * `max_dns_names` - The maximum number of `dnsNames` of a [`Certificate`].
+///
+/// ### Returns
+///
+/// The name of the selected [`Certificate`], and the [`Certificate`] if it already exists.
+///
+/// ### Example
+///
+/// ```rust
+/// let (cert_name, cert) = select_group_certificate(&certs, &group, "letsencrypt-prod", "app.example.com", 100);
+/// println!("Certificate: {}", cert_name);
+/// ```
+pub fn select_group_certificate<'a>(
+    certs: &'a [Certificate],
+    group: &CertificateGroup,
+    issuer: &str,
+    hostname: &str,
+    max_dns_names: usize,
+) -> (String, Option<&'a Certificate>) {
+    let same_issuer = || {
+        certs
+            .iter()
+            .filter(move |cert| cert.spec.issuer_ref.name == issuer)
+    };
+    if let Some(cert) = same_issuer()
+        .find(|cert| certificate_covers_host(cert, hostname))
+        .or_else(|| {
+            same_issuer().find(|cert| {
+                cert.spec
+                    .dns_names
+                    .as_ref()
+                    .map_or(0, |dns_names| dns_names.len())
+                    < max_dns_names
+            })
+        })
+    {
+        return (cert.name_any(), Some(cert));
+    }
+    let names = certs
+        .iter()
+        .map(|cert| cert.name_any())
+        .collect::<HashSet<_>>();
+    let prefix = group.cert_name_prefix();
+    let name = (0..)
+        .map(|index| format_cert_name(&format!("{}-{}", prefix, index)))
+        .find(|name| !names.contains(name))
+        .unwrap_or_else(|| format_cert_name(&prefix));
+    (name, None)
+}
+
+#[test]
+fn test_select_group_certificate() {
+    let cert = |name: &str, issuer: &str, dns_names: &[&str]| {
+        let mut cert = Certificate::new(
+            name,
+            CertificateSpec {
+                secret_name: name.to_owned(),
+                dns_names: Some(dns_names.iter().map(|name| name.to_string()).collect()),
+                issuer_ref: CertificateIssuerRef {
+                    name: issuer.to_owned(),
+                    kind: None,
+                    group: None,
+                },
+                is_ca: None,
+                private_key: None,
+                additional_output_formats: None,
+                common_name: None,
+                duration: None,
+                email_addresses: None,
+                encode_usages_in_request: None,
+                ip_addresses: None,
+                keystores: None,
+                literal_subject: None,
+                renew_before: None,
+                revision_history_limit: None,
+                secret_template: None,
+                subject: None,
+                uris: None,
+                usages: None,
+            },
+        );
+        cert.metadata.namespace = Some("cert-manager".to_owned());
+        cert
+    };
+    let select = |certs: &[Certificate], issuer: &str, hostname: &str, max_dns_names: usize| {
+        let group = CertificateGroup {
+            name: "web".to_owned(),
+            namespace: None,
+        };
+        let (name, cert) = select_group_certificate(certs, &group, issuer, hostname, max_dns_names);
+        (name, cert.is_some())
+    };
+    assert_eq!(
+        select(&[], "prod", "a.example.com", 2),
+        ("web-0-cert".to_owned(), false)
+    );
+    let group = CertificateGroup {
+        name: "web".to_owned(),
+        namespace: Some("shop".to_owned()),
+    };
+    assert_eq!(
+        select_group_certificate(&[], &group, "prod", "a.example.com", 2).0,
+        "shop.web-0-cert"
+    );
+
+    let certs = [
+        cert("web-0-cert", "prod", &["a.example.com", "b.example.com"]),
+        cert("web-1-cert", "staging", &["c.example.com"]),
+        cert("web-3-cert", "prod", &["d.example.com"]),
+    ];
+    assert_eq!(
+        select(&certs, "prod", "b.example.com", 2),
+        ("web-0-cert".to_owned(), true)
+    );
+    assert_eq!(
+        select(&certs, "prod", "e.example.com", 2),
+        ("web-3-cert".to_owned(), true)
+    );
+    assert_eq!(
+        select(&certs, "prod", "e.example.com", 1),
+        ("web-2-cert".to_owned(), false)
+    );
+    assert_eq!(
+        select(&certs, "staging", "c.example.com", 1),
+        ("web-1-cert".to_owned(), true)
+    );
+}
+
+/// Resolve the [`Certificate`] of a certificate group a hostname belongs to (see [`select_group_certificate()`]).
+///
+/// ### Arguments
+///
+/// * `group` - The certificate group.
+/// * `issuer` - The `ClusterIssuer` of the hostname's [`Route`].
+/// * `hostname` - The hostname.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the name of the [`Certificate`] and the [`Certificate`] if it already exists,
+/// or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let (cert_name, cert) = resolve_group_certificate(&group, "letsencrypt-prod", "app.example.com", &ctx).await?;
+/// println!("Certificate: {}", cert_name);
+/// ```
+pub async fn resolve_group_certificate(
+    group: &CertificateGroup,
+    issuer: &str,
+    hostname: &str,
+    ctx: &ContextData,
+) -> Result<(String, Option<Certificate>), Error> {
+    let certs = Api::<Certificate>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
+        .list(&ListParams::default().labels(&group.label_selector()))
+        .instrument(api_span(
+            "list",
+            "Certificate",
+            &ctx.cert_manager_namespace,
+            "",
+        ))
+        .await?
+        .items;
+    let (cert_name, cert) = select_group_certificate(
+        &certs,
+        group,
+        issuer,
+        hostname,
+        ctx.config().certificate_group_max_dns_names,
+    );
+    Ok((cert_name, cert.cloned()))
+}
+
+/// Add a hostname to the `dnsNames` of a [`Certificate`] shared by a certificate group.
+///
+/// The update is guarded by the `resourceVersion` of the [`Certificate`], so that the hostnames added
+/// concurrently by other [`Route`]s aren't lost. On a conflict the [`Certificate`] is read again and the update
+/// retried, unless it was filled up in the meantime: the conflict is then returned so that the [`Route`] is
+/// requeued and resolves its [`Certificate`] again (see [`resolve_group_certificate()`]).
+///
+/// ### Arguments
+///
+/// * `cert` - The [`Certificate`] to update.
+/// * `hostname` - The hostname to add.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the updated [`Certificate`] or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let cert = add_certificate_host(&cert, "app.example.com", &ctx).await?;
+/// println!("Updated Certificate: {}", cert);
+/// ```
+pub async fn add_certificate_host(
+    cert: &Certificate,
+    hostname: &str,
+    ctx: &ContextData,
+) -> Result<Certificate, Error> {
+    let namespace = get_namespace(cert)?;
+    let name = cert.name_any();
+    let cert_api = Api::<Certificate>::namespaced(ctx.client.clone(), &namespace);
+    let mut cert = cert.clone();
+    loop {
+        if certificate_covers_host(&cert, hostname) {
+            return Ok(cert);
+        }
+        cert.spec
+            .dns_names
+            .get_or_insert_with(Vec::new)
+            .push(hostname.to_owned());
+        match cert_api
+            .replace(&name, &PostParams::default(), &cert)
+            .instrument(api_span("replace", "Certificate", &namespace, &name))
+            .await
+        {
+            Ok(cert) => return Ok(cert),
+            Err(kube::Error::Api(response)) if response.code == 409 => {
+                cert = cert_api
+                    .get(&name)
+                    .instrument(api_span("get", "Certificate", &namespace, &name))
+                    .await?;
+                if !certificate_covers_host(&cert, hostname)
+                    && cert
+                        .spec
+                        .dns_names
+                        .as_ref()
+                        .map_or(0, |dns_names| dns_names.len())
+                        >= ctx.config().certificate_group_max_dns_names
+                {
+                    return Err(Error::Conflict(kube::Error::Api(response)));
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Check whether a [`Certificate`] is shared by a certificate group.
+pub fn is_group_certificate(cert: &Certificate) -> bool {
+    cert.labels().contains_key(CERTIFICATE_GROUP_ANNOTATION_KEY)
+}
+
+/// Check whether a [`Certificate`] shared by a certificate group may have `dnsNames` no [`Route`] uses anymore.
+///
+/// Each [`Route`] of the [`CERT_ANNOTATION_KEY`] annotation uses a single `dnsName`, so there are
+/// stale `dnsNames` at most when the [`Certificate`] has more `dnsNames` than [`Route`]s.
+pub fn may_prune_group_certificate(cert: &Certificate) -> bool {
+    let routes = cert
+        .annotations()
+        .get(CERT_ANNOTATION_KEY)
+        .map_or(0, |annotation| {
+            annotation.split(",").filter(|s| !s.is_empty()).count()
+        });
+    is_group_certificate(cert)
+        && cert
+            .spec
+            .dns_names
+            .as_ref()
+            .map_or(0, |dns_names| dns_names.len())
+            > routes
+}
+
+/// Remove the `dnsNames` no [`Route`] uses anymore from a [`Certificate`] shared by a certificate group.
+///
+/// The [`Route`]s using the [`Certificate`] are read from its [`CERT_ANNOTATION_KEY`] annotation.
+/// The [`Certificate`] and its [`Secret`] are deleted once no [`Route`] uses them, so that their slots are freed.
+/// Like in [`add_certificate_host()`], the update and the deletion are guarded by the `resourceVersion`
+/// of the [`Certificate`], and retried on a conflict with the [`Certificate`] read again.
+///
+/// ### Arguments
+///
+/// * `cert` - The [`Certificate`] to prune.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the pruned [`Certificate`], [`None`] if it was deleted, or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// if prune_group_certificate(&cert, &ctx).await?.is_none() {
+///     println!("Deleted Certificate `{}`", cert);
+/// }
+/// ```
+pub async fn prune_group_certificate(
+    cert: &Certificate,
+    ctx: &ContextData,
+) -> Result<Option<Certificate>, Error> {
+    let namespace = get_namespace(cert)?;
+    let name = cert.name_any();
+    let cert_api = Api::<Certificate>::namespaced(ctx.client.clone(), &namespace);
+    let mut cert = cert.clone();
+    loop {
+        let mut hostnames = HashSet::new();
+        for reference in cert
+            .annotations()
+            .get(CERT_ANNOTATION_KEY)
+            .map(|annotation| annotation.split(",").collect::<Vec<_>>())
+            .unwrap_or_default()
+        {
+            if let Some((route_namespace, name)) = reference.split_once("/") {
+                if let Some(host) = Api::<Route>::namespaced(ctx.client.clone(), route_namespace)
+                    .get_opt(name)
+                    .instrument(api_span("get", "Route", route_namespace, name))
+                    .await?
+                    .and_then(|route| route.spec.host)
+                {
+                    hostnames.insert(host.to_lowercase());
+                }
+            }
+        }
+        let dns_names = cert.spec.dns_names.clone().unwrap_or_default();
+        let used = dns_names
+            .iter()
+            .filter(|dns_name| hostnames.contains(&dns_name.to_lowercase()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let result = if used.is_empty() {
+            delete_certificate(&cert, ctx).await.map(|_| None)
+        } else if used.len() == dns_names.len() {
+            return Ok(Some(cert));
+        } else {
+            cert.spec.dns_names = Some(used);
+            cert_api
+                .replace(&name, &PostParams::default(), &cert)
+                .instrument(api_span("replace", "Certificate", &namespace, &name))
+                .await
+                .map(Some)
+                .map_err(Error::from)
+        };
+        match result {
+            Err(Error::Conflict(_)) => {
+                match cert_api
+                    .get_opt(&name)
+                    .instrument(api_span("get", "Certificate", &namespace, &name))
+                    .await?
+                {
+                    Some(fresh) => cert = fresh,
+                    None => return Ok(None),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Check whether a [`Certificate`] is the dedicated [`Certificate`] of a [`Route`].
+pub fn is_dedicated_certificate(cert: &Certificate) -> bool {
+    cert.labels().contains_key(CERTIFICATE_MODE_ANNOTATION_KEY)
+}
+
+/// Delete a [`Certificate`] no [`Route`] uses anymore, along with its [`Secret`].
+///
+/// The deletion is guarded by the `resourceVersion` of the [`Certificate`], so that a [`Certificate`]
+/// updated in the meantime, for instance by a [`Route`] joining it, is kept and an [`Error::Conflict`] returned.
+///
+/// ### Arguments
+///
+/// * `cert` - The [`Certificate`] to delete.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// delete_certificate(&cert, &ctx).await?;
+/// ```
+pub async fn delete_certificate(cert: &Certificate, ctx: &ContextData) -> Result<(), Error> {
+    let namespace = get_namespace(cert)?;
+    let params = DeleteParams {
+        preconditions: Some(Preconditions {
+            resource_version: cert.resource_version(),
+            uid: None,
+        }),
+        ..Default::default()
+    };
+    let _ = Api::<Certificate>::namespaced(ctx.client.clone(), &namespace)
+        .delete(&cert.name_any(), &params)
+        .instrument(api_span(
+            "delete",
+            "Certificate",
+            &namespace,
+            &cert.name_any(),
+        ))
+        .await?;
+    match Api::<Secret>::namespaced(ctx.client.clone(), &namespace)
+        .delete(&cert.spec.secret_name, &DeleteParams::default())
+        .instrument(api_span(
+            "delete",
+            "Secret",
+            &namespace,
+            &cert.spec.secret_name,
+        ))
+        .await
+    {
+        Err(kube::Error::Api(response)) if response.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Parse a [`CertificatePrivateKeyAlgorithm`] from its string representation.
+///
+/// The accepted values are `RSA`, `ECDSA` and `Ed25519` (case insensitive).
+///
+/// ### Arguments
+///
+/// * `value` - The value to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the [`CertificatePrivateKeyAlgorithm`], or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let algorithm = parse_private_key_algorithm("ECDSA");
+/// println!("{:?}", algorithm); // Some(Ecdsa)
+/// ```
+pub fn parse_private_key_algorithm(value: &str) -> Option<CertificatePrivateKeyAlgorithm> {
+    match value.to_lowercase().as_str() {
+        "rsa" => Some(CertificatePrivateKeyAlgorithm::Rsa),
+        "ecdsa" => Some(CertificatePrivateKeyAlgorithm::Ecdsa),
+        "ed25519" => Some(CertificatePrivateKeyAlgorithm::Ed25519),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_private_key_algorithm() {
+    assert!(matches!(
+        parse_private_key_algorithm("RSA"),
+        Some(CertificatePrivateKeyAlgorithm::Rsa)
+    ));
+    assert!(matches!(
+        parse_private_key_algorithm("ecdsa"),
+        Some(CertificatePrivateKeyAlgorithm::Ecdsa)
+    ));
+    assert!(matches!(
+        parse_private_key_algorithm("Ed25519"),
+        Some(CertificatePrivateKeyAlgorithm::Ed25519)
+    ));
+    assert!(parse_private_key_algorithm("DSA").is_none());
+}
+
+/// Get the [`CertificateSettings`] of the [`Certificate`] created for a [`Route`].
+///
+/// The [`CERT_DURATION_ANNOTATION_KEY`], [`CERT_RENEW_BEFORE_ANNOTATION_KEY`],
+/// [`CERT_PRIVATE_KEY_ALGORITHM_ANNOTATION_KEY`] and [`CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY`] annotations
+/// of the [`Route`] override the settings of its `RoutesCertificatePolicy` rule.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to get the [`CertificateSettings`] for.
+/// * `defaults` - The [`CertificateSettings`] of the [`Route`]'s policy rule, if any.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`CertificateSettings`] or a [`String`] describing why a setting is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let settings = get_certificate_settings(&route, rule.and_then(|rule| rule.certificate).as_ref())?;
+/// let cert = create_certificate(&route, &settings, &ctx).await?;
+/// ```
+pub fn get_certificate_settings(
+    route: &Route,
+    defaults: Option<&CertificateSettings>,
+) -> Result<CertificateSettings, String> {
+    let mut settings = defaults.cloned().unwrap_or_default();
+    let annotations = route.annotations();
+    if let Some(value) = annotations.get(CERT_DURATION_ANNOTATION_KEY) {
+        settings.duration = Some(value.clone());
+    }
+    if let Some(value) = annotations.get(CERT_RENEW_BEFORE_ANNOTATION_KEY) {
+        settings.renew_before = Some(value.clone());
+    }
+    if let Some(value) = annotations.get(CERT_PRIVATE_KEY_ALGORITHM_ANNOTATION_KEY) {
+        settings.private_key_algorithm = Some(value.clone());
+    }
+    if let Some(value) = annotations.get(CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY) {
+        settings.private_key_size = Some(value.parse::<i64>().map_err(|_| {
+            format!(
+                "Invalid `{}` annotation value `{}`, expected a number of bits",
+                CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY, value
+            )
+        })?);
+    }
+
+    let duration = match &settings.duration {
+        Some(value) => Some(parse_duration(value).ok_or(format!(
+            "Invalid duration `{}`, expected e.g. `2160h`",
+            value
+        ))?),
+        None => None,
+    };
+    if let Some(value) = &settings.renew_before {
+        let renew_before = parse_duration(value).ok_or(format!(
+            "Invalid renew before `{}`, expected e.g. `360h`",
+            value
+        ))?;
+        if duration.is_some_and(|duration| renew_before >= duration) {
+            return Err(format!(
+                "Renew before `{}` must be shorter than the duration `{}`",
+                value,
+                settings.duration.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+    if let Some(value) = &settings.private_key_algorithm {
+        parse_private_key_algorithm(value).ok_or(format!(
+            "Invalid private key algorithm `{}`, expected one of `RSA`, `ECDSA`, `Ed25519`",
+            value
+        ))?;
+    }
+    if let Some(size) = settings.private_key_size {
+        if size <= 0 {
+            return Err(format!("Invalid private key size `{}`", size));
+        }
+    }
+    Ok(settings)
+}
+
+#[test]
+fn test_get_certificate_settings() {
+    let route = Route::new_test_route("test", "test", "example.com", None, None);
+    assert_eq!(
+        get_certificate_settings(&route, None),
+        Ok(CertificateSettings::default())
+    );
+
+    let policy = CertificateSettings {
+        duration: Some("2160h".to_owned()),
+        renew_before: None,
+        private_key_algorithm: Some("ECDSA".to_owned()),
+        private_key_size: Some(256),
+    };
+    assert_eq!(
+        get_certificate_settings(&route, Some(&policy)),
+        Ok(policy.clone())
+    );
+
+    let mut route = route;
+    route
+        .annotations_mut()
+        .insert(CERT_DURATION_ANNOTATION_KEY.to_owned(), "720h".to_owned());
+    route.annotations_mut().insert(
+        CERT_RENEW_BEFORE_ANNOTATION_KEY.to_owned(),
+        "240h".to_owned(),
+    );
+    assert_eq!(
+        get_certificate_settings(&route, Some(&policy)),
+        Ok(CertificateSettings {
+            duration: Some("720h".to_owned()),
+            renew_before: Some("240h".to_owned()),
+            ..policy.clone()
+        })
+    );
+
+    route.annotations_mut().insert(
+        CERT_RENEW_BEFORE_ANNOTATION_KEY.to_owned(),
+        "720h".to_owned(),
+    );
+    assert!(get_certificate_settings(&route, Some(&policy)).is_err());
+
+    for (key, value) in [
+        (CERT_DURATION_ANNOTATION_KEY, "90d"),
+        (CERT_PRIVATE_KEY_ALGORITHM_ANNOTATION_KEY, "DSA"),
+        (CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY, "big"),
+        (CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY, "-1"),
+    ] {
+        let mut route = Route::new_test_route("test", "test", "example.com", None, None);
+        route
+            .annotations_mut()
+            .insert(key.to_owned(), value.to_owned());
+        assert!(get_certificate_settings(&route, None).is_err());
+    }
+}
+
+/// Get a [`Certificate`].
+///
+/// ### Arguments
+///
+/// * `namespace` - The namespace of the [`Certificate`].
+/// * `cert_name` - The name of the [`Certificate`].
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`Certificate`] or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let cert = get_certificate(&namespace, &cert_name, &ctx).await?;
+/// println!("Certificate: {}", cert);
+/// ```
+pub async fn get_certificate(
+    namespace: &str,
+    cert_name: &str,
+    ctx: &ContextData,
+) -> Result<Certificate, Error> {
+    Ok(
+        Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
+            .get(cert_name)
+            .instrument(api_span("get", "Certificate", namespace, cert_name))
+            .await?,
+    )
 }
 
 /// Check whether a [`Certificate`] exists.
 ///
 /// ### Arguments
 ///
+/// * `namespace` - The namespace of the [`Certificate`] to check.
 /// * `cert_name` - The name of the [`Certificate`] to check.
 /// * `ctx` - The [`ContextData`].
 ///
@@ -193,56 +1123,214 @@ pub async fn create_certificate(
 /// ### Example
 ///
 /// ```rust
-/// let exists = certificate_exists(&cert_name, &ctx).await;
+/// let exists = certificate_exists(&namespace, &cert_name, &ctx).await;
 /// println!("Certificate `{}` exists: {}", &cert_name, exists);
 /// ```
-pub async fn certificate_exists(cert_name: &str, ctx: &ContextData) -> bool {
-    match Api::<Certificate>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
+pub async fn certificate_exists(namespace: &str, cert_name: &str, ctx: &ContextData) -> bool {
+    Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
         .get(cert_name)
+        .instrument(api_span("get", "Certificate", namespace, cert_name))
         .await
-    {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+        .is_ok()
 }
 
 /// Check whether a [`Certificate`] is annotated with a [`Route`].
 ///
 /// ### Arguments
 ///
+/// * `namespace` - The namespace of the [`Certificate`] to check.
 /// * `cert_name` - The name of the [`Certificate`] to check.
 /// * `route` - The [`Route`] to check in the annotation.
 /// * `ctx` - The [`ContextData`].
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing a [`bool`] indicating whether the [`Certificate`] is annotated with the [`Route`] or a [`kube::Error`].
+/// A [`Result`] containing a [`bool`] indicating whether the [`Certificate`] is annotated with the [`Route`] or an [`Error`].
 ///
 /// ### Example
 ///
 /// ```rust
-/// let is_annotated = is_cert_annotated(&cert_name, &route, &ctx).await?;
+/// let is_annotated = is_cert_annotated(&namespace, &cert_name, &route, &ctx).await?;
 /// println!("Certificate `{}` is annotated with Route `{}`: {}", &cert_name, &route, is_annotated);
 /// ```
 pub async fn is_cert_annotated(
+    namespace: &str,
     cert_name: &str,
     route: &Route,
     ctx: &ContextData,
-) -> Result<bool, kube::Error> {
-    let cert = Api::<Certificate>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
+) -> Result<bool, Error> {
+    let cert = Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
         .get(cert_name)
+        .instrument(api_span("get", "Certificate", namespace, cert_name))
         .await?;
-    match cert.metadata.annotations {
-        Some(annotations) => {
-            if let Some(annotation) = annotations.get(CERT_ANNOTATION_KEY) {
-                Ok(
-                    HashSet::<String>::from_iter(annotation.split(",").map(|s| s.to_owned()))
-                        .contains(&route.to_string()),
-                )
-            } else {
-                Ok(false)
-            }
+    Ok(is_cert_used_by(&cert, route))
+}
+
+/// Check whether a [`Certificate`]'s [`CERT_ANNOTATION_KEY`] annotation contains a [`Route`].
+fn is_cert_used_by(cert: &Certificate, route: &Route) -> bool {
+    match cert.annotations().get(CERT_ANNOTATION_KEY) {
+        Some(annotation) => {
+            HashSet::<String>::from_iter(annotation.split(",").map(|s| s.to_owned()))
+                .contains(&route.to_string())
+        }
+        None => false,
+    }
+}
+
+/// Check whether a [`Certificate`]'s `dnsNames` cover a hostname.
+///
+/// A wildcard `dnsName` such as `*.example.com` covers a single label, e.g. `hello.example.com`
+/// but neither `example.com` nor `hello.world.example.com`.
+///
+/// ### Arguments
+///
+/// * `cert` - The [`Certificate`] to check.
+/// * `hostname` - The hostname to look for.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the [`Certificate`] covers the hostname.
+///
+/// ### Example
+///
+/// ```rust
+/// let covered = certificate_covers_host(&cert, "hello.example.com");
+/// println!("Host covered: {}", covered);
+/// ```
+pub fn certificate_covers_host(cert: &Certificate, hostname: &str) -> bool {
+    let hostname = hostname.to_lowercase();
+    cert.spec
+        .dns_names
+        .as_ref()
+        .map(|dns_names| {
+            dns_names.iter().any(|dns_name| {
+                let dns_name = dns_name.to_lowercase();
+                match dns_name.strip_prefix("*.") {
+                    Some(domain) => match hostname.split_once(".") {
+                        Some((label, rest)) => !label.is_empty() && rest == domain,
+                        None => false,
+                    },
+                    None => dns_name == hostname,
+                }
+            })
+        })
+        .unwrap_or(false)
+}
+
+#[test]
+fn test_certificate_covers_host() {
+    let mut cert = Certificate::new(
+        "test",
+        CertificateSpec {
+            secret_name: "test".to_owned(),
+            dns_names: None,
+            issuer_ref: CertificateIssuerRef {
+                name: "test".to_owned(),
+                kind: None,
+                group: None,
+            },
+            is_ca: None,
+            private_key: None,
+            additional_output_formats: None,
+            common_name: None,
+            duration: None,
+            email_addresses: None,
+            encode_usages_in_request: None,
+            ip_addresses: None,
+            keystores: None,
+            literal_subject: None,
+            renew_before: None,
+            revision_history_limit: None,
+            secret_template: None,
+            subject: None,
+            uris: None,
+            usages: None,
+        },
+    );
+    assert!(!certificate_covers_host(&cert, "example.com"));
+    cert.spec.dns_names = Some(vec![
+        "example.com".to_owned(),
+        "*.apps.example.com".to_owned(),
+    ]);
+    assert!(certificate_covers_host(&cert, "example.com"));
+    assert!(certificate_covers_host(&cert, "Example.COM"));
+    assert!(certificate_covers_host(&cert, "hello.apps.example.com"));
+    assert!(!certificate_covers_host(&cert, "apps.example.com"));
+    assert!(!certificate_covers_host(
+        &cert,
+        "hello.world.apps.example.com"
+    ));
+    assert!(!certificate_covers_host(&cert, "hello.example.com"));
+}
+
+/// Release the [`Certificate`]s a [`Route`] doesn't use anymore.
+///
+/// The [`Route`] is removed from the [`CERT_ANNOTATION_KEY`] annotation of every [`Certificate`]
+/// of the cert-manager namespace and of the [`Route`]'s namespace, except the one it still uses.
+/// The [`Certificate`]s annotated with the [`Route`] are found in the caches of the [`ContextData`], so that
+/// a [`Route`] which doesn't release any [`Certificate`] costs no API call.
+/// The [`Certificate`]s shared by a certificate group are then pruned (see [`prune_group_certificate()`]),
+/// and the dedicated [`Certificate`]s no [`Route`] uses anymore are deleted.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] releasing the [`Certificate`]s.
+/// * `keep` - The namespace and name of the [`Certificate`] the [`Route`] still uses, if any.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the released [`Certificate`]s or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let released = release_certificates(&route, None, &ctx).await?;
+/// println!("Released {} Certificates", released.len());
+/// ```
+pub async fn release_certificates(
+    route: &Route,
+    keep: Option<(&str, &str)>,
+    ctx: &ContextData,
+) -> Result<Vec<Certificate>, Error> {
+    let mut namespaces = vec![ctx.cert_manager_namespace.clone()];
+    if let Some(namespace) = route.namespace() {
+        if namespace != ctx.cert_manager_namespace {
+            namespaces.push(namespace);
+        }
+    }
+    let mut released = vec![];
+    for store in &ctx.certificates {
+        let _ = store.wait_until_ready().await;
+    }
+    let used = ctx
+        .certificates
+        .iter()
+        .flat_map(|store| store.state())
+        .filter(|cert| {
+            cert.namespace()
+                .is_some_and(|namespace| namespaces.contains(&namespace))
+                && is_cert_used_by(cert, route)
+        })
+        .collect::<Vec<_>>();
+    for cert in used {
+        let namespace = cert.namespace().unwrap_or_default();
+        let name = cert.name_any();
+        if keep == Some((namespace.as_str(), name.as_str())) {
+            continue;
+        }
+        let cert = annotate_cert(&namespace, &name, route, ctx, false).await?;
+        if is_group_certificate(&cert) {
+            let _ = prune_group_certificate(&cert, ctx).await?;
+        } else if is_dedicated_certificate(&cert)
+            && cert
+                .annotations()
+                .get(CERT_ANNOTATION_KEY)
+                .is_none_or(|annotation| annotation.is_empty())
+        {
+            delete_certificate(&cert, ctx).await?;
         }
-        None => Ok(false),
+        released.push(cert);
     }
+    Ok(released)
 }
@@ -0,0 +1,915 @@
+use crate::budget::IssuanceBudget;
+use crate::crd::route::{RouteTlsInsecureEdgeTerminationPolicy, RouteTlsTermination};
+use crate::logging::{parse_log_format, LogFormat};
+use crate::route::{
+    parse_bool, parse_insecure_edge_termination_policy, parse_termination, validate_tls_settings,
+    RouteTlsSettings,
+};
+use crate::tools::parse_interval;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+pub const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+const CONFIG_MAP_ENV: &str = "CONFIG_MAP";
+pub const CONFIG_MAP_KEY: &str = "config.yaml";
+pub const CERT_MANAGER_NAMESPACE_ENV: &str = "CERT_MANAGER_NAMESPACE";
+const WATCH_NAMESPACES_ENV: &str = "WATCH_NAMESPACES";
+const NAMESPACE_SCOPED_ENV: &str = "NAMESPACE_SCOPED";
+const DEFAULT_TERMINATION_ENV: &str = "DEFAULT_ROUTE_TERMINATION";
+const DEFAULT_INSECURE_POLICY_ENV: &str = "DEFAULT_ROUTE_INSECURE_POLICY";
+const DEFAULT_EXTERNAL_CERTIFICATE_ENV: &str = "DEFAULT_ROUTE_EXTERNAL_CERTIFICATE";
+const ROUTER_SERVICE_ACCOUNT_NAMESPACE_ENV: &str = "ROUTER_SERVICE_ACCOUNT_NAMESPACE";
+const ROUTER_SERVICE_ACCOUNT_NAME_ENV: &str = "ROUTER_SERVICE_ACCOUNT_NAME";
+const TLS_ACME_ISSUER_ENV: &str = "TLS_ACME_CLUSTER_ISSUER";
+const RESTRICT_ISSUERS_ENV: &str = "RESTRICT_CLUSTER_ISSUERS";
+const ALLOWED_ISSUERS_ENV: &str = "ALLOWED_CLUSTER_ISSUERS";
+const ISSUER_AUDIT_INTERVAL_ENV: &str = "ISSUER_AUDIT_INTERVAL";
+const ISSUANCE_BUDGET_ENV: &str = "ISSUANCE_BUDGET";
+const ISSUANCE_BUDGET_WINDOW_ENV: &str = "ISSUANCE_BUDGET_WINDOW";
+const CERTIFICATE_GROUP_MAX_DNS_NAMES_ENV: &str = "CERTIFICATE_GROUP_MAX_DNS_NAMES";
+const REQUEUE_INTERVAL_ENV: &str = "REQUEUE_INTERVAL";
+const FINALIZER_ENV: &str = "FINALIZER";
+const LEADER_ELECTION_ENV: &str = "LEADER_ELECTION";
+const CONTROLLER_POD_ENV: &str = "CONTROLLER_POD_NAME";
+const CONTROLLER_SERVICE_ACCOUNT_ENV: &str = "CONTROLLER_SERVICE_ACCOUNT_NAME";
+const LIVENESS_WINDOW_ENV: &str = "LIVENESS_WINDOW";
+const SHUTDOWN_DEADLINE_ENV: &str = "SHUTDOWN_DEADLINE";
+const WEBHOOK_PORT_ENV: &str = "WEBHOOK_PORT";
+const WEBHOOK_TLS_CERT_FILE_ENV: &str = "WEBHOOK_TLS_CERT_FILE";
+const WEBHOOK_TLS_KEY_FILE_ENV: &str = "WEBHOOK_TLS_KEY_FILE";
+const METRICS_PORT_ENV: &str = "METRICS_PORT";
+const LOG_LEVEL_ENV: &str = "LOG_LEVEL";
+const LOG_FORMAT_ENV: &str = "LOG_FORMAT";
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const DEFAULT_CERT_MANAGER_NAMESPACE: &str = "cert-manager";
+pub const DEFAULT_CERTIFICATE_GROUP_MAX_DNS_NAMES: usize = 100;
+pub const DEFAULT_FINALIZER: &str = "kubernetes";
+pub const DEFAULT_ROUTER_SERVICE_ACCOUNT_NAMESPACE: &str = "openshift-ingress";
+pub const DEFAULT_ROUTER_SERVICE_ACCOUNT_NAME: &str = "router";
+
+/// The settings of the controller.
+///
+/// They are read from the YAML file passed with `--config`, if any, then overridden by the environment variables and
+/// the command-line flags of the [`Args`]. The keys of the file are the names of the fields.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The namespace of cert-manager, where the `Certificate`s are created.
+    pub cert_manager_namespace: String,
+    /// The namespaces whose `Route`s are reconciled, all of them when empty.
+    pub watch_namespaces: Vec<String>,
+    /// Whether the controller only watches the `watch_namespaces` and the cert-manager namespace, instead of the
+    /// whole cluster, so that it can run with namespaced permissions.
+    pub namespace_scoped: bool,
+    /// The default termination of the `Route`s.
+    pub route_termination: RouteTlsTermination,
+    /// The default insecure edge termination policy of the `Route`s.
+    pub route_insecure_policy: RouteTlsInsecureEdgeTerminationPolicy,
+    /// Whether the `Route`s reference a mirror of their `Secret` through `spec.tls.externalCertificate` by default.
+    pub route_external_certificate: bool,
+    /// The namespace of the router's `ServiceAccount`, which is granted access to the `Secret`s of the external
+    /// certificates.
+    pub router_service_account_namespace: String,
+    /// The name of the router's `ServiceAccount`.
+    pub router_service_account_name: String,
+    /// The `ClusterIssuer` requested by the `kubernetes.io/tls-acme` annotation.
+    pub tls_acme_cluster_issuer: Option<String>,
+    /// Whether the `ClusterIssuer`s every `Namespace` may request are restricted to the `allowed_cluster_issuers`.
+    pub restrict_cluster_issuers: bool,
+    /// The `ClusterIssuer`s every `Namespace` may request when `restrict_cluster_issuers` is set.
+    pub allowed_cluster_issuers: Vec<String>,
+    /// The interval between the audits of the `ClusterIssuer`s requested by the `Route`s.
+    #[serde(with = "duration")]
+    pub issuer_audit_interval: Duration,
+    /// The number of `Certificate`s created per registered domain and `ClusterIssuer` over the
+    /// `issuance_budget_window`, unlimited when `0`.
+    pub issuance_budget: usize,
+    /// The sliding window of the `issuance_budget`.
+    #[serde(with = "duration")]
+    pub issuance_budget_window: Duration,
+    /// The maximum number of `dnsNames` of the `Certificate`s shared by a certificate group.
+    pub certificate_group_max_dns_names: usize,
+    /// The interval between the reconciles of an up-to-date `Route`.
+    #[serde(with = "duration")]
+    pub requeue_interval: Duration,
+    /// The finalizer added to the managed `Route`s.
+    pub finalizer: String,
+    /// Whether the replicas elect a leader to reconcile the `Route`s.
+    pub leader_election: bool,
+    /// The name of the controller's `Pod`, its identity in the leader election and the events.
+    pub pod_name: Option<String>,
+    /// The name of the controller's `ServiceAccount`, in the cert-manager namespace,
+    /// whose updates of the `Route`s the admission webhook always allows.
+    pub service_account_name: Option<String>,
+    /// The liveness probe fails when no watch event was received and no `Route` was reconciled within this window.
+    #[serde(with = "duration")]
+    pub liveness_window: Duration,
+    /// The time the in-flight reconciles get to finish on shutdown.
+    #[serde(with = "duration")]
+    pub shutdown_deadline: Duration,
+    /// The port of the admission webhook.
+    pub webhook_port: u16,
+    /// The certificate of the admission webhook, which is only served along with a key.
+    pub webhook_tls_cert_file: Option<String>,
+    /// The key of the admission webhook.
+    pub webhook_tls_key_file: Option<String>,
+    /// The port of the metrics and probes.
+    pub metrics_port: u16,
+    /// The levels of the logs, such as `info` or `warn,cert_manager_routes_controller=debug`.
+    pub log_level: String,
+    /// The format of the logs.
+    pub log_format: LogFormat,
+    /// The OTLP/HTTP collector the traces are exported to.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let tls_settings = RouteTlsSettings::default();
+        Self {
+            cert_manager_namespace: DEFAULT_CERT_MANAGER_NAMESPACE.to_owned(),
+            watch_namespaces: vec![],
+            namespace_scoped: false,
+            route_termination: tls_settings.termination,
+            route_insecure_policy: tls_settings
+                .insecure_edge_termination_policy
+                .unwrap_or(RouteTlsInsecureEdgeTerminationPolicy::Redirect),
+            route_external_certificate: tls_settings.external_certificate,
+            router_service_account_namespace: DEFAULT_ROUTER_SERVICE_ACCOUNT_NAMESPACE.to_owned(),
+            router_service_account_name: DEFAULT_ROUTER_SERVICE_ACCOUNT_NAME.to_owned(),
+            tls_acme_cluster_issuer: None,
+            restrict_cluster_issuers: false,
+            allowed_cluster_issuers: vec![],
+            issuer_audit_interval: Duration::from_secs(3600),
+            issuance_budget: 0,
+            issuance_budget_window: Duration::from_secs(7 * 24 * 3600),
+            certificate_group_max_dns_names: DEFAULT_CERTIFICATE_GROUP_MAX_DNS_NAMES,
+            requeue_interval: Duration::from_secs(3600),
+            finalizer: DEFAULT_FINALIZER.to_owned(),
+            leader_election: false,
+            pod_name: None,
+            service_account_name: None,
+            liveness_window: Duration::from_secs(2 * 3600),
+            shutdown_deadline: Duration::from_secs(25),
+            webhook_port: 8443,
+            webhook_tls_cert_file: None,
+            webhook_tls_key_file: None,
+            metrics_port: 8080,
+            log_level: "info".to_owned(),
+            log_format: LogFormat::Text,
+            otlp_endpoint: None,
+        }
+    }
+}
+
+impl Config {
+    /// The default [`RouteTlsSettings`] of the `Route`s.
+    pub fn tls_settings(&self) -> RouteTlsSettings {
+        RouteTlsSettings {
+            termination: self.route_termination.clone(),
+            insecure_edge_termination_policy: Some(self.route_insecure_policy.clone()),
+            external_certificate: self.route_external_certificate,
+        }
+    }
+
+    /// The `ClusterIssuer`s every `Namespace` may request, or [`None`] if they aren't restricted.
+    pub fn allowed_issuers(&self) -> Option<HashSet<String>> {
+        self.restrict_cluster_issuers
+            .then(|| self.allowed_cluster_issuers.iter().cloned().collect())
+    }
+
+    /// The [`IssuanceBudget`], or [`None`] if the creations of `Certificate`s aren't limited.
+    pub fn issuance_budget(&self) -> Option<IssuanceBudget> {
+        (self.issuance_budget > 0).then_some(IssuanceBudget {
+            limit: self.issuance_budget,
+            window: self.issuance_budget_window,
+        })
+    }
+
+    /// The scopes of the watches of the controller: each of the `watch_namespaces` when it is namespace-scoped,
+    /// or [`None`] for the whole cluster.
+    pub fn watch_scopes(&self) -> Vec<Option<String>> {
+        match self.namespace_scoped {
+            true => self.watch_namespaces.iter().cloned().map(Some).collect(),
+            false => vec![None],
+        }
+    }
+
+    /// The scopes of the watches of the `Certificate`s: the cert-manager namespace, which holds the shared
+    /// `Certificate`s, and each of the `watch_namespaces` when it is namespace-scoped, or [`None`] for the whole cluster.
+    pub fn certificate_scopes(&self) -> Vec<Option<String>> {
+        match self.namespace_scoped {
+            true => {
+                let mut namespaces = vec![self.cert_manager_namespace.clone()];
+                for namespace in &self.watch_namespaces {
+                    if !namespaces.contains(namespace) {
+                        namespaces.push(namespace.clone());
+                    }
+                }
+                namespaces.into_iter().map(Some).collect()
+            }
+            false => vec![None],
+        }
+    }
+
+    /// The identity of the replica in the leader election, or [`None`] if the leader election is disabled.
+    pub fn leader_identity(&self) -> Option<String> {
+        self.leader_election
+            .then(|| self.pod_name.clone())
+            .flatten()
+    }
+
+    /// Check that the settings are consistent.
+    ///
+    /// ### Returns
+    ///
+    /// A [`Result`] containing `()` or the reason the settings are invalid.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// config.validate()?;
+    /// ```
+    pub fn validate(&self) -> Result<(), String> {
+        validate_tls_settings(&self.tls_settings())?;
+        if self.namespace_scoped && self.watch_namespaces.is_empty() {
+            return Err("`watch_namespaces` must be set when `namespace_scoped` is".to_owned());
+        }
+        if self.route_insecure_policy == RouteTlsInsecureEdgeTerminationPolicy::KopiumEmpty {
+            return Err(
+                "`route_insecure_policy` must be one of `Allow`, `None`, `Redirect`".to_owned(),
+            );
+        }
+        for (name, duration) in [
+            ("issuer_audit_interval", self.issuer_audit_interval),
+            ("issuance_budget_window", self.issuance_budget_window),
+            ("requeue_interval", self.requeue_interval),
+            ("liveness_window", self.liveness_window),
+            ("shutdown_deadline", self.shutdown_deadline),
+        ] {
+            if duration.is_zero() {
+                return Err(format!("`{}` must be a positive duration", name));
+            }
+        }
+        if self.certificate_group_max_dns_names == 0 {
+            return Err("`certificate_group_max_dns_names` must be positive".to_owned());
+        }
+        if self.finalizer.is_empty() {
+            return Err("`finalizer` must be set".to_owned());
+        }
+        if self.router_service_account_namespace.is_empty()
+            || self.router_service_account_name.is_empty()
+        {
+            return Err(
+                "`router_service_account_namespace` and `router_service_account_name` must be set"
+                    .to_owned(),
+            );
+        }
+        if self.leader_election && self.pod_name.is_none() {
+            return Err("`pod_name` must be set to take part in the leader election".to_owned());
+        }
+        if self.webhook_tls_cert_file.is_some() != self.webhook_tls_key_file.is_some() {
+            return Err(
+                "`webhook_tls_cert_file` and `webhook_tls_key_file` must be set together"
+                    .to_owned(),
+            );
+        }
+        EnvFilter::try_new(&self.log_level)
+            .map_err(|e| format!("`log_level` `{}` is invalid: {}", self.log_level, e))?;
+        Ok(())
+    }
+
+    /// Apply the settings of a new [`Config`] that can change while the controller runs.
+    ///
+    /// The defaults of the `Route`s, the `ClusterIssuer` restrictions, the watched namespaces and the intervals are
+    /// reloaded, while the other settings keep their current value until the controller restarts.
+    /// The watched namespaces of a namespace-scoped controller, which has a watch per namespace, are kept too.
+    ///
+    /// ### Arguments
+    ///
+    /// * `new` - The new [`Config`].
+    ///
+    /// ### Returns
+    ///
+    /// A tuple containing the reloaded [`Config`] and the names of the settings that only change on restart.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// let (config, restart) = ctx.config().reload(&new);
+    /// println!("{:?}", restart); // ["metrics_port"]
+    /// ```
+    pub fn reload(&self, new: &Config) -> (Config, Vec<String>) {
+        let reloaded = Config {
+            watch_namespaces: match self.namespace_scoped {
+                true => self.watch_namespaces.clone(),
+                false => new.watch_namespaces.clone(),
+            },
+            route_termination: new.route_termination.clone(),
+            route_insecure_policy: new.route_insecure_policy.clone(),
+            route_external_certificate: new.route_external_certificate,
+            tls_acme_cluster_issuer: new.tls_acme_cluster_issuer.clone(),
+            restrict_cluster_issuers: new.restrict_cluster_issuers,
+            allowed_cluster_issuers: new.allowed_cluster_issuers.clone(),
+            issuer_audit_interval: new.issuer_audit_interval,
+            certificate_group_max_dns_names: new.certificate_group_max_dns_names,
+            requeue_interval: new.requeue_interval,
+            ..self.clone()
+        };
+        let restart = reloaded.get_changed_settings(new);
+        (reloaded, restart)
+    }
+
+    /// Get the names of the settings that differ between two [`Config`]s.
+    ///
+    /// ### Arguments
+    ///
+    /// * `other` - The [`Config`] to compare with.
+    ///
+    /// ### Returns
+    ///
+    /// The names of the settings that differ, in the order of the [`Config`] fields.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// let changed = ctx.config().get_changed_settings(&config);
+    /// println!("{:?}", changed); // ["requeue_interval"]
+    /// ```
+    pub fn get_changed_settings(&self, other: &Config) -> Vec<String> {
+        match (serde_json::to_value(self), serde_json::to_value(other)) {
+            (Ok(serde_json::Value::Object(current)), Ok(serde_json::Value::Object(other))) => {
+                current
+                    .into_iter()
+                    .filter(|(name, value)| other.get(name) != Some(value))
+                    .map(|(name, _)| name)
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[test]
+fn test_get_changed_settings() {
+    let current = Config::default();
+    assert!(current.get_changed_settings(&current).is_empty());
+    let other = Config {
+        requeue_interval: Duration::from_secs(600),
+        tls_acme_cluster_issuer: Some("letsencrypt-prod".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(
+        current.get_changed_settings(&other),
+        vec!["tls_acme_cluster_issuer", "requeue_interval"]
+    );
+}
+
+#[test]
+fn test_config_reload() {
+    let current = Config::default();
+    let new = Config {
+        requeue_interval: Duration::from_secs(600),
+        tls_acme_cluster_issuer: Some("letsencrypt-prod".to_owned()),
+        metrics_port: 9090,
+        finalizer: "routes.cert-manager.io/finalizer".to_owned(),
+        ..Default::default()
+    };
+    let (reloaded, restart) = current.reload(&new);
+    assert_eq!(reloaded.requeue_interval, Duration::from_secs(600));
+    assert_eq!(
+        reloaded.tls_acme_cluster_issuer,
+        Some("letsencrypt-prod".to_owned())
+    );
+    assert_eq!(reloaded.metrics_port, 8080);
+    assert_eq!(reloaded.finalizer, DEFAULT_FINALIZER);
+    assert_eq!(restart, vec!["finalizer", "metrics_port"]);
+    assert_eq!(current.reload(&current), (current.clone(), vec![]));
+
+    let scoped = Config {
+        watch_namespaces: vec!["team-a".to_owned()],
+        namespace_scoped: true,
+        ..Default::default()
+    };
+    let new = Config {
+        watch_namespaces: vec!["team-a".to_owned(), "team-b".to_owned()],
+        ..scoped.clone()
+    };
+    assert_eq!(
+        scoped.reload(&new),
+        (scoped.clone(), vec!["watch_namespaces".to_owned()])
+    );
+}
+
+#[test]
+fn test_config_watch_scopes() {
+    let config = Config {
+        watch_namespaces: vec!["team-a".to_owned(), "team-b".to_owned()],
+        ..Default::default()
+    };
+    assert_eq!(config.watch_scopes(), vec![None]);
+    let config = Config {
+        namespace_scoped: true,
+        ..config
+    };
+    assert_eq!(
+        config.watch_scopes(),
+        vec![Some("team-a".to_owned()), Some("team-b".to_owned())]
+    );
+}
+
+#[test]
+fn test_config_certificate_scopes() {
+    let config = Config {
+        watch_namespaces: vec![
+            "team-a".to_owned(),
+            DEFAULT_CERT_MANAGER_NAMESPACE.to_owned(),
+        ],
+        ..Default::default()
+    };
+    assert_eq!(config.certificate_scopes(), vec![None]);
+    let config = Config {
+        namespace_scoped: true,
+        ..config
+    };
+    assert_eq!(
+        config.certificate_scopes(),
+        vec![
+            Some(DEFAULT_CERT_MANAGER_NAMESPACE.to_owned()),
+            Some("team-a".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn test_config_validate() {
+    assert_eq!(Config::default().validate(), Ok(()));
+    let invalid = [
+        Config {
+            namespace_scoped: true,
+            ..Default::default()
+        },
+        Config {
+            route_termination: RouteTlsTermination::Passthrough,
+            ..Default::default()
+        },
+        Config {
+            requeue_interval: Duration::ZERO,
+            ..Default::default()
+        },
+        Config {
+            certificate_group_max_dns_names: 0,
+            ..Default::default()
+        },
+        Config {
+            leader_election: true,
+            ..Default::default()
+        },
+        Config {
+            router_service_account_name: String::new(),
+            ..Default::default()
+        },
+        Config {
+            webhook_tls_cert_file: Some("/etc/webhook/tls/tls.crt".to_owned()),
+            ..Default::default()
+        },
+        Config {
+            log_level: "info,=".to_owned(),
+            ..Default::default()
+        },
+    ];
+    for config in invalid {
+        assert!(config.validate().is_err(), "{:?}", config);
+    }
+}
+
+/// (De)serialize a [`Duration`] as a string such as `1h30m`, or a number of seconds.
+mod duration {
+    use crate::tools::{format_duration, parse_interval};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Seconds(u64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Seconds(seconds) => Ok(Duration::from_secs(seconds)),
+            Value::Text(value) => parse_interval(&value).ok_or_else(|| {
+                D::Error::custom(format!("`{}` isn't a duration such as `1h30m`", value))
+            }),
+        }
+    }
+}
+
+/// The command-line flags of the controller, each of which can also be set with an environment variable.
+///
+/// An empty value leaves the setting of the configuration file untouched.
+#[derive(Parser, Clone, Debug, Default)]
+#[command(
+    version,
+    about = "Populates the TLS of OpenShift Routes with cert-manager Certificates"
+)]
+pub struct Args {
+    /// A YAML file with the settings, overridden by the environment variables and the flags
+    #[arg(long, env = CONFIG_FILE_ENV)]
+    pub config: Option<String>,
+    /// A ConfigMap of the cert-manager namespace whose `config.yaml` replaces the file whenever it changes
+    #[arg(long, env = CONFIG_MAP_ENV)]
+    pub config_map: Option<String>,
+    /// The namespace of cert-manager [default: cert-manager]
+    #[arg(long, env = CERT_MANAGER_NAMESPACE_ENV)]
+    pub cert_manager_namespace: Option<String>,
+    /// The comma-separated namespaces whose Routes are reconciled [default: all]
+    #[arg(long, env = WATCH_NAMESPACES_ENV)]
+    pub watch_namespaces: Option<String>,
+    /// Whether only the watched namespaces and the cert-manager namespace are watched [default: false]
+    #[arg(long, env = NAMESPACE_SCOPED_ENV)]
+    pub namespace_scoped: Option<String>,
+    /// The default termination of the Routes, `edge` or `reencrypt` [default: edge]
+    #[arg(long, env = DEFAULT_TERMINATION_ENV)]
+    pub route_termination: Option<String>,
+    /// The default insecure edge termination policy, `Allow`, `None` or `Redirect` [default: Redirect]
+    #[arg(long, env = DEFAULT_INSECURE_POLICY_ENV)]
+    pub route_insecure_policy: Option<String>,
+    /// Whether the Routes use `spec.tls.externalCertificate` by default [default: false]
+    #[arg(long, env = DEFAULT_EXTERNAL_CERTIFICATE_ENV)]
+    pub route_external_certificate: Option<String>,
+    /// The namespace of the router's ServiceAccount [default: openshift-ingress]
+    #[arg(long, env = ROUTER_SERVICE_ACCOUNT_NAMESPACE_ENV)]
+    pub router_service_account_namespace: Option<String>,
+    /// The name of the router's ServiceAccount [default: router]
+    #[arg(long, env = ROUTER_SERVICE_ACCOUNT_NAME_ENV)]
+    pub router_service_account_name: Option<String>,
+    /// The ClusterIssuer requested by the `kubernetes.io/tls-acme` annotation
+    #[arg(long, env = TLS_ACME_ISSUER_ENV)]
+    pub tls_acme_cluster_issuer: Option<String>,
+    /// Whether the ClusterIssuers are restricted to the allowed ones [default: false]
+    #[arg(long, env = RESTRICT_ISSUERS_ENV)]
+    pub restrict_cluster_issuers: Option<String>,
+    /// The comma-separated ClusterIssuers every namespace may request
+    #[arg(long, env = ALLOWED_ISSUERS_ENV)]
+    pub allowed_cluster_issuers: Option<String>,
+    /// The interval between the audits of the requested ClusterIssuers [default: 1h]
+    #[arg(long, env = ISSUER_AUDIT_INTERVAL_ENV)]
+    pub issuer_audit_interval: Option<String>,
+    /// The number of Certificates created per domain and ClusterIssuer over the window [default: 0, unlimited]
+    #[arg(long, env = ISSUANCE_BUDGET_ENV)]
+    pub issuance_budget: Option<String>,
+    /// The sliding window of the issuance budget [default: 168h]
+    #[arg(long, env = ISSUANCE_BUDGET_WINDOW_ENV)]
+    pub issuance_budget_window: Option<String>,
+    /// The maximum number of dnsNames of a certificate group's Certificates [default: 100]
+    #[arg(long, env = CERTIFICATE_GROUP_MAX_DNS_NAMES_ENV)]
+    pub certificate_group_max_dns_names: Option<String>,
+    /// The interval between the reconciles of an up-to-date Route [default: 1h]
+    #[arg(long, env = REQUEUE_INTERVAL_ENV)]
+    pub requeue_interval: Option<String>,
+    /// The finalizer added to the managed Routes [default: kubernetes]
+    #[arg(long, env = FINALIZER_ENV)]
+    pub finalizer: Option<String>,
+    /// Whether the replicas elect a leader [default: false]
+    #[arg(long, env = LEADER_ELECTION_ENV)]
+    pub leader_election: Option<String>,
+    /// The name of the controller's Pod
+    #[arg(long, env = CONTROLLER_POD_ENV)]
+    pub pod_name: Option<String>,
+    /// The name of the controller's ServiceAccount
+    #[arg(long, env = CONTROLLER_SERVICE_ACCOUNT_ENV)]
+    pub service_account_name: Option<String>,
+    /// The window of the liveness probe [default: 2h]
+    #[arg(long, env = LIVENESS_WINDOW_ENV)]
+    pub liveness_window: Option<String>,
+    /// The time the in-flight reconciles get to finish on shutdown [default: 25s]
+    #[arg(long, env = SHUTDOWN_DEADLINE_ENV)]
+    pub shutdown_deadline: Option<String>,
+    /// The port of the admission webhook [default: 8443]
+    #[arg(long, env = WEBHOOK_PORT_ENV)]
+    pub webhook_port: Option<String>,
+    /// The certificate of the admission webhook
+    #[arg(long, env = WEBHOOK_TLS_CERT_FILE_ENV)]
+    pub webhook_tls_cert_file: Option<String>,
+    /// The key of the admission webhook
+    #[arg(long, env = WEBHOOK_TLS_KEY_FILE_ENV)]
+    pub webhook_tls_key_file: Option<String>,
+    /// The port of the metrics and probes [default: 8080]
+    #[arg(long, env = METRICS_PORT_ENV)]
+    pub metrics_port: Option<String>,
+    /// The levels of the logs [default: info]
+    #[arg(long, env = LOG_LEVEL_ENV)]
+    pub log_level: Option<String>,
+    /// The format of the logs, `text` or `json` [default: text]
+    #[arg(long, env = LOG_FORMAT_ENV)]
+    pub log_format: Option<String>,
+    /// The OTLP/HTTP collector the traces are exported to
+    #[arg(long, env = OTLP_ENDPOINT_ENV)]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Parse the value of a flag, or [`None`] if it is unset or empty.
+///
+/// ### Arguments
+///
+/// * `value` - The value of the flag.
+/// * `flag` - The name of the flag, without the leading `--`.
+/// * `expected` - A description of the expected values, for the error message.
+/// * `parse` - The function parsing the value.
+fn parse_arg<T>(
+    value: &Option<String>,
+    flag: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, String> {
+    match value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(value) => parse(value).map(Some).ok_or(format!(
+            "`--{}` must be {}, got `{}`",
+            flag.replace("_", "-"),
+            expected,
+            value
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Split a comma-separated list, ignoring the empty items.
+fn parse_list(value: &str) -> Option<Vec<String>> {
+    Some(
+        value
+            .split(",")
+            .map(|item| item.trim().to_owned())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    )
+}
+
+impl Args {
+    /// Override the settings of a [`Config`] with the flags that are set.
+    ///
+    /// ### Arguments
+    ///
+    /// * `config` - The [`Config`] to override.
+    ///
+    /// ### Returns
+    ///
+    /// A [`Result`] containing `()` or the reason a flag is invalid.
+    pub fn apply(&self, config: &mut Config) -> Result<(), String> {
+        let text = |value: &str| Some(value.to_owned());
+        let boolean = "one of `true`, `false`";
+        let duration = "a duration such as `1h30m`";
+        let number = "a positive number";
+        let port = "a port number";
+        macro_rules! set {
+            ($field:ident, $expected:expr, $parse:expr) => {
+                if let Some(value) = parse_arg(&self.$field, stringify!($field), $expected, $parse)?
+                {
+                    config.$field = value;
+                }
+            };
+            ($field:ident, $expected:expr, $parse:expr, optional) => {
+                if let Some(value) = parse_arg(&self.$field, stringify!($field), $expected, $parse)?
+                {
+                    config.$field = Some(value);
+                }
+            };
+        }
+        set!(cert_manager_namespace, "a namespace", text);
+        set!(watch_namespaces, "a list of namespaces", parse_list);
+        set!(namespace_scoped, boolean, parse_bool);
+        set!(
+            route_termination,
+            "one of `edge`, `reencrypt`",
+            parse_termination
+        );
+        set!(
+            route_insecure_policy,
+            "one of `Allow`, `None`, `Redirect`",
+            parse_insecure_edge_termination_policy
+        );
+        set!(route_external_certificate, boolean, parse_bool);
+        set!(router_service_account_namespace, "a namespace", text);
+        set!(router_service_account_name, "a ServiceAccount", text);
+        set!(tls_acme_cluster_issuer, "a ClusterIssuer", text, optional);
+        set!(restrict_cluster_issuers, boolean, parse_bool);
+        set!(
+            allowed_cluster_issuers,
+            "a list of ClusterIssuers",
+            parse_list
+        );
+        set!(issuer_audit_interval, duration, parse_interval);
+        set!(
+            issuance_budget,
+            "a number of Certificates",
+            |value: &str| value.parse().ok()
+        );
+        set!(issuance_budget_window, duration, parse_interval);
+        set!(certificate_group_max_dns_names, number, |value: &str| value
+            .parse()
+            .ok());
+        set!(requeue_interval, duration, parse_interval);
+        set!(finalizer, "a finalizer", text);
+        set!(leader_election, boolean, parse_bool);
+        set!(pod_name, "a Pod name", text, optional);
+        set!(
+            service_account_name,
+            "a ServiceAccount name",
+            text,
+            optional
+        );
+        set!(liveness_window, duration, parse_interval);
+        set!(shutdown_deadline, duration, parse_interval);
+        set!(webhook_port, port, |value: &str| value.parse().ok());
+        set!(webhook_tls_cert_file, "a file", text, optional);
+        set!(webhook_tls_key_file, "a file", text, optional);
+        set!(metrics_port, port, |value: &str| value.parse().ok());
+        set!(log_level, "a log filter", text);
+        set!(log_format, "one of `text`, `json`", parse_log_format);
+        set!(otlp_endpoint, "a URL", text, optional);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_args_apply() {
+    let args = Args::try_parse_from([
+        crate::CONTROLLER_NAME,
+        "--cert-manager-namespace",
+        "certs",
+        "--watch-namespaces",
+        "team-a, team-b,",
+        "--route-termination",
+        "reencrypt",
+        "--issuance-budget",
+        "5",
+        "--requeue-interval",
+        "30m",
+        "--issuer-audit-interval",
+        "600",
+        "--leader-election",
+        "True",
+        "--tls-acme-cluster-issuer",
+        "",
+    ])
+    .unwrap();
+    let mut config = Config::default();
+    args.apply(&mut config).unwrap();
+    assert_eq!(config.cert_manager_namespace, "certs");
+    assert_eq!(config.watch_namespaces, vec!["team-a", "team-b"]);
+    assert_eq!(config.route_termination, RouteTlsTermination::Reencrypt);
+    assert_eq!(config.issuance_budget, 5);
+    assert_eq!(config.requeue_interval, Duration::from_secs(1800));
+    assert_eq!(config.issuer_audit_interval, Duration::from_secs(600));
+    assert!(config.leader_election);
+    assert_eq!(config.tls_acme_cluster_issuer, None);
+    assert_eq!(config.finalizer, DEFAULT_FINALIZER);
+
+    let args = Args::try_parse_from([crate::CONTROLLER_NAME, "--metrics-port", "http"]).unwrap();
+    assert_eq!(
+        args.apply(&mut Config::default()),
+        Err("`--metrics-port` must be a port number, got `http`".to_owned())
+    );
+}
+
+/// Parse a [`Config`] from a YAML document.
+///
+/// The settings missing from the document keep their default value, and the empty optional settings are unset.
+///
+/// ### Arguments
+///
+/// * `yaml` - The YAML document.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`Config`] or the reason the document is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let config = parse_config("requeue_interval: 30m")?;
+/// println!("{:?}", config.requeue_interval); // 1800s
+/// ```
+pub fn parse_config(yaml: &str) -> Result<Config, String> {
+    if yaml.trim().is_empty() {
+        return Ok(Config::default());
+    }
+    let mut config: Config = serde_yaml::from_str(yaml).map_err(|e| e.to_string())?;
+    for value in [
+        &mut config.tls_acme_cluster_issuer,
+        &mut config.pod_name,
+        &mut config.service_account_name,
+        &mut config.webhook_tls_cert_file,
+        &mut config.webhook_tls_key_file,
+        &mut config.otlp_endpoint,
+    ] {
+        if value.as_deref() == Some("") {
+            *value = None;
+        }
+    }
+    Ok(config)
+}
+
+#[test]
+fn test_parse_config() {
+    let config = parse_config(include_str!("../fixtures/config/config.yaml")).unwrap();
+    assert_eq!(config.cert_manager_namespace, "certs");
+    assert_eq!(config.watch_namespaces, vec!["team-a"]);
+    assert_eq!(config.watch_scopes(), vec![None]);
+    assert_eq!(
+        config.route_insecure_policy,
+        RouteTlsInsecureEdgeTerminationPolicy::Allow
+    );
+    assert_eq!(config.tls_acme_cluster_issuer, None);
+    assert_eq!(
+        config.allowed_issuers(),
+        Some(HashSet::from(["letsencrypt-prod".to_owned()]))
+    );
+    assert_eq!(config.issuer_audit_interval, Duration::from_secs(3600));
+    assert_eq!(config.issuance_budget(), None);
+    assert_eq!(config.requeue_interval, Duration::from_secs(1800));
+    assert_eq!(config.log_format, LogFormat::Json);
+    assert_eq!(config.metrics_port, 8080);
+    assert_eq!(parse_config(""), Ok(Config::default()));
+    assert!(parse_config("requeue_interval: soon").is_err());
+    assert!(parse_config("requeue: 1h").is_err());
+    let printed = serde_yaml::to_string(&config).unwrap();
+    assert_eq!(parse_config(&printed), Ok(config));
+}
+
+/// Load the [`Config`] of the controller from its configuration file, environment and flags, and validate it.
+///
+/// ### Arguments
+///
+/// * `args` - The [`Args`] of the controller.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`Config`] or the reason it is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let config = load_config(&Args::parse())?;
+/// ```
+pub fn load_config(args: &Args) -> Result<Config, String> {
+    match args.config.as_deref().filter(|path| !path.is_empty()) {
+        Some(path) => {
+            let yaml = std::fs::read_to_string(path)
+                .map_err(|e| format!("Error reading `{}`: {}", path, e))?;
+            resolve_config(args, &yaml).map_err(|e| format!("Invalid `{}`: {}", path, e))
+        }
+        None => resolve_config(args, ""),
+    }
+}
+
+/// Build the [`Config`] of the controller from a YAML document overridden by its environment and flags,
+/// and validate it.
+///
+/// ### Arguments
+///
+/// * `args` - The [`Args`] of the controller.
+/// * `yaml` - The YAML document, from the configuration file or `ConfigMap`.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`Config`] or the reason it is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let config = resolve_config(&args, "requeue_interval: 30m")?;
+/// ```
+pub fn resolve_config(args: &Args, yaml: &str) -> Result<Config, String> {
+    let mut config = parse_config(yaml)?;
+    args.apply(&mut config)?;
+    config.validate()?;
+    Ok(config)
+}
+
+#[test]
+fn test_resolve_config() {
+    let args = Args::try_parse_from([crate::CONTROLLER_NAME, "--requeue-interval", "10m"]).unwrap();
+    let config = resolve_config(
+        &args,
+        "requeue_interval: 30m\nfinalizer: example.com/routes",
+    )
+    .unwrap();
+    assert_eq!(config.requeue_interval, Duration::from_secs(600));
+    assert_eq!(config.finalizer, "example.com/routes");
+    assert!(resolve_config(&args, "route_termination: passthrough").is_err());
+    assert!(resolve_config(&args, "- requeue_interval").is_err());
+}
@@ -1,2 +1,3 @@
 pub mod certificate;
+pub mod policy;
 pub mod route;
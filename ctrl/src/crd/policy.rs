@@ -0,0 +1,94 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Cluster-wide rules resolving the `ClusterIssuer`, `Certificate` and TLS termination defaults of the Routes.
+///
+/// The policies are evaluated by decreasing priority, then by name, and the rules of a policy in order.
+/// The first matching rule applies. The annotations of a Route always take precedence over the rule.
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[kube(
+    group = "routes.cert-manager.io",
+    version = "v1alpha1",
+    kind = "RoutesCertificatePolicy",
+    plural = "routescertificatepolicies",
+    shortname = "rcp"
+)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutesCertificatePolicySpec {
+    /// The priority of the policy. Policies with a higher priority are evaluated first.
+    #[serde(default)]
+    pub priority: i32,
+    /// The rules of the policy, evaluated in order.
+    pub rules: Vec<RoutesCertificatePolicyRule>,
+}
+
+/// A rule matching Routes and the defaults it applies to them.
+///
+/// All of the set selectors must match for the rule to apply.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutesCertificatePolicyRule {
+    /// The suffix the Route's host must end with, such as `internal.corp` or `*.internal.corp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_suffix: Option<String>,
+    /// The labels of the Route's Namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_selector: Option<LabelSelector>,
+    /// The labels of the Route.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route_selector: Option<LabelSelector>,
+    /// The name of a router shard that admitted the Route.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router_name: Option<String>,
+    /// The `ClusterIssuer` of the Route's `Certificate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// The `Certificate` settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<RoutesCertificatePolicyCertificate>,
+    /// The group of the Routes sharing multi-SAN `Certificate`s.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_group: Option<String>,
+    /// Whether the Routes share the `Certificate` of their host, `shared`, or get their own, `dedicated`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_mode: Option<String>,
+    /// The TLS termination of the Route, `edge` or `reencrypt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<String>,
+    /// The insecure edge termination policy of the Route, `Allow`, `None` or `Redirect`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insecure_edge_termination_policy: Option<String>,
+}
+
+/// The settings of the `Certificate`s created for the Routes.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutesCertificatePolicyCertificate {
+    /// The requested duration of the certificate, such as `2160h`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    /// How long before the certificate expiry it is renewed, such as `360h`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renew_before: Option<String>,
+    /// The private key algorithm, `RSA`, `ECDSA` or `Ed25519`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key_algorithm: Option<String>,
+    /// The private key size in bits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key_size: Option<i64>,
+}
+
+/// The CustomResourceDefinition shipped with the Helm chart must match the one generated from the types.
+#[test]
+fn test_crd_manifest() {
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+    use kube::CustomResourceExt;
+
+    let manifest: CustomResourceDefinition = serde_yaml::from_str(include_str!(
+        "../../../charts/crds/routescertificatepolicies.yaml"
+    ))
+    .unwrap();
+    assert_eq!(manifest, RoutesCertificatePolicy::crd());
+}
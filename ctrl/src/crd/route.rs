@@ -1,12 +1,12 @@
 // WARNING: generated by kopium - manual changes will be overwritten
-// kopium command: curl -sSL https://raw.githubusercontent.com/openshift/api/master/route/v1/route.crd.yaml | kopium -f - > src/route.rs
+// kopium command: curl -sSL https://raw.githubusercontent.com/openshift/api/master/route/v1/route.crd.yaml | kopium -f - --derive PartialEq > src/route.rs
 // kopium version: 0.15.0
 
 use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::CustomResource;
 use serde::{Deserialize, Serialize};
 
-#[derive(CustomResource, Serialize, Deserialize, Clone, Debug)]
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[kube(
     group = "route.openshift.io",
     version = "v1",
@@ -42,7 +42,7 @@ pub struct RouteSpec {
     pub wildcard_policy: Option<RouteWildcardPolicy>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RouteAlternateBackends {
     pub kind: RouteAlternateBackendsKind,
     pub name: String,
@@ -50,20 +50,20 @@ pub struct RouteAlternateBackends {
     pub weight: Option<i32>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum RouteAlternateBackendsKind {
     Service,
     #[serde(rename = "")]
     KopiumEmpty,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RoutePort {
     #[serde(rename = "targetPort")]
     pub target_port: IntOrString,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RouteTls {
     #[serde(
         default,
@@ -79,6 +79,12 @@ pub struct RouteTls {
         rename = "destinationCACertificate"
     )]
     pub destination_ca_certificate: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        rename = "externalCertificate"
+    )]
+    pub external_certificate: Option<RouteTlsExternalCertificate>,
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
@@ -90,7 +96,13 @@ pub struct RouteTls {
     pub termination: RouteTlsTermination,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RouteTlsExternalCertificate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum RouteTlsInsecureEdgeTerminationPolicy {
     Allow,
     None,
@@ -99,7 +111,7 @@ pub enum RouteTlsInsecureEdgeTerminationPolicy {
     KopiumEmpty,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum RouteTlsTermination {
     #[serde(rename = "edge")]
     Edge,
@@ -109,7 +121,7 @@ pub enum RouteTlsTermination {
     Passthrough,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RouteTo {
     pub kind: RouteToKind,
     pub name: String,
@@ -117,14 +129,14 @@ pub struct RouteTo {
     pub weight: Option<i32>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum RouteToKind {
     Service,
     #[serde(rename = "")]
     KopiumEmpty,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum RouteWildcardPolicy {
     None,
     Subdomain,
@@ -132,13 +144,13 @@ pub enum RouteWildcardPolicy {
     KopiumEmpty,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RouteStatus {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ingress: Option<Vec<RouteStatusIngress>>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RouteStatusIngress {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub conditions: Option<Vec<RouteStatusIngressConditions>>,
@@ -164,7 +176,7 @@ pub struct RouteStatusIngress {
     pub wildcard_policy: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RouteStatusIngressConditions {
     #[serde(
         default,
@@ -1,4 +1,10 @@
+use crate::metrics::record_event;
+use k8s_openapi::api::core::v1::ObjectReference;
 use kube::runtime::events::{Event, EventType, Recorder};
+use tracing::{info, warn};
+
+/// The maximum length of a list in an event note, which is limited to 1 KiB (see `format_capped_list()`).
+pub const MAX_EVENT_NOTE_LIST_LEN: usize = 768;
 
 /// Publish a successful event
 ///
@@ -7,54 +13,55 @@ use kube::runtime::events::{Event, EventType, Recorder};
 /// * `action` - The action that was taken
 /// * `reason` - The reason for the action
 /// * `note` - An optional note to include
-/// * `recorder` - The event recorder
+/// * `secondary` - An optional secondary object the event concerns, such as a `Certificate`
+/// * `recorder` - The event recorder, whose reference is the object the event is about
 pub async fn success_event(
     action: String,
     reason: String,
     note: Option<String>,
+    secondary: Option<ObjectReference>,
     recorder: &Recorder,
-) -> () {
-    println!("OK; action={}; reason={}; note={:?};", action, reason, note);
+) {
+    info!(%action, %reason, "{}", note.as_deref().unwrap_or_default());
+    record_event(&reason, false);
     let _ = recorder
         .publish(Event {
             action,
             reason,
             note,
             type_: EventType::Normal,
-            secondary: None,
+            secondary,
         })
         .await;
-    ()
 }
 
 /// Publish an unsuccessful event
 ///
-/// This event will appear as a warning in the Kubernetes event log
+/// This event will appear as a warning in the Kubernetes event log and in the logs
 ///
 /// # Arguments
 ///
 /// * `action` - The action that was taken
 /// * `reason` - The reason for the action
 /// * `note` - An optional note to include
-/// * `recorder` - The event recorder
+/// * `secondary` - An optional secondary object the event concerns, such as a `Certificate`
+/// * `recorder` - The event recorder, whose reference is the object the event is about
 pub async fn error_event(
     action: String,
     reason: String,
     note: Option<String>,
+    secondary: Option<ObjectReference>,
     recorder: &Recorder,
-) -> () {
-    eprintln!(
-        "ERR; action={}; reason={}; note={:?};",
-        action, reason, note
-    );
+) {
+    warn!(%action, %reason, "{}", note.as_deref().unwrap_or_default());
+    record_event(&reason, true);
     let _ = recorder
         .publish(Event {
             action,
             reason,
             note,
             type_: EventType::Warning,
-            secondary: None,
+            secondary,
         })
         .await;
-    ()
 }
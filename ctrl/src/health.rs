@@ -0,0 +1,129 @@
+use crate::crd::certificate::Certificate;
+use crate::crd::route::Route;
+use chrono::Utc;
+use futures::FutureExt;
+use kube::runtime::reflector::Store;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::Duration;
+
+pub const HEALTHZ_PATH: &str = "/healthz";
+pub const READYZ_PATH: &str = "/readyz";
+
+/// The last time a watch event was received or a reconcile ran, in seconds since the epoch.
+static LAST_ACTIVITY: AtomicI64 = AtomicI64::new(0);
+
+/// Whether the controller is shutting down and no longer accepts new work.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Record that the controller is shutting down, so that it is no longer ready.
+pub fn record_shutdown() {
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+}
+
+/// Record that a watch event was received or a reconcile ran.
+pub fn record_activity() {
+    LAST_ACTIVITY.store(Utc::now().timestamp(), Ordering::Relaxed);
+}
+
+/// The liveness and readiness probes of the controller.
+#[derive(Clone)]
+pub struct HealthProbes {
+    /// The caches of the [`Route`]s watched by the controller, one per watched scope.
+    pub routes: Vec<Store<Route>>,
+    /// The caches of the [`Certificate`]s, one per watched scope.
+    pub certificates: Vec<Store<Certificate>>,
+    /// How long the controller may go without any watch event or reconcile before it is considered stalled.
+    pub liveness_window: Duration,
+}
+
+impl HealthProbes {
+    /// Check whether the initial sync of the [`Route`] and [`Certificate`] caches is complete.
+    /// The controller is no longer ready once it is shutting down.
+    ///
+    /// ### Returns
+    ///
+    /// A [`bool`] indicating whether the controller is ready.
+    pub fn is_ready(&self) -> bool {
+        !SHUTTING_DOWN.load(Ordering::Relaxed)
+            && self.routes.iter().all(is_store_ready)
+            && self.certificates.iter().all(is_store_ready)
+    }
+
+    /// Check whether the controller is still processing watch events or reconciles.
+    ///
+    /// A standby replica doesn't reconcile nor requeue the [`Route`]s, so it is idle like a leader without any.
+    ///
+    /// ### Arguments
+    ///
+    /// * `leader` - Whether the replica is the leader and reconciles the [`Route`]s.
+    ///
+    /// ### Returns
+    ///
+    /// A [`bool`] indicating whether the controller is alive.
+    pub fn is_alive(&self, leader: bool) -> bool {
+        is_alive(
+            LAST_ACTIVITY.load(Ordering::Relaxed),
+            Utc::now().timestamp(),
+            self.liveness_window,
+            !leader || self.routes.iter().all(|routes| routes.state().is_empty()),
+        )
+    }
+}
+
+/// Check whether the initial sync of a cache is complete, without waiting for it.
+fn is_store_ready<K>(store: &Store<K>) -> bool
+where
+    K: kube::Resource + Clone + 'static,
+    K::DynamicType: Eq + std::hash::Hash + Clone,
+{
+    matches!(store.wait_until_ready().now_or_never(), Some(Ok(_)))
+}
+
+/// Check whether the controller ran within the liveness window.
+///
+/// Every [`Route`] is requeued periodically, so the controller is only expected to be idle when there is no [`Route`].
+///
+/// ### Arguments
+///
+/// * `last_activity` - The last time a watch event was received or a reconcile ran, in seconds since the epoch.
+/// * `now` - The current time, in seconds since the epoch.
+/// * `window` - The liveness window.
+/// * `idle` - Whether there is no [`Route`] to reconcile, or the replica is a standby.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the controller is alive.
+///
+/// ### Example
+///
+/// ```rust
+/// let alive = is_alive(last_activity, Utc::now().timestamp(), Duration::from_secs(7200), false);
+/// println!("Controller alive: {}", alive);
+/// ```
+pub fn is_alive(last_activity: i64, now: i64, window: Duration, idle: bool) -> bool {
+    idle || now - last_activity <= window.as_secs() as i64
+}
+
+#[test]
+fn test_is_alive() {
+    let window = Duration::from_secs(7200);
+    assert!(is_alive(1000, 1000, window, false));
+    assert!(is_alive(1000, 8200, window, false));
+    assert!(!is_alive(1000, 8201, window, false));
+    assert!(is_alive(1000, 8201, window, true));
+}
+
+#[test]
+fn test_health_probes_is_alive() {
+    let mut writer = kube::runtime::reflector::store::Writer::<Route>::default();
+    writer.apply_watcher_event(&kube::runtime::watcher::Event::Applied(
+        Route::new_test_route("hello", "world", "example.com", None, None),
+    ));
+    let probes = HealthProbes {
+        routes: vec![writer.as_reader()],
+        certificates: vec![],
+        liveness_window: Duration::from_secs(0),
+    };
+    // A standby replica with Routes never reconciles them, but is still alive
+    assert!(probes.is_alive(false));
+}
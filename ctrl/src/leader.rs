@@ -0,0 +1,373 @@
+use crate::budget::load_budget_state;
+use crate::events::{error_event, success_event};
+use crate::secret::owner_labels;
+use crate::types::ContextData;
+use crate::CONTROLLER_NAME;
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc::Sender;
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use kube::api::{ObjectMeta, PostParams};
+use kube::Api;
+use std::sync::{atomic::Ordering, Arc};
+use std::time::{Duration, Instant};
+
+/// The name of the `Lease` held by the leader, in the cert-manager namespace.
+pub const LEASE_NAME: &str = CONTROLLER_NAME;
+/// How long a `Lease` that isn't renewed is held, in seconds.
+pub const LEASE_DURATION_SECONDS: i32 = 15;
+/// How often the leader renews the `Lease` and the standby replicas try to acquire it, in seconds.
+const LEASE_RETRY_INTERVAL: u64 = 5;
+
+/// The last change of the leader [`Lease`] seen by a replica: its `resourceVersion` and when it was seen locally.
+///
+/// As in client-go, the expiry of a [`Lease`] is measured with the local clock from the last time it was seen
+/// changing, rather than from its `renewTime`, so that a clock skew between the replicas doesn't let a standby
+/// take the [`Lease`] over from a healthy leader.
+pub type LeaseObservation = Option<(String, Instant)>;
+
+/// Record the [`Lease`] seen by a replica, if it changed since the last time it was seen.
+///
+/// ### Arguments
+///
+/// * `observation` - The [`LeaseObservation`] of the replica.
+/// * `lease` - The [`Lease`].
+/// * `now` - The current local time.
+///
+/// ### Returns
+///
+/// The local time the [`Lease`] was last seen changing.
+fn observe_lease(observation: &mut LeaseObservation, lease: &Lease, now: Instant) -> Instant {
+    let version = lease.metadata.resource_version.clone().unwrap_or_default();
+    match observation {
+        Some((observed, observed_at)) if *observed == version => *observed_at,
+        _ => {
+            *observation = Some((version, now));
+            now
+        }
+    }
+}
+
+/// Check whether a replica may acquire or renew a [`Lease`].
+///
+/// A [`Lease`] may be acquired if it has no holder, if the replica already holds it,
+/// or if it wasn't seen changing within its duration (see [`LeaseObservation`]).
+///
+/// ### Arguments
+///
+/// * `lease` - The [`Lease`].
+/// * `identity` - The identity of the replica.
+/// * `observed_at` - The local time the [`Lease`] was last seen changing.
+/// * `now` - The current local time.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the replica may acquire the [`Lease`].
+///
+/// ### Example
+///
+/// ```rust
+/// if can_acquire_lease(&lease, "controller-0", observed_at, Instant::now()) {
+///     println!("The Lease is free");
+/// }
+/// ```
+pub fn can_acquire_lease(
+    lease: &Lease,
+    identity: &str,
+    observed_at: Instant,
+    now: Instant,
+) -> bool {
+    let spec = match lease.spec.as_ref() {
+        Some(spec) => spec,
+        None => return true,
+    };
+    match spec.holder_identity.as_deref() {
+        None | Some("") => return true,
+        Some(holder) if holder == identity => return true,
+        _ => {}
+    }
+    let duration = spec
+        .lease_duration_seconds
+        .unwrap_or(LEASE_DURATION_SECONDS);
+    now.saturating_duration_since(observed_at) > Duration::from_secs(duration.max(0) as u64)
+}
+
+#[test]
+fn test_can_acquire_lease() {
+    let now = Instant::now();
+    let mut lease = Lease::default();
+    assert!(can_acquire_lease(&lease, "controller-0", now, now));
+
+    claim_lease(&mut lease, "controller-1", Utc::now());
+    let expiry = Duration::from_secs(LEASE_DURATION_SECONDS as u64 + 1);
+    assert!(!can_acquire_lease(&lease, "controller-0", now, now));
+    assert!(can_acquire_lease(&lease, "controller-1", now, now));
+    assert!(can_acquire_lease(&lease, "controller-0", now, now + expiry));
+
+    lease.spec.as_mut().unwrap().holder_identity = None;
+    assert!(can_acquire_lease(&lease, "controller-0", now, now));
+}
+
+#[test]
+fn test_observe_lease() {
+    let now = Instant::now();
+    let expiry = Duration::from_secs(LEASE_DURATION_SECONDS as u64 + 1);
+    let mut observation = None;
+    // The leader's clock is an hour behind, its renewTime looks long expired
+    let mut lease = Lease::default();
+    claim_lease(
+        &mut lease,
+        "controller-1",
+        Utc::now() - chrono::Duration::hours(1),
+    );
+    lease.metadata.resource_version = Some("1".to_owned());
+    let observed_at = observe_lease(&mut observation, &lease, now);
+    assert!(!can_acquire_lease(&lease, "controller-0", observed_at, now));
+
+    // The leader keeps renewing the Lease, which is never expired
+    lease.metadata.resource_version = Some("2".to_owned());
+    let observed_at = observe_lease(&mut observation, &lease, now + expiry);
+    assert_eq!(observed_at, now + expiry);
+    assert!(!can_acquire_lease(
+        &lease,
+        "controller-0",
+        observed_at,
+        now + expiry
+    ));
+
+    // The leader stops renewing the Lease, which expires a Lease duration later
+    let observed_at = observe_lease(&mut observation, &lease, now + expiry * 2);
+    assert_eq!(observed_at, now + expiry);
+    assert!(can_acquire_lease(
+        &lease,
+        "controller-0",
+        observed_at,
+        now + expiry * 2
+    ));
+}
+
+/// Make a replica the holder of a [`Lease`], renewing it if it already holds it.
+///
+/// ### Arguments
+///
+/// * `lease` - The [`Lease`] to claim.
+/// * `identity` - The identity of the replica.
+/// * `now` - The current time.
+fn claim_lease(lease: &mut Lease, identity: &str, now: DateTime<Utc>) {
+    let spec = lease.spec.get_or_insert_with(LeaseSpec::default);
+    if spec.holder_identity.as_deref() != Some(identity) {
+        if spec.holder_identity.is_some() {
+            spec.lease_transitions = Some(spec.lease_transitions.unwrap_or(0) + 1);
+        }
+        spec.holder_identity = Some(identity.to_owned());
+        spec.acquire_time = Some(MicroTime(now));
+    }
+    spec.lease_duration_seconds = Some(LEASE_DURATION_SECONDS);
+    spec.renew_time = Some(MicroTime(now));
+}
+
+#[test]
+fn test_claim_lease() {
+    let now = Utc::now();
+    let mut lease = Lease::default();
+    claim_lease(&mut lease, "controller-0", now);
+    let spec = lease.spec.as_ref().unwrap();
+    assert_eq!(spec.holder_identity, Some("controller-0".to_owned()));
+    assert_eq!(spec.acquire_time, Some(MicroTime(now)));
+    assert_eq!(spec.lease_transitions, None);
+
+    let later = now + chrono::Duration::seconds(5);
+    claim_lease(&mut lease, "controller-0", later);
+    let spec = lease.spec.as_ref().unwrap();
+    assert_eq!(spec.acquire_time, Some(MicroTime(now)));
+    assert_eq!(spec.renew_time, Some(MicroTime(later)));
+
+    claim_lease(&mut lease, "controller-1", later);
+    let spec = lease.spec.as_ref().unwrap();
+    assert_eq!(spec.holder_identity, Some("controller-1".to_owned()));
+    assert_eq!(spec.acquire_time, Some(MicroTime(later)));
+    assert_eq!(spec.lease_transitions, Some(1));
+}
+
+/// Try to acquire or renew the leader [`Lease`].
+///
+/// The [`Lease`] is created if it doesn't exist, and recorded in the [`LeaseObservation`] of the replica otherwise. Concurrent updates are rejected by the API server
+/// thanks to the `resourceVersion` of the [`Lease`], in which case another replica won.
+///
+/// ### Arguments
+///
+/// * `identity` - The identity of the replica.
+/// * `observation` - The [`LeaseObservation`] of the replica.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing a [`bool`] indicating whether the replica holds the [`Lease`], or a [`kube::Error`].
+pub async fn try_acquire_lease(
+    identity: &str,
+    observation: &mut LeaseObservation,
+    ctx: &ContextData,
+) -> Result<bool, kube::Error> {
+    let api = Api::<Lease>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace);
+    let now = Utc::now();
+    let result = match api.get_opt(LEASE_NAME).await? {
+        Some(mut lease) => {
+            let observed_at = observe_lease(observation, &lease, Instant::now());
+            if !can_acquire_lease(&lease, identity, observed_at, Instant::now()) {
+                return Ok(false);
+            }
+            claim_lease(&mut lease, identity, now);
+            api.replace(LEASE_NAME, &PostParams::default(), &lease)
+                .await
+        }
+        None => {
+            let mut lease = Lease {
+                metadata: ObjectMeta {
+                    name: Some(LEASE_NAME.to_owned()),
+                    labels: Some(owner_labels()),
+                    ..Default::default()
+                },
+                spec: None,
+            };
+            claim_lease(&mut lease, identity, now);
+            api.create(&PostParams::default(), &lease).await
+        }
+    };
+    match result {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(e)) if e.code == 409 => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Release the leader [`Lease`] if the replica holds it, so that a standby replica takes over right away.
+///
+/// ### Arguments
+///
+/// * `identity` - The identity of the replica.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`kube::Error`].
+pub async fn release_lease(identity: &str, ctx: &ContextData) -> Result<(), kube::Error> {
+    ctx.leader.store(false, Ordering::Relaxed);
+    let api = Api::<Lease>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace);
+    let mut lease = match api.get_opt(LEASE_NAME).await? {
+        Some(lease) => lease,
+        None => return Ok(()),
+    };
+    match lease.spec.as_mut() {
+        Some(spec) if spec.holder_identity.as_deref() == Some(identity) => {
+            spec.holder_identity = None;
+            spec.lease_duration_seconds = Some(1);
+        }
+        _ => return Ok(()),
+    }
+    api.replace(LEASE_NAME, &PostParams::default(), &lease)
+        .await?;
+    Ok(())
+}
+
+/// Take part in the leader election until the process exits.
+///
+/// Every replica keeps its caches warm, but only the leader reconciles the `Route`s.
+/// Once it acquires the [`Lease`], a replica reloads the issuance budget persisted by the previous leader
+/// and triggers the reconciliation of every `Route`.
+/// The leader steps down if it couldn't renew the [`Lease`] within its duration.
+///
+/// ### Arguments
+///
+/// * `identity` - The identity of the replica.
+/// * `ctx` - The [`ContextData`].
+/// * `acquired` - The channel notified when the replica becomes the leader.
+///
+/// ### Example
+///
+/// ```rust
+/// tokio::spawn(run_leader_election(identity, ctx.clone(), reconcile_all));
+/// ```
+pub async fn run_leader_election(
+    identity: String,
+    ctx: Arc<ContextData>,
+    mut acquired: Sender<()>,
+) {
+    let mut last_renewal: Option<Instant> = None;
+    let mut observation = None;
+    loop {
+        let leader = ctx.leader.load(Ordering::Relaxed);
+        match try_acquire_lease(&identity, &mut observation, &ctx).await {
+            Ok(true) => {
+                last_renewal = Some(Instant::now());
+                if !leader {
+                    let reloaded = match ctx.issuance_budget {
+                        Some(_) => load_budget_state(&ctx).await.map(Some),
+                        None => Ok(None),
+                    };
+                    match reloaded {
+                        Ok(state) => {
+                            if let Some(state) = state {
+                                *ctx.budget_state.lock().await = state;
+                            }
+                            ctx.leader.store(true, Ordering::Relaxed);
+                            let _ = acquired.try_send(());
+                            success_event(
+                                "Acquire".to_owned(),
+                                "LeaderElection".to_owned(),
+                                Some(format!("`{}` became the leader", identity)),
+                                None,
+                                &ctx.recorder.clone(),
+                            )
+                            .await
+                        }
+                        Err(e) => {
+                            error_event(
+                                "Acquire".to_owned(),
+                                "LeaderElection".to_owned(),
+                                Some(format!(
+                                    "Error loading the issuance budget before leading: {}",
+                                    e
+                                )),
+                                None,
+                                &ctx.recorder.clone(),
+                            )
+                            .await
+                        }
+                    }
+                }
+            }
+            Ok(false) => {
+                if leader {
+                    ctx.leader.store(false, Ordering::Relaxed);
+                    error_event(
+                        "Acquire".to_owned(),
+                        "LeaderElection".to_owned(),
+                        Some(format!("`{}` lost the leader Lease", identity)),
+                        None,
+                        &ctx.recorder.clone(),
+                    )
+                    .await
+                }
+            }
+            Err(e) => {
+                // Step down before the Lease expires, as another replica may acquire it right after.
+                let expired = last_renewal.is_none_or(|renewal| {
+                    renewal.elapsed()
+                        >= Duration::from_secs(LEASE_DURATION_SECONDS as u64 - LEASE_RETRY_INTERVAL)
+                });
+                if leader && expired {
+                    ctx.leader.store(false, Ordering::Relaxed);
+                }
+                error_event(
+                    "Acquire".to_owned(),
+                    "LeaderElection".to_owned(),
+                    Some(format!("Error renewing the leader Lease: {}", e)),
+                    None,
+                    &ctx.recorder.clone(),
+                )
+                .await
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(LEASE_RETRY_INTERVAL)).await;
+    }
+}
@@ -0,0 +1,285 @@
+use crate::crd::route::Route;
+use crate::route::{get_route_cluster_issuer, get_route_tls_source};
+use crate::tools::resource_to_string;
+use crate::types::{ContextData, TlsSource};
+#[cfg(feature = "otlp")]
+use crate::CONTROLLER_NAME;
+use kube::ResourceExt;
+#[cfg(feature = "otlp")]
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "otlp")]
+use std::sync::OnceLock;
+#[cfg(feature = "otlp")]
+use tracing::error;
+#[cfg(not(feature = "otlp"))]
+use tracing::warn;
+use tracing::{info_span, Span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// The format of the logs.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line, with the fields of the current spans.
+    Json,
+}
+
+/// Parse a [`LogFormat`] from its string representation.
+///
+/// The accepted values are `text` and `json` (case insensitive).
+///
+/// ### Arguments
+///
+/// * `value` - The value to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the [`LogFormat`], or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let format = parse_log_format("json");
+/// println!("{:?}", format); // Some(Json)
+/// ```
+pub fn parse_log_format(value: &str) -> Option<LogFormat> {
+    match value.to_lowercase().as_str() {
+        "text" => Some(LogFormat::Text),
+        "json" => Some(LogFormat::Json),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_log_format() {
+    assert_eq!(parse_log_format("text"), Some(LogFormat::Text));
+    assert_eq!(parse_log_format("JSON"), Some(LogFormat::Json));
+    assert_eq!(parse_log_format("logfmt"), None);
+    assert_eq!(parse_log_format(""), None);
+}
+
+/// Install the global subscriber writing the logs to the standard output.
+///
+/// When `otlp_endpoint` is set and the `otlp` feature is enabled, the spans are also exported to the OpenTelemetry
+/// collector listening at this endpoint over OTLP/HTTP. Without the feature, the endpoint is ignored with a warning.
+///
+/// ### Arguments
+///
+/// * `format` - The [`LogFormat`].
+/// * `filter` - The levels of the logs and spans, such as `info` or `info,cert_manager_routes_controller=debug`.
+/// * `otlp_endpoint` - The base URL of the OTLP/HTTP collector, such as `http://otel-collector:4318`.
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or the reason the OTLP exporter couldn't be created.
+///
+/// ### Example
+///
+/// ```rust
+/// init_logging(LogFormat::Json, EnvFilter::new("info"), Some("http://otel-collector:4318"))?;
+/// ```
+pub fn init_logging(
+    format: LogFormat,
+    filter: EnvFilter,
+    otlp_endpoint: Option<&str>,
+) -> Result<(), String> {
+    let logs = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(logs);
+    #[cfg(feature = "otlp")]
+    {
+        let traces = match otlp_endpoint {
+            Some(endpoint) => {
+                let provider = new_tracer_provider(endpoint)?;
+                let layer =
+                    tracing_opentelemetry::layer().with_tracer(provider.tracer(CONTROLLER_NAME));
+                let _ = TRACER_PROVIDER.set(provider);
+                Some(layer)
+            }
+            None => None,
+        };
+        registry.with(traces).init();
+    }
+    #[cfg(not(feature = "otlp"))]
+    {
+        registry.init();
+        if let Some(endpoint) = otlp_endpoint {
+            warn!(
+                "Ignoring the OTLP endpoint `{}`, the controller was built without the `otlp` feature",
+                endpoint
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The [`TracerProvider`] exporting the spans, flushed by [`shutdown_tracing()`].
+#[cfg(feature = "otlp")]
+static TRACER_PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+
+/// Create a [`TracerProvider`] exporting the spans in batches to an OTLP/HTTP collector.
+///
+/// ### Arguments
+///
+/// * `endpoint` - The base URL of the collector, the spans are posted to `<endpoint>/v1/traces`.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`TracerProvider`] or the reason the exporter couldn't be created.
+///
+/// ### Example
+///
+/// ```rust
+/// let provider = new_tracer_provider("http://otel-collector:4318")?;
+/// ```
+#[cfg(feature = "otlp")]
+pub fn new_tracer_provider(endpoint: &str) -> Result<TracerProvider, String> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| e.to_string())?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            CONTROLLER_NAME,
+        )]))
+        .build())
+}
+
+/// Export the spans not exported yet, if the spans are exported over OTLP.
+///
+/// ### Example
+///
+/// ```rust
+/// shutdown_tracing();
+/// std::process::exit(1);
+/// ```
+pub fn shutdown_tracing() {
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            error!("Error exporting the traces: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_new_tracer_provider() {
+    use crate::tools::api_span;
+    use hyper::server::conn::Http;
+    use hyper::service::service_fn;
+    use hyper::{Body, Request, Response};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tracing::Instrument;
+    use tracing_subscriber::Registry;
+
+    // A collector stand-in, forwarding the bodies posted to `/v1/traces`.
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let sender = sender.clone();
+            tokio::spawn(Http::new().serve_connection(
+                stream,
+                service_fn(move |request: Request<Body>| {
+                    let sender = sender.clone();
+                    async move {
+                        let path = request.uri().path().to_owned();
+                        let body = hyper::body::to_bytes(request.into_body()).await?;
+                        let _ = sender.send((path, body));
+                        Ok::<_, hyper::Error>(Response::new(Body::empty()))
+                    }
+                }),
+            ));
+        }
+    });
+
+    let provider = new_tracer_provider(&endpoint).unwrap();
+    let subscriber = Registry::default()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(CONTROLLER_NAME)));
+    let _guard = tracing::subscriber::set_default(subscriber);
+    async {}
+        .instrument(api_span("get", "Certificate", "cert-manager", "web-0-cert"))
+        .instrument(info_span!(
+            "reconcile",
+            namespace = "hello",
+            route = "world"
+        ))
+        .await;
+    let flushed = provider.clone();
+    let _ = tokio::task::spawn_blocking(move || flushed.force_flush())
+        .await
+        .unwrap();
+
+    let (path, body) = tokio::time::timeout(std::time::Duration::from_secs(10), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(path, "/v1/traces");
+    let contains = |needle: &str| {
+        body.windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    };
+    assert!(contains("get Certificate"));
+    assert!(contains("reconcile"));
+    assert!(contains(CONTROLLER_NAME));
+}
+
+/// Create the span of the reconciliation of a [`Route`].
+///
+/// The span carries the namespace, name (`route`) and host of the [`Route`], and the `Certificate` and `ClusterIssuer` it
+/// requests, if any. The `certificate` field is updated once the `Certificate` of a certificate group is resolved.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to reconcile.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// The [`Span`] of the reconciliation.
+///
+/// ### Example
+///
+/// ```rust
+/// reconcile(route.clone(), ctx.clone()).instrument(reconcile_span(&route, &ctx)).await
+/// ```
+pub fn reconcile_span(route: &Route, ctx: &ContextData) -> Span {
+    let defaults = ctx.get_issuer_defaults(route);
+    let certificate = match get_route_tls_source(route, &ctx.cert_manager_namespace, &defaults) {
+        Some(Ok(TlsSource::Certificate(namespace, name))) => {
+            Some(resource_to_string(&name, &namespace))
+        }
+        _ => None,
+    };
+    let issuer = get_route_cluster_issuer(route, &defaults);
+    info_span!(
+        "reconcile",
+        namespace = route.namespace().unwrap_or_default(),
+        route = route.name_any(),
+        host = route.spec.host.as_deref().unwrap_or_default(),
+        certificate = certificate.as_deref(),
+        issuer = issuer.as_deref(),
+    )
+}
@@ -1,89 +1,453 @@
+mod budget;
 pub mod certificate;
+mod config;
 pub mod crd;
 pub mod events;
+mod health;
+mod leader;
+mod logging;
+mod metrics;
+mod namespace;
+mod policy;
 pub mod route;
+pub mod secret;
 pub mod tools;
 pub mod types;
+mod webhook;
 
-use certificate::{annotate_cert, certificate_exists, create_certificate, is_cert_annotated};
-use crd::{certificate::Certificate, route::Route};
-use events::{error_event, success_event};
-use futures::StreamExt;
-use k8s_openapi::api::core::v1::ObjectReference;
+use budget::load_budget_state;
+use certificate::{
+    add_certificate_host, annotate_cert, certificate_covers_host, certificate_exists,
+    create_certificate, create_group_certificate, get_certificate, get_certificate_settings,
+    is_cert_annotated, may_prune_group_certificate, prune_group_certificate, release_certificates,
+    resolve_group_certificate,
+};
+use chrono::Utc;
+use clap::Parser;
+use config::{load_config, resolve_config, Args, CONFIG_MAP_KEY};
+use crd::{certificate::Certificate, policy::RoutesCertificatePolicy, route::Route};
+use events::{error_event, success_event, MAX_EVENT_NOTE_LIST_LEN};
+use futures::{channel::oneshot, FutureExt, StreamExt};
+use health::{record_activity, record_shutdown, HealthProbes};
+use k8s_openapi::api::core::v1::{ConfigMap, Namespace, Secret};
 use kube::{
     api::ListParams,
+    client::ClientBuilder,
     runtime::{
         controller::{Action, Controller},
         events::{Recorder, Reporter},
-        reflector::ObjectRef,
+        reflector::{self, reflector, ObjectRef},
+        watcher::{self, watcher},
+        WatchStreamExt,
     },
-    Api, Client, ResourceExt,
+    Api, Resource, ResourceExt,
+};
+use leader::{release_lease, run_leader_election};
+use logging::{init_logging, reconcile_span, shutdown_tracing};
+use metrics::{
+    observe_reconcile, record_issuer_audit, record_tls_patch, run_metrics, ApiErrorsLayer,
 };
+use policy::apply_policy_tls_settings;
 use route::{
-    add_finalizer, is_tls_up_to_date, is_valid_route, populate_route_tls, remove_finalizer,
+    add_finalizer, annotate_issuance_queued, get_mirrored_secret_names, get_route_cluster_issuer,
+    get_route_tls_settings, get_route_tls_source, is_passthrough_route, is_tls_acme_route,
+    is_tls_up_to_date, mark_tls_acme_migrated, populate_route_external_certificate,
+    populate_route_tls, remove_finalizer, validate_hostname,
+};
+use secret::{
+    can_route_use_source, is_secret_mirrored, mirror_secret, release_mirrored_secrets,
+    MANAGED_BY_LABEL_KEY,
 };
-use std::{sync::Arc, time::Duration};
-use tools::format_cert_name;
+use std::{
+    collections::HashSet,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tools::{
+    format_capped_list, get_backoff_duration, get_secret_tls_data, resource_to_string, scoped_api,
+};
+use tracing::{error, info, warn, Instrument, Span};
+use tracing_subscriber::EnvFilter;
 use types::*;
+use webhook::run_webhook;
 
-const REQUEUE_DEFAULT_INTERVAL: u64 = 3600;
-const REQUEUE_ERROR_DURATION_SLOW: u64 = 120;
 const REQUEUE_ERROR_DURATION_FAST: u64 = 5;
-const CONTROLLER_NAME: &'static str = "cert-manager-routes-controller";
-const CONTROLLER_POD_ENV: &'static str = "CONTROLLER_POD_NAME";
-pub const DEFAULT_CERT_MANAGER_NAMESPACE: &'static str = "cert-manager";
-pub const CERT_MANAGER_NAMESPACE_ENV: &'static str = "CERT_MANAGER_NAMESPACE";
-pub const CERT_ANNOTATION_KEY: &'static str = "cert-manager.io/routes";
-pub const CLUSTER_ISSUER_ANNOTATION_KEY: &'static str = "cert-manager.io/cluster-issuer";
-pub const FINALIZER: &'static str = "kubernetes";
+pub const CONTROLLER_NAME: &str = "cert-manager-routes-controller";
+const TLS_SECRET_FIELD_SELECTOR: &str = "type=kubernetes.io/tls";
+pub const CERT_ANNOTATION_KEY: &str = "cert-manager.io/routes";
+pub const CLUSTER_ISSUER_ANNOTATION_KEY: &str = "cert-manager.io/cluster-issuer";
+pub const ROUTE_TERMINATION_ANNOTATION_KEY: &str = "cert-manager.io/route-termination";
+pub const ROUTE_INSECURE_POLICY_ANNOTATION_KEY: &str = "cert-manager.io/route-insecure-policy";
+pub const ROUTE_EXTERNAL_CERTIFICATE_ANNOTATION_KEY: &str =
+    "cert-manager.io/route-external-certificate";
+pub const CERTIFICATE_NAME_ANNOTATION_KEY: &str = "cert-manager.io/certificate-name";
+pub const TLS_ACME_ANNOTATION_KEY: &str = "kubernetes.io/tls-acme";
+pub const TLS_ACME_MIGRATED_ANNOTATION_KEY: &str = "cert-manager.io/tls-acme-migrated";
+pub const NAMESPACE_MANAGE_ROUTES_ANNOTATION_KEY: &str = "cert-manager.io/manage-routes";
+pub const ROUTE_OPT_OUT_ANNOTATION_KEY: &str = "cert-manager.io/opt-out";
+pub const CERT_DURATION_ANNOTATION_KEY: &str = "cert-manager.io/duration";
+pub const CERT_RENEW_BEFORE_ANNOTATION_KEY: &str = "cert-manager.io/renew-before";
+pub const CERT_PRIVATE_KEY_ALGORITHM_ANNOTATION_KEY: &str = "cert-manager.io/private-key-algorithm";
+pub const CERT_PRIVATE_KEY_SIZE_ANNOTATION_KEY: &str = "cert-manager.io/private-key-size";
+pub const ALLOWED_ISSUER_LABEL_PREFIX: &str = "allowed-issuers.cert-manager.io/";
+pub const CERTIFICATE_MODE_ANNOTATION_KEY: &str = "cert-manager.io/certificate-mode";
+pub const CERTIFICATE_GROUP_ANNOTATION_KEY: &str = "cert-manager.io/certificate-group";
+pub const CERTIFICATE_GROUP_NAMESPACE_LABEL_KEY: &str =
+    "cert-manager.io/certificate-group-namespace";
+pub const ISSUANCE_QUEUED_ANNOTATION_KEY: &str = "cert-manager.io/issuance-queued";
+pub const TLS_SECRET_ANNOTATION_KEY: &str = "cert-manager.io/tls-secret";
+pub const SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY: &str =
+    "cert-manager.io/allowed-route-namespaces";
+pub const MIRROR_SECRET_ANNOTATION_KEY: &str = "cert-manager.io/mirror-secret-name";
 
 /// The main function initializes the controller and runs it in a multi-threaded context.
 ///
-/// The controller watches for [`Route`] and matching [`Certificate`] events.
+/// The controller watches for [`Route`] and matching [`Certificate`] and TLS [`Secret`] events.
 #[tokio::main]
 async fn main() -> Result<(), kube::Error> {
-    let cert_manager_namespace = std::env::var(CERT_MANAGER_NAMESPACE_ENV)
-        .unwrap_or(DEFAULT_CERT_MANAGER_NAMESPACE.to_owned());
+    let args = Args::parse();
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1)
+        }
+    };
+    if let Err(e) = init_logging(
+        config.log_format,
+        EnvFilter::new(&config.log_level),
+        config.otlp_endpoint.as_deref(),
+    ) {
+        eprintln!(
+            "Invalid `otlp_endpoint` `{}`: {}",
+            config.otlp_endpoint.as_deref().unwrap_or_default(),
+            e
+        );
+        std::process::exit(1)
+    }
+    info!(
+        "Loaded the configuration: {}",
+        serde_json::to_string(&config).unwrap_or_default()
+    );
+    let leader_identity = config.leader_identity();
 
-    let client = Client::try_default().await?;
+    let client = ClientBuilder::try_from(
+        kube::Config::infer()
+            .await
+            .map_err(kube::Error::InferConfig)?,
+    )?
+    .with_layer(&ApiErrorsLayer)
+    .build();
 
-    let reference = ObjectReference {
-        namespace: Some(cert_manager_namespace.clone()),
-        ..Default::default()
-    };
     let reporter = Reporter {
         controller: CONTROLLER_NAME.into(),
-        instance: std::env::var(CONTROLLER_POD_ENV).ok(),
+        instance: config.pod_name.clone(),
     };
-    let recorder = Recorder::new(client.clone(), reporter, reference);
 
-    let context = Arc::new(ContextData::new(client, cert_manager_namespace, recorder));
+    let (policies, policies_writer) = reflector::store::<RoutesCertificatePolicy>();
+    let (reconcile_all, reconcile_all_trigger) = futures::channel::mpsc::channel::<()>(1);
+    let mut policies_changed = reconcile_all.clone();
+    tokio::spawn(
+        reflector(
+            policies_writer,
+            watcher(
+                Api::<RoutesCertificatePolicy>::all(client.clone()),
+                watcher::Config::default(),
+            ),
+        )
+        .default_backoff()
+        .touched_objects()
+        .for_each(move |_| {
+            record_activity();
+            let _ = policies_changed.try_send(());
+            futures::future::ready(())
+        }),
+    );
 
-    Controller::new(
-        Api::<Route>::all(context.client.clone()),
-        Default::default(),
-    )
-    .watches(
-        Api::<Certificate>::all(context.client.clone()),
+    let mut certificates = vec![];
+    for scope in config.certificate_scopes() {
+        let (store, writer) = reflector::store::<Certificate>();
+        tokio::spawn(
+            reflector(
+                writer,
+                watcher(
+                    scoped_api::<Certificate>(client.clone(), scope.as_deref()),
+                    watcher::Config::default(),
+                ),
+            )
+            .default_backoff()
+            .for_each(|_| {
+                record_activity();
+                futures::future::ready(())
+            }),
+        );
+        certificates.push(store);
+    }
+
+    let mut mirrored_secrets = vec![];
+    for scope in config.watch_scopes() {
+        let (store, writer) = reflector::store::<Secret>();
+        tokio::spawn(
+            reflector(
+                writer,
+                watcher(
+                    scoped_api::<Secret>(client.clone(), scope.as_deref()),
+                    watcher::Config::default()
+                        .fields(TLS_SECRET_FIELD_SELECTOR)
+                        .labels(&format!("{}={}", MANAGED_BY_LABEL_KEY, CONTROLLER_NAME)),
+                ),
+            )
+            .default_backoff()
+            .for_each(|_| futures::future::ready(())),
+        );
+        mirrored_secrets.push(store);
+    }
+
+    let mut context = ContextData::new(
+        client,
+        reporter,
+        policies,
+        certificates.clone(),
+        mirrored_secrets,
+        &config,
+    );
+    if leader_identity.is_some() {
+        context = context.with_leader_election();
+    }
+    if let Some(budget) = config.issuance_budget() {
+        let state = load_budget_state(&context).await?;
+        context = context.with_issuance_budget(budget, state);
+    }
+    let context = Arc::new(context);
+
+    let scopes = config.watch_scopes();
+    for scope in &scopes {
+        let params = match scope {
+            Some(namespace) => ListParams::default().fields(&namespace_field_selector(namespace)),
+            None => ListParams::default(),
+        };
+        for namespace in Api::<Namespace>::all(context.client.clone())
+            .list(&params)
+            .await?
+        {
+            context.update_namespace(&namespace);
+        }
+    }
+    let _ = context.policies.wait_until_ready().await;
+
+    if let Some(identity) = leader_identity.clone() {
+        tokio::spawn(run_leader_election(
+            identity,
+            context.clone(),
+            reconcile_all.clone(),
+        ));
+    }
+
+    tokio::spawn(audit_issuers(context.clone()));
+
+    if let Some(name) = args.config_map.clone().filter(|name| !name.is_empty()) {
+        tokio::spawn(reload_config_on_change(
+            name,
+            args.clone(),
+            context.clone(),
+            reconcile_all,
+        ));
+    }
+
+    if let (Some(cert_file), Some(key_file)) = (
+        config.webhook_tls_cert_file.clone(),
+        config.webhook_tls_key_file.clone(),
+    ) {
+        let port = config.webhook_port;
+        let webhook_context = context.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_webhook(port, cert_file, key_file, webhook_context).await {
+                error!("Error running the admission webhook: {}", e);
+            }
+        });
+    }
+
+    let (shutdown, shutdown_trigger) = oneshot::channel::<()>();
+    tokio::spawn(shutdown_on_signal(
+        config.shutdown_deadline,
+        shutdown,
+        leader_identity.clone(),
+        context.clone(),
+    ));
+    let shutdown_trigger = shutdown_trigger.map(|_| ()).shared();
+
+    // Each scope has its own controller, which all reconcile every Route when asked to
+    let mut controllers = vec![];
+    let mut routes = vec![];
+    let mut reconcile_all_senders = vec![];
+    for scope in scopes {
+        let (sender, trigger) = futures::channel::mpsc::channel::<()>(1);
+        let controller = route_controller(scope, context.clone())
+            .graceful_shutdown_on(shutdown_trigger.clone())
+            .reconcile_all_on(trigger);
+        routes.push(controller.store());
+        reconcile_all_senders.push(sender);
+        controllers.push(controller);
+    }
+    tokio::spawn(reconcile_all_trigger.for_each(move |_| {
+        for sender in reconcile_all_senders.iter_mut() {
+            let _ = sender.try_send(());
+        }
+        futures::future::ready(())
+    }));
+
+    let metrics_port = config.metrics_port;
+    let probes = HealthProbes {
+        routes,
+        certificates,
+        liveness_window: config.liveness_window,
+    };
+    let metrics_context = context.clone();
+    record_activity();
+    tokio::spawn(async move {
+        if let Err(e) = run_metrics(metrics_port, probes, metrics_context).await {
+            error!("Error running the metrics server: {}", e);
+        }
+    });
+
+    let leader_context = context.clone();
+    futures::future::join_all(controllers.into_iter().map(|controller| {
+        controller
+            .run(observe_and_reconcile, error_policy, context.clone())
+            .for_each(|_| futures::future::ready(()))
+    }))
+    .await;
+
+    if let Some(identity) = leader_identity {
+        if let Err(e) = release_lease(&identity, &leader_context).await {
+            error!("Error releasing the leader Lease: {}", e);
+        }
+    }
+    info!("Controller stopped");
+    shutdown_tracing();
+    Ok(())
+}
+
+/// Create the controller of the [`Route`]s of a scope.
+///
+/// The controller watches the [`Route`]s of the scope, and the [`Certificate`]s and TLS [`Secret`]s they may use,
+/// which live either in the namespace of the [`Route`] or in the cert-manager namespace.
+///
+/// ### Arguments
+///
+/// * `scope` - The namespace watched by the controller, or [`None`] for the whole cluster.
+/// * `ctx` - The context of the controller.
+///
+/// ### Returns
+///
+/// The [`Controller`] of the [`Route`]s.
+///
+/// ### Example
+///
+/// ```rust
+/// let controller = route_controller(Some("team-a".to_owned()), ctx.clone());
+/// ```
+fn route_controller(scope: Option<String>, ctx: Arc<ContextData>) -> Controller<Route> {
+    let mut controller = Controller::new(
+        scoped_api::<Route>(ctx.client.clone(), scope.as_deref()),
         Default::default(),
-        |obj| match obj.annotations().get(CERT_ANNOTATION_KEY) {
-            Some(annotation) => annotation
-                .split(",")
-                .map(|s| {
-                    if let Some((namespace, name)) = s.split_once("/") {
-                        ObjectRef::new(name).within(namespace)
-                    } else {
-                        ObjectRef::new("")
-                    }
-                })
-                .collect::<Vec<_>>(),
-            None => vec![],
+    );
+    let namespaces = match &scope {
+        Some(namespace) if *namespace != ctx.cert_manager_namespace => vec![
+            Some(namespace.clone()),
+            Some(ctx.cert_manager_namespace.clone()),
+        ],
+        _ => vec![scope.clone()],
+    };
+
+    for namespace in namespaces {
+        let certificate_scope = scope.clone();
+        let secret_scope = scope.clone();
+        let routes_context = ctx.clone();
+        controller = controller
+            .watches(
+                scoped_api::<Certificate>(ctx.client.clone(), namespace.as_deref()),
+                Default::default(),
+                move |obj| match obj.annotations().get(CERT_ANNOTATION_KEY) {
+                    // A shared Certificate may be used by Routes outside of the scope
+                    Some(annotation) => annotation
+                        .split(",")
+                        .filter_map(|s| s.split_once("/"))
+                        .filter(|(namespace, _)| {
+                            certificate_scope
+                                .as_deref()
+                                .is_none_or(|scope| scope == *namespace)
+                        })
+                        .map(|(namespace, name)| ObjectRef::new(name).within(namespace))
+                        .collect::<Vec<_>>(),
+                    None => vec![],
+                },
+            )
+            .watches(
+                scoped_api::<Secret>(ctx.client.clone(), namespace.as_deref()),
+                watcher::Config::default().fields(TLS_SECRET_FIELD_SELECTOR),
+                move |secret| {
+                    routes_context
+                        .get_secret_routes(
+                            &secret.namespace().unwrap_or_default(),
+                            &secret.name_any(),
+                        )
+                        .into_iter()
+                        .filter(|route| {
+                            secret_scope
+                                .as_deref()
+                                .is_none_or(|scope| route.namespace.as_deref() == Some(scope))
+                        })
+                        .collect::<Vec<_>>()
+                },
+            );
+    }
+
+    let namespace_routes = controller.store();
+    let namespace_context = ctx.clone();
+    let namespace_config = match &scope {
+        Some(namespace) => watcher::Config::default().fields(&namespace_field_selector(namespace)),
+        None => watcher::Config::default(),
+    };
+    controller.watches(
+        Api::<Namespace>::all(ctx.client.clone()),
+        namespace_config,
+        move |namespace| {
+            namespace_context.update_namespace(&namespace);
+            namespace_routes
+                .state()
+                .into_iter()
+                .filter(|route| route.namespace() == namespace.metadata.name)
+                .map(|route| ObjectRef::from_obj(route.as_ref()))
+                .collect::<Vec<_>>()
         },
     )
-    .run(reconcile, error_policy, context)
-    .for_each(|_| futures::future::ready(()))
-    .await;
-    Ok(())
+}
+
+/// The field selector of a single `Namespace`, which a namespace-scoped controller may list and watch.
+fn namespace_field_selector(namespace: &str) -> String {
+    format!("metadata.name={}", namespace)
+}
+
+/// List the [`Route`]s of every scope watched by the controller.
+///
+/// ### Arguments
+///
+/// * `ctx` - The context of the controller.
+///
+/// ### Returns
+///
+/// The [`Route`]s of the watched scopes, or an error if any of them can't be listed.
+async fn list_routes(ctx: &ContextData) -> Result<Vec<Route>, kube::Error> {
+    let mut routes = vec![];
+    for scope in ctx.config().watch_scopes() {
+        routes.extend(
+            scoped_api::<Route>(ctx.client.clone(), scope.as_deref())
+                .list(&ListParams::default())
+                .await?,
+        );
+    }
+    Ok(routes)
 }
 
 /// The reconcile function is called for each [`Route`] event and related [`Certificate`] events by the main controller.
@@ -92,24 +456,54 @@ async fn main() -> Result<(), kube::Error> {
 /// the route will be removed from the [`Certificate`] annotation if it exists.
 ///
 /// Else, it checks if the [`Route`] is valid,
-/// if a [`Certificate`] exists for the [`Route`]'s hostname,
+/// if a [`Certificate`] exists for the [`Route`]'s hostname (unless it uses an existing [`Secret`]),
 /// if the [`Certificate`] is annotated with the [`Route`]'s name and namespace
 /// and if the [`Route`]'s TLS is up to date.
 ///
+/// Each failure publishes an error event and returns an [`Error`], whose [`ErrorClass`] decides when
+/// the [`error_policy()`] retries the [`Route`].
+///
+/// Standby replicas don't reconcile the [`Route`]s until they acquire the leader `Lease`,
+/// at which point every [`Route`] is reconciled again. The [`Route`]s outside of the watched namespaces are ignored.
+///
 /// This function is idempotent.
 async fn reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, Error> {
-    let mut remove_annotation: bool = false;
+    if !ctx.is_leader() || !ctx.is_namespace_watched(&route.namespace().unwrap_or_default()) {
+        return Ok(Action::await_change());
+    }
+    let remove_annotation =
+        route.metadata.deletion_timestamp.is_some() && route.metadata.finalizers.as_ref().is_some();
+    let issuer_defaults = ctx.get_issuer_defaults(&route);
 
-    if route.metadata.deletion_timestamp.is_some() && route.metadata.finalizers.as_ref().is_some() {
-        remove_annotation = true;
+    let is_managed = !remove_annotation
+        && get_route_tls_source(&route, &ctx.cert_manager_namespace, &issuer_defaults).is_some();
+    ctx.index_route_secret(
+        &route,
+        match get_route_tls_source(&route, &ctx.cert_manager_namespace, &issuer_defaults) {
+            Some(Ok(TlsSource::Secret(namespace, name))) if !remove_annotation => {
+                Some((namespace, name))
+            }
+            _ => None,
+        },
+    );
 
+    if !is_managed && (remove_annotation || route.finalizers().contains(&ctx.finalizer)) {
+        release_unused_certificates(&route, None, &ctx).await?;
+        release_unused_secrets(&route, &HashSet::new(), &ctx).await?;
+        release_issuance_queue(&route, &ctx).await?;
+    }
+
+    // The finalizer is only removed once every cleanup succeeded, so that a failed cleanup is retried
+    if remove_annotation {
         match remove_finalizer(&route, &ctx).await {
             Ok(_) => {
+                ctx.reset_failures(&route);
                 success_event(
                     "Patch".to_owned(),
                     "RouteDeletion".to_owned(),
                     Some(format!("Removed finalizer from Route `{}`", &route)),
-                    &ctx.recorder.clone(),
+                    None,
+                    &ctx.route_recorder(&route),
                 )
                 .await
             }
@@ -121,133 +515,646 @@ async fn reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, E
                         "Error removing finalizer from Route `{}`: {}",
                         &route, e
                     )),
-                    &ctx.recorder.clone(),
+                    None,
+                    &ctx.route_recorder(&route),
                 )
                 .await;
-                return Ok(Action::requeue(Duration::from_secs(
-                    REQUEUE_ERROR_DURATION_SLOW,
-                )));
+                return Err(e);
             }
         }
     }
 
-    if (remove_annotation
-        || route
-            .annotations()
-            .get(CLUSTER_ISSUER_ANNOTATION_KEY)
-            .is_none())
-        && route.spec.host.as_ref().is_some()
-    {
-        let cert_name = format_cert_name(&route.spec.host.as_ref().unwrap());
-        if certificate_exists(&cert_name, &ctx).await
-            && is_cert_annotated(&cert_name, &route, &ctx)
+    if let (false, Some(source)) = (
+        remove_annotation,
+        get_route_tls_source(&route, &ctx.cert_manager_namespace, &issuer_defaults),
+    ) {
+        let source = match source {
+            Ok(source) => source,
+            Err(e) => {
+                error_event(
+                    "Validate".to_owned(),
+                    "InvalidTLSSecret".to_owned(),
+                    Some(format!("Invalid TLS Secret for Route `{}`: {}", &route, e)),
+                    None,
+                    &ctx.route_recorder(&route),
+                )
+                .await;
+                return Err(Error::InvalidRoute(route.to_string(), e));
+            }
+        };
+
+        // The TLS of a passthrough Route is terminated by its backend, so it is left alone until it changes
+        if is_passthrough_route(&route) {
+            error_event(
+                "Validate".to_owned(),
+                "PassthroughRoute".to_owned(),
+                Some(format!(
+                    "Skipped Route `{}` since its `passthrough` TLS is terminated by the backend, set the `{}` annotation to manage it",
+                    &route, ROUTE_TERMINATION_ANNOTATION_KEY
+                )),
+                None,
+                &ctx.route_recorder(&route),
+            )
+            .await;
+            return Ok(Action::await_change());
+        }
+
+        let policy_rule = ctx.get_route_policy_rule(&route);
+        let mut tls_settings =
+            match apply_policy_tls_settings(policy_rule.as_ref(), &ctx.config().tls_settings())
+                .and_then(|defaults| get_route_tls_settings(&route, &defaults))
+            {
+                Ok(settings) => settings,
+                Err(e) => {
+                    error_event(
+                        "Validate".to_owned(),
+                        "InvalidRouteTLSSettings".to_owned(),
+                        Some(format!(
+                            "Invalid TLS settings for Route `{}`: {}",
+                            &route, e
+                        )),
+                        None,
+                        &ctx.route_recorder(&route),
+                    )
+                    .await;
+                    return Err(Error::InvalidRoute(route.to_string(), e));
+                }
+            };
+
+        let group = match &source {
+            TlsSource::Certificate(_, _) => match ctx.get_certificate_group(&route) {
+                Ok(group) => group,
+                Err(e) => {
+                    error_event(
+                        "Validate".to_owned(),
+                        "InvalidCertificateGroup".to_owned(),
+                        Some(format!(
+                            "Invalid certificate group for Route `{}`: {}",
+                            &route, e
+                        )),
+                        None,
+                        &ctx.route_recorder(&route),
+                    )
+                    .await;
+                    return Err(Error::InvalidAnnotation(
+                        CERTIFICATE_GROUP_ANNOTATION_KEY.to_owned(),
+                        e,
+                    ));
+                }
+            },
+            _ => None,
+        };
+        let mut group_certificate = None;
+        let source = match &group {
+            Some(group) => {
+                let issuer = get_route_cluster_issuer(&route, &issuer_defaults).unwrap_or_default();
+                match resolve_group_certificate(
+                    group,
+                    &issuer,
+                    route.spec.host.as_deref().unwrap_or_default(),
+                    &ctx,
+                )
                 .await
-                .unwrap_or(true)
-        {
-            match annotate_cert(&cert_name, &route, &ctx, false).await {
-                Ok(certificate) => {
-                    success_event(
-                        "Patch".to_owned(),
-                        "UnmanageRoute".to_owned(),
+                {
+                    Ok((cert_name, certificate)) => {
+                        group_certificate = certificate;
+                        Span::current().record(
+                            "certificate",
+                            resource_to_string(&cert_name, &ctx.cert_manager_namespace),
+                        );
+                        TlsSource::Certificate(ctx.cert_manager_namespace.clone(), cert_name)
+                    }
+                    Err(e) => {
+                        error_event(
+                            "Get".to_owned(),
+                            "CertificateGroup".to_owned(),
+                            Some(format!(
+                                "Error resolving the Certificate of group `{}` for Route `{}`: {}",
+                                group, &route, e
+                            )),
+                            None,
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(e);
+                    }
+                }
+            }
+            None => source,
+        };
+
+        match &source {
+            TlsSource::Certificate(namespace, cert_name)
+                if route
+                    .annotations()
+                    .contains_key(CERTIFICATE_NAME_ANNOTATION_KEY) =>
+            {
+                // Referencing a Certificate of another namespace would hand its private key over to the Route
+                match can_route_use_source(&source, &route, &ctx).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        error_event(
+                            "Validate".to_owned(),
+                            "ForbiddenCertificate".to_owned(),
+                            Some(format!(
+                                "Route `{}` isn't allowed to use {}, the Certificate must be annotated with `{}`",
+                                &route, &source, SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY
+                            )),
+                            Some(source.object_ref()),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(Error::InvalidRoute(
+                            route.to_string(),
+                            format!("{} isn't shared with its namespace", &source),
+                        ));
+                    }
+                    Err(e) => {
+                        error_event(
+                            "Get".to_owned(),
+                            "MissingCertificate".to_owned(),
+                            Some(format!(
+                                "Error getting {} referenced by Route `{}`: {}",
+                                &source, &route, e
+                            )),
+                            Some(source.object_ref()),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(e.into());
+                    }
+                }
+                let hostname = route.spec.host.clone().unwrap_or_default();
+                match get_certificate(namespace, cert_name, &ctx).await {
+                    Ok(certificate) if !certificate_covers_host(&certificate, &hostname) => {
+                        error_event(
+                            "Validate".to_owned(),
+                            "CertificateHostMismatch".to_owned(),
+                            Some(format!(
+                                "Certificate `{}` referenced by Route `{}` doesn't cover host `{}`",
+                                &certificate, &route, &hostname
+                            )),
+                            Some(certificate.object_ref(&())),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(Error::InvalidRoute(
+                            route.to_string(),
+                            format!("{} doesn't cover host `{}`", &source, &hostname),
+                        ));
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error_event(
+                            "Get".to_owned(),
+                            "MissingCertificate".to_owned(),
+                            Some(format!(
+                                "Error getting Certificate `{}/{}` referenced by Route `{}`: {}",
+                                &namespace, &cert_name, &route, e
+                            )),
+                            Some(source.object_ref()),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(e);
+                    }
+                }
+            }
+            TlsSource::Certificate(namespace, cert_name)
+                if match (&group, &group_certificate) {
+                    (Some(_), Some(certificate)) => !certificate_covers_host(
+                        certificate,
+                        route.spec.host.as_deref().unwrap_or_default(),
+                    ),
+                    (Some(_), None) => true,
+                    (None, _) => !certificate_exists(namespace, cert_name, &ctx).await,
+                } =>
+            {
+                if let Err(e) = validate_hostname(route.spec.host.as_deref().unwrap_or_default()) {
+                    error_event(
+                        "Validate".to_owned(),
+                        "InvalidHost".to_owned(),
+                        Some(format!("Invalid host for Route `{}`: {}", &route, e)),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
+                    )
+                    .await;
+                    return Err(Error::InvalidRoute(route.to_string(), e));
+                }
+                if let Some(issuer) = ctx.get_forbidden_issuer(&route) {
+                    error_event(
+                        "Validate".to_owned(),
+                        "ForbiddenIssuer".to_owned(),
                         Some(format!(
-                            "Removed  Route `{}` from Certificate `{}` annotation",
-                            &route, &certificate
+                            "ClusterIssuer `{}` requested by Route `{}` isn't allowed in its namespace",
+                            &issuer, &route
                         )),
-                        &ctx.recorder.clone(),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
                     )
-                    .await
+                    .await;
+                    return Err(Error::InvalidRoute(
+                        route.to_string(),
+                        format!("ClusterIssuer `{}` isn't allowed in its namespace", issuer),
+                    ));
+                }
+                let settings = match get_certificate_settings(
+                    &route,
+                    policy_rule
+                        .as_ref()
+                        .and_then(|rule| rule.certificate.as_ref()),
+                ) {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        error_event(
+                            "Validate".to_owned(),
+                            "InvalidCertificateSettings".to_owned(),
+                            Some(format!(
+                                "Invalid Certificate settings for Route `{}`: {}",
+                                &route, e
+                            )),
+                            Some(source.object_ref()),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(Error::InvalidRoute(route.to_string(), e));
+                    }
+                };
+                if let Some(issuer) = get_route_cluster_issuer(&route, &issuer_defaults) {
+                    match ctx.acquire_issuance(&route, &issuer).await {
+                        Ok(None) => {}
+                        Ok(Some(ready_at)) => {
+                            if let Err(e) =
+                                annotate_issuance_queued(&route, Some(ready_at), &ctx).await
+                            {
+                                error_event(
+                                    "Patch".to_owned(),
+                                    "IssuanceQueued".to_owned(),
+                                    Some(format!(
+                                        "Error annotating queued Route `{}`: {}",
+                                        &route, e
+                                    )),
+                                    Some(source.object_ref()),
+                                    &ctx.route_recorder(&route),
+                                )
+                                .await
+                            }
+                            success_event(
+                                "Queue".to_owned(),
+                                "IssuanceQueued".to_owned(),
+                                Some(format!(
+                                    "Certificate `{}/{}` requested by Route `{}` is queued until {} by the issuance budget of ClusterIssuer `{}`",
+                                    &namespace, &cert_name, &route, ready_at.to_rfc3339(), &issuer
+                                )),
+                                Some(source.object_ref()),
+                                &ctx.route_recorder(&route),
+                            )
+                            .await;
+                            return Ok(Action::requeue(Duration::from_secs(
+                                (ready_at - Utc::now())
+                                    .num_seconds()
+                                    .max(REQUEUE_ERROR_DURATION_FAST as i64)
+                                    as u64,
+                            )));
+                        }
+                        Err(e) => {
+                            error_event(
+                                "Patch".to_owned(),
+                                "IssuanceBudget".to_owned(),
+                                Some(format!(
+                                    "Error saving the issuance budget for Route `{}`: {}",
+                                    &route, e
+                                )),
+                                Some(source.object_ref()),
+                                &ctx.route_recorder(&route),
+                            )
+                            .await;
+                            return Err(e.into());
+                        }
+                    }
+                }
+                let created = match (&group, &group_certificate) {
+                    (Some(_), Some(certificate)) => {
+                        add_certificate_host(
+                            certificate,
+                            route.spec.host.as_deref().unwrap_or_default(),
+                            &ctx,
+                        )
+                        .await
+                    }
+                    (Some(group), None) => {
+                        create_group_certificate(&route, cert_name, group, &settings, &ctx).await
+                    }
+                    (None, _) => create_certificate(&route, &settings, &ctx).await,
+                };
+                match created {
+                    Ok(certificate) if group_certificate.is_some() => {
+                        success_event(
+                            "Patch".to_owned(),
+                            "MissingCertificate".to_owned(),
+                            Some(format!(
+                                "Added the host of Route `{}` to Certificate `{}`",
+                                &route, &certificate
+                            )),
+                            Some(certificate.object_ref(&())),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await
+                    }
+                    Ok(certificate) => {
+                        success_event(
+                            "Create".to_owned(),
+                            "MissingCertificate".to_owned(),
+                            Some(format!(
+                                "Created Certificate `{}` requested by Route `{}`",
+                                &certificate, &route
+                            )),
+                            Some(certificate.object_ref(&())),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await
+                    }
+                    Err(e) => {
+                        error_event(
+                            "Patch".to_owned(),
+                            "MissingCertificate".to_owned(),
+                            Some(format!(
+                                "Error creating Certificate `{}/{}` requested by Route `{}`: {}",
+                                &namespace, &cert_name, &route, e
+                            )),
+                            Some(source.object_ref()),
+                            &ctx.route_recorder(&route),
+                        )
+                        .await;
+                        return Err(e);
+                    }
+                }
+            }
+            TlsSource::Secret(_, _) => match can_route_use_source(&source, &route, &ctx).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    error_event(
+                        "Validate".to_owned(),
+                        "ForbiddenTLSSecret".to_owned(),
+                        Some(format!(
+                            "Route `{}` isn't allowed to use {}, the Secret must be annotated with `{}`",
+                            &route, &source, SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY
+                        )),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
+                    )
+                    .await;
+                    return Err(Error::InvalidRoute(
+                        route.to_string(),
+                        format!("{} isn't shared with its namespace", &source),
+                    ));
                 }
                 Err(e) => {
                     error_event(
-                        "Patch".to_owned(),
-                        "UnmanageRoute".to_owned(),
+                        "Get".to_owned(),
+                        "ForbiddenTLSSecret".to_owned(),
                         Some(format!(
-                            "Error removing Route `{}` from Certificate `{}/{}` annotation: {}",
-                            &ctx.cert_manager_namespace, &cert_name, &route, e
+                            "Error getting {} requested by Route `{}`: {}",
+                            &source, &route, e
                         )),
-                        &ctx.recorder.clone(),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
                     )
                     .await;
-                    return Ok(Action::requeue(Duration::from_secs(
-                        REQUEUE_ERROR_DURATION_SLOW,
-                    )));
+                    return Err(e.into());
                 }
+            },
+            _ => {}
+        }
+
+        release_issuance_queue(&route, &ctx).await?;
+
+        let keep = match &source {
+            TlsSource::Certificate(namespace, cert_name) => {
+                Some((namespace.as_str(), cert_name.as_str()))
             }
+            TlsSource::Secret(_, _) => None,
+        };
+        release_unused_certificates(&route, keep, &ctx).await?;
+
+        let issuer = get_route_cluster_issuer(&route, &issuer_defaults);
+        let is_tls_acme = is_tls_acme_route(&route) && issuer.is_some();
+        // Keep the existing TLS of a Route migrating to a new Certificate, e.g. from openshift-acme
+        // or between the shared and dedicated modes, until the Certificate is issued
+        let is_migration_pending =
+            matches!(source, TlsSource::Certificate(_, _))
+                && route.spec.tls.as_ref().is_some_and(|tls| {
+                    tls.certificate.is_some() || tls.external_certificate.is_some()
+                })
+                && get_secret_tls_data(&source, &ctx).await.is_err();
+        if is_migration_pending {
+            success_event(
+                "Migrate".to_owned(),
+                if is_tls_acme {
+                    "TlsAcmeMigration".to_owned()
+                } else {
+                    "CertificateMigration".to_owned()
+                },
+                Some(format!(
+                    "Keeping the existing TLS of Route `{}` until {} is issued",
+                    &route, &source
+                )),
+                Some(source.object_ref()),
+                &ctx.route_recorder(&route),
+            )
+            .await
+        }
+
+        if !ctx.external_certificate_supported.load(Ordering::Relaxed) {
+            tls_settings.external_certificate = false;
         }
-    } else if is_valid_route(&route) {
-        let hostname = route.spec.host.as_ref().unwrap();
-        let cert_name = format_cert_name(&hostname);
 
-        if !certificate_exists(&cert_name, &ctx).await {
-            match create_certificate(&route, &ctx).await {
-                Ok(certificate) => {
+        if !is_migration_pending
+            && tls_settings.external_certificate
+            && !is_tls_up_to_date(&route, &source, &tls_settings, &ctx)
+                .await
+                .unwrap_or(false)
+        {
+            match populate_route_external_certificate(&route, &source, &tls_settings, &ctx).await {
+                Ok(true) => {
+                    record_tls_patch("external");
                     success_event(
-                        "Create".to_owned(),
-                        "MissingCertificate".to_owned(),
+                        "Patch".to_owned(),
+                        "InvalidRouteTLS".to_owned(),
                         Some(format!(
-                            "Created Certificate `{}` requested by Route `{}`",
-                            &certificate, &route
+                            "Populated TLS for Route `{}` with an external certificate",
+                            &route
                         )),
-                        &ctx.recorder.clone(),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
+                    )
+                    .await
+                }
+                Ok(false) => {
+                    ctx.external_certificate_supported
+                        .store(false, Ordering::Relaxed);
+                    tls_settings.external_certificate = false;
+                    error_event(
+                        "Patch".to_owned(),
+                        "ExternalCertificateUnsupported".to_owned(),
+                        Some(format!(
+                            "The cluster doesn't support `spec.tls.externalCertificate`, falling back to inline TLS for Route `{}`",
+                            &route
+                        )),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
                     )
                     .await
                 }
                 Err(e) => {
                     error_event(
                         "Patch".to_owned(),
-                        "MissingCertificate".to_owned(),
+                        "InvalidRouteTLS".to_owned(),
                         Some(format!(
-                            "Error creating Certificate `{}/{}` requested by Route `{}`: {}",
-                            &ctx.cert_manager_namespace, &cert_name, &route, e
+                            "Error populating TLS for Route `{}` with an external certificate: {}",
+                            &route, e
                         )),
-                        &ctx.recorder.clone(),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
                     )
                     .await;
-                    return Ok(Action::requeue(Duration::from_secs(
-                        REQUEUE_ERROR_DURATION_SLOW,
-                    )));
+                    return Err(e);
                 }
             }
         }
 
-        match is_tls_up_to_date(&route, &cert_name, &ctx).await {
-            Ok(false) | Err(_) => match populate_route_tls(&route, &cert_name, &ctx).await {
+        if !is_migration_pending && !tls_settings.external_certificate {
+            match is_tls_up_to_date(&route, &source, &tls_settings, &ctx).await {
+                Ok(false) | Err(_) => {
+                    match populate_route_tls(&route, &source, &tls_settings, &ctx).await {
+                        Ok(_) => {
+                            record_tls_patch("inline");
+                            success_event(
+                                "Patch".to_owned(),
+                                "InvalidRouteTLS".to_owned(),
+                                Some(format!("Populated TLS for Route `{}`", &route)),
+                                Some(source.object_ref()),
+                                &ctx.route_recorder(&route),
+                            )
+                            .await
+                        }
+                        Err(e) => {
+                            error_event(
+                                "Patch".to_owned(),
+                                "InvalidRouteTLS".to_owned(),
+                                Some(format!(
+                                    "Error populating TLS for Route `{}`: {}",
+                                    &route, e
+                                )),
+                                Some(source.object_ref()),
+                                &ctx.route_recorder(&route),
+                            )
+                            .await;
+                            return Err(e);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if is_tls_acme
+            && !is_migration_pending
+            && !route
+                .annotations()
+                .contains_key(TLS_ACME_MIGRATED_ANNOTATION_KEY)
+        {
+            match mark_tls_acme_migrated(&route, &ctx).await {
                 Ok(_) => {
                     success_event(
-                        "Patch".to_owned(),
-                        "InvalidRouteTLS".to_owned(),
-                        Some(format!("Populated TLS for Route `{}`", &route)),
-                        &ctx.recorder.clone(),
+                        "Migrate".to_owned(),
+                        "TlsAcmeMigration".to_owned(),
+                        Some(format!(
+                            "Migrated Route `{}` from `{}` to {} issued by ClusterIssuer `{}`",
+                            &route,
+                            TLS_ACME_ANNOTATION_KEY,
+                            &source,
+                            issuer.unwrap_or_default()
+                        )),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
                     )
                     .await
                 }
                 Err(e) => {
                     error_event(
-                        "Patch".to_owned(),
-                        "InvalidRouteTLS".to_owned(),
+                        "Migrate".to_owned(),
+                        "TlsAcmeMigration".to_owned(),
                         Some(format!(
-                            "Error populating TLS for Route `{}`: {}",
-                            &route, e
+                            "Error marking Route `{}` as migrated from `{}`: {}",
+                            &route, TLS_ACME_ANNOTATION_KEY, e
                         )),
-                        &ctx.recorder.clone(),
+                        Some(source.object_ref()),
+                        &ctx.route_recorder(&route),
                     )
                     .await;
-                    return Ok(Action::requeue(Duration::from_secs(
-                        REQUEUE_ERROR_DURATION_SLOW,
-                    )));
+                    return Err(e);
                 }
-            },
-            _ => {}
+            }
+        }
+
+        if let (false, Some(secret_name)) = (
+            is_migration_pending,
+            route.annotations().get(MIRROR_SECRET_ANNOTATION_KEY),
+        ) {
+            match is_secret_mirrored(&source, &route, secret_name, &ctx).await {
+                Ok(false) | Err(_) => {
+                    match mirror_secret(&source, &route, secret_name, &ctx).await {
+                        Ok(_) => {
+                            success_event(
+                                "Apply".to_owned(),
+                                "OutdatedMirroredSecret".to_owned(),
+                                Some(format!(
+                                    "Mirrored Secret `{}/{}` for Route `{}`",
+                                    &route.namespace().unwrap_or_default(),
+                                    &secret_name,
+                                    &route
+                                )),
+                                Some(source.object_ref()),
+                                &ctx.route_recorder(&route),
+                            )
+                            .await
+                        }
+                        Err(e) => {
+                            error_event(
+                                "Apply".to_owned(),
+                                "OutdatedMirroredSecret".to_owned(),
+                                Some(format!(
+                                    "Error mirroring Secret `{}/{}` for Route `{}`: {}",
+                                    &route.namespace().unwrap_or_default(),
+                                    &secret_name,
+                                    &route,
+                                    e
+                                )),
+                                Some(source.object_ref()),
+                                &ctx.route_recorder(&route),
+                            )
+                            .await;
+                            return Err(e);
+                        }
+                    }
+                }
+                _ => {}
+            }
         }
 
-        if !route.finalizers().contains(&FINALIZER.to_string()) {
+        let mirrored_secrets = get_mirrored_secret_names(&route, &tls_settings);
+        release_unused_secrets(&route, &mirrored_secrets, &ctx).await?;
+
+        if !route.finalizers().contains(&ctx.finalizer) {
             match add_finalizer(&route, &ctx).await {
                 Ok(_) => {
                     success_event(
                         "Patch".to_owned(),
                         "MissingRouteFinalizer".to_owned(),
                         Some(format!("Added finalizer to Route `{}`", &route)),
-                        &ctx.recorder.clone(),
+                        None,
+                        &ctx.route_recorder(&route),
                     )
                     .await
                 }
@@ -259,28 +1166,34 @@ async fn reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, E
                             "Error adding finalizer to Route `{}`: {}",
                             &route, e
                         )),
-                        &ctx.recorder.clone(),
+                        None,
+                        &ctx.route_recorder(&route),
                     )
                     .await;
-                    return Ok(Action::requeue(Duration::from_secs(
-                        REQUEUE_ERROR_DURATION_SLOW,
-                    )));
+                    return Err(e);
                 }
             }
         }
+
+        if let (Some(_), TlsSource::Certificate(namespace, cert_name)) = (&group, &source) {
+            annotate_group_certificate(&route, namespace, cert_name, &ctx).await?;
+        }
     }
 
     // Ensure that each managed certificate is correclty annotated
-    for route in Api::<Route>::all(ctx.client.clone())
-        .list(&ListParams::default())
-        .await
-        .unwrap()
-    {
-        if is_valid_route(&route) {
-            let cert_name = format_cert_name(&route.spec.host.as_ref().unwrap());
-            match is_cert_annotated(&cert_name, &route, &ctx).await {
+    for route in list_routes(&ctx).await? {
+        // The Certificates of the certificate groups are annotated by the reconciliation of their Routes
+        if !matches!(ctx.get_certificate_group(&route), Ok(None)) {
+            continue;
+        }
+        if let Some(Ok(TlsSource::Certificate(namespace, cert_name))) = get_route_tls_source(
+            &route,
+            &ctx.cert_manager_namespace,
+            &ctx.get_issuer_defaults(&route),
+        ) {
+            match is_cert_annotated(&namespace, &cert_name, &route, &ctx).await {
                 Ok(false) | Err(_) => {
-                    match annotate_cert(&cert_name, &route, &ctx, true).await {
+                    match annotate_cert(&namespace, &cert_name, &route, &ctx, true).await {
                         Ok(certificate) => {
                             success_event(
                                 "Patch".to_owned(),
@@ -289,7 +1202,8 @@ async fn reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, E
                                     "Annotated Certificate `{}` for Route `{}`",
                                     &certificate, &route
                                 )),
-                                &ctx.recorder.clone(),
+                                Some(certificate.object_ref(&())),
+                                &ctx.route_recorder(&route),
                             )
                             .await
                         }
@@ -297,12 +1211,10 @@ async fn reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, E
                             error_event(
                             "Patch".to_owned(), 
                             "MissingRouteInCertificateAnnotation".to_owned(), 
-                            Some(format!("Error annotating Certificate `{}/{}` requested by Route `{}`: {}", &ctx.cert_manager_namespace, &cert_name, &route, e)),
-                            &ctx.recorder.clone()
-                        ).await;
-                            return Ok(Action::requeue(Duration::from_secs(
-                                REQUEUE_ERROR_DURATION_SLOW,
-                            )));
+                            Some(format!("Error annotating Certificate `{}/{}` requested by Route `{}`: {}", &namespace, &cert_name, &route, e)),
+                            Some(TlsSource::Certificate(namespace.clone(), cert_name.clone()).object_ref()),
+                            &ctx.route_recorder(&route)).await;
+                            return Err(e);
                         }
                     }
                 }
@@ -311,13 +1223,466 @@ async fn reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, E
         }
     }
 
-    Ok(Action::requeue(Duration::from_secs(
-        REQUEUE_DEFAULT_INTERVAL,
-    )))
+    Ok(Action::requeue(ctx.config().requeue_interval))
 }
 
-/// The error policy function is called by the controller when an unexpected error occurs during the reconcile function.
-fn error_policy(route: Arc<Route>, err: &Error, _ctx: Arc<ContextData>) -> Action {
-    eprintln!("Error reconciling Route `{}`: {}", &route, err);
-    Action::requeue(Duration::from_secs(REQUEUE_ERROR_DURATION_FAST))
+/// Periodically audit the `ClusterIssuer`s requested by all of the watched [`Route`]s.
+///
+/// Publishes a warning event listing the [`Route`]s that request a `ClusterIssuer` their namespace isn't allowed to use,
+/// every `issuer_audit_interval` of the live [`Config`](config::Config), if any. The list is capped to fit in the
+/// event, while the number of allowed and forbidden requests is recorded in the metrics.
+/// Only the leader replica audits the [`Route`]s, and only while the `ClusterIssuer`s are restricted.
+async fn audit_issuers(ctx: Arc<ContextData>) {
+    loop {
+        let interval = ctx.config().issuer_audit_interval;
+        if !ctx.is_leader() || ctx.config().allowed_issuers().is_none() {
+            tokio::time::sleep(interval).await;
+            continue;
+        }
+        match list_routes(&ctx).await {
+            Ok(routes) => {
+                let violations = routes
+                    .iter()
+                    .filter_map(|route| {
+                        ctx.get_forbidden_issuer(route)
+                            .map(|issuer| format!("Route `{}` requests `{}`", route, issuer))
+                    })
+                    .collect::<Vec<_>>();
+                record_issuer_audit(routes.len() - violations.len(), violations.len());
+                if !violations.is_empty() {
+                    error_event(
+                        "Audit".to_owned(),
+                        "IssuerAudit".to_owned(),
+                        Some(format!(
+                            "{} Routes request a forbidden ClusterIssuer: {}",
+                            violations.len(),
+                            format_capped_list(&violations, MAX_EVENT_NOTE_LIST_LEN)
+                        )),
+                        None,
+                        &ctx.recorder.clone(),
+                    )
+                    .await
+                }
+            }
+            Err(e) => {
+                error_event(
+                    "Audit".to_owned(),
+                    "IssuerAudit".to_owned(),
+                    Some(format!("Error listing Routes to audit: {}", e)),
+                    None,
+                    &ctx.recorder.clone(),
+                )
+                .await
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Reload the [`Config`](config::Config) whenever the `name` `ConfigMap` of the cert-manager namespace changes.
+///
+/// The `config.yaml` key of the `ConfigMap` replaces the configuration file, and is still overridden by the
+/// environment variables and flags of the `args`. A valid configuration is swapped into the [`ContextData`] and every
+/// [`Route`] is reconciled again with it, while an invalid one is rejected with a warning event on the `ConfigMap`.
+/// The settings that only apply at startup, such as the ports, keep their value until the controller restarts.
+async fn reload_config_on_change(
+    name: String,
+    args: Args,
+    ctx: Arc<ContextData>,
+    mut reconcile_all: futures::channel::mpsc::Sender<()>,
+) {
+    let config_maps = Api::<ConfigMap>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace);
+    let resource = resource_to_string(&name, &ctx.cert_manager_namespace);
+    let mut changes = watcher(
+        config_maps,
+        watcher::Config::default().fields(&format!("metadata.name={}", name)),
+    )
+    .default_backoff()
+    .applied_objects()
+    .boxed();
+    while let Some(change) = changes.next().await {
+        let config_map = match change {
+            Ok(config_map) => config_map,
+            Err(e) => {
+                error!("Error watching ConfigMap `{}`: {}", &resource, e);
+                continue;
+            }
+        };
+        let recorder = Recorder::new(
+            ctx.client.clone(),
+            ctx.reporter.clone(),
+            config_map.object_ref(&()),
+        );
+        let yaml = config_map
+            .data
+            .as_ref()
+            .and_then(|data| data.get(CONFIG_MAP_KEY))
+            .cloned()
+            .unwrap_or_default();
+        let current = ctx.config();
+        match resolve_config(&args, &yaml) {
+            Ok(new) => {
+                let (config, restart) = current.reload(&new);
+                if config != *current {
+                    let changed = current.get_changed_settings(&config);
+                    ctx.update_config(config);
+                    let _ = reconcile_all.try_send(());
+                    reload_event(
+                        "ConfigReloaded",
+                        format!(
+                            "Reloaded the settings {} from ConfigMap `{}`",
+                            format_capped_list(&changed, MAX_EVENT_NOTE_LIST_LEN),
+                            &resource
+                        ),
+                        false,
+                        &recorder,
+                        &ctx,
+                    )
+                    .await;
+                }
+                if !restart.is_empty() {
+                    reload_event(
+                        "ConfigRestartRequired",
+                        format!(
+                            "The settings {} of ConfigMap `{}` only apply once the controller restarts",
+                            format_capped_list(&restart, MAX_EVENT_NOTE_LIST_LEN),
+                            &resource
+                        ),
+                        true,
+                        &recorder,
+                        &ctx,
+                    )
+                    .await;
+                }
+            }
+            Err(e) => {
+                reload_event(
+                    "InvalidConfig",
+                    format!(
+                        "Rejected the configuration of ConfigMap `{}`, keeping the current one: {}",
+                        &resource, e
+                    ),
+                    true,
+                    &recorder,
+                    &ctx,
+                )
+                .await;
+            }
+        }
+    }
+}
+
+/// Publish an event about the reload of the configuration.
+///
+/// Every replica reloads the configuration, but only the leader publishes the events, the others only log them.
+async fn reload_event(
+    reason: &str,
+    note: String,
+    failed: bool,
+    recorder: &Recorder,
+    ctx: &ContextData,
+) {
+    match (ctx.is_leader(), failed) {
+        (true, false) => {
+            success_event(
+                "Reload".to_owned(),
+                reason.to_owned(),
+                Some(note),
+                None,
+                recorder,
+            )
+            .await
+        }
+        (true, true) => {
+            error_event(
+                "Reload".to_owned(),
+                reason.to_owned(),
+                Some(note),
+                None,
+                recorder,
+            )
+            .await
+        }
+        (false, false) => info!(action = "Reload", reason, "{}", note),
+        (false, true) => warn!(action = "Reload", reason, "{}", note),
+    }
+}
+
+/// Release the [`Certificate`]s a [`Route`] doesn't use anymore and publish the related events.
+///
+/// Returns an [`Error`] if the [`Certificate`]s couldn't be released.
+async fn release_unused_certificates(
+    route: &Route,
+    keep: Option<(&str, &str)>,
+    ctx: &ContextData,
+) -> Result<(), Error> {
+    match release_certificates(route, keep, ctx).await {
+        Ok(released) => {
+            for certificate in released {
+                success_event(
+                    "Patch".to_owned(),
+                    "UnmanageRoute".to_owned(),
+                    Some(format!(
+                        "Removed Route `{}` from Certificate `{}` annotation",
+                        &route, &certificate
+                    )),
+                    Some(certificate.object_ref(&())),
+                    &ctx.route_recorder(route),
+                )
+                .await
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error_event(
+                "Patch".to_owned(),
+                "UnmanageRoute".to_owned(),
+                Some(format!(
+                    "Error removing Route `{}` from the Certificates it doesn't use anymore: {}",
+                    &route, e
+                )),
+                None,
+                &ctx.route_recorder(route),
+            )
+            .await;
+            Err(e)
+        }
+    }
+}
+
+/// Release the mirrored [`Secret`]s a [`Route`] doesn't use anymore and publish the related events.
+///
+/// Returns an [`Error`] if the [`Secret`]s couldn't be released.
+async fn release_unused_secrets(
+    route: &Route,
+    keep: &HashSet<String>,
+    ctx: &ContextData,
+) -> Result<(), Error> {
+    match release_mirrored_secrets(route, keep, ctx).await {
+        Ok(released) => {
+            for secret_name in released {
+                success_event(
+                    "Delete".to_owned(),
+                    "UnusedMirroredSecret".to_owned(),
+                    Some(format!(
+                        "Released mirrored Secret `{}/{}` no longer used by Route `{}`",
+                        &route.namespace().unwrap_or_default(),
+                        &secret_name,
+                        &route
+                    )),
+                    None,
+                    &ctx.route_recorder(route),
+                )
+                .await
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error_event(
+                "Delete".to_owned(),
+                "UnusedMirroredSecret".to_owned(),
+                Some(format!(
+                    "Error releasing mirrored Secrets no longer used by Route `{}`: {}",
+                    &route, e
+                )),
+                None,
+                &ctx.route_recorder(route),
+            )
+            .await;
+            Err(e)
+        }
+    }
+}
+
+/// The error policy function is called by the controller when the reconcile function fails.
+///
+/// The [`Route`] is retried with an exponential backoff depending on the [`ErrorClass`] of the error
+/// and on its number of consecutive failures, see [`get_backoff_duration()`].
+/// Invalid configurations aren't retried until the [`Route`] or one of its related objects changes.
+fn error_policy(route: Arc<Route>, err: &Error, ctx: Arc<ContextData>) -> Action {
+    let _span = reconcile_span(&route, &ctx).entered();
+    let attempt = ctx.record_failure(&route);
+    let jitter = Utc::now().timestamp_subsec_nanos() as f64 / 1e9;
+    match get_backoff_duration(err.class(), attempt, jitter) {
+        Some(delay) => {
+            warn!(
+                outcome = "error",
+                "Error reconciling Route `{}`, retrying in {:?}: {}", &route, delay, err
+            );
+            Action::requeue(delay)
+        }
+        None => {
+            warn!(
+                outcome = "error",
+                "Error reconciling Route `{}`, waiting for a change: {}", &route, err
+            );
+            Action::await_change()
+        }
+    }
+}
+
+/// Reconcile a [`Route`] within its [`reconcile_span()`], record the metrics of the reconciliation
+/// and forget its failures once it succeeds.
+///
+/// The no-op reconciles of the standby replicas aren't recorded.
+async fn observe_and_reconcile(route: Arc<Route>, ctx: Arc<ContextData>) -> Result<Action, Error> {
+    if !ctx.is_leader() {
+        return reconcile(route, ctx).await;
+    }
+    let span = reconcile_span(&route, &ctx);
+    ctx.record_reconciling(&route, true);
+    let result = observe_reconcile(reconcile(route.clone(), ctx.clone()))
+        .instrument(span.clone())
+        .await;
+    ctx.record_reconciling(&route, false);
+    if result.is_ok() {
+        ctx.reset_failures(&route);
+        span.in_scope(|| info!(outcome = "success", "Reconciled Route `{}`", &route));
+    }
+    result
+}
+
+/// Remove a [`Route`] that no longer waits for the issuance budget from its queue and publish the related events.
+///
+/// Returns an [`Error`] if the [`Route`] couldn't be removed from the queue.
+async fn release_issuance_queue(route: &Route, ctx: &ContextData) -> Result<(), Error> {
+    let result = match ctx.dequeue_issuance(route).await {
+        Ok(_)
+            if route.metadata.deletion_timestamp.is_none()
+                && route
+                    .annotations()
+                    .contains_key(ISSUANCE_QUEUED_ANNOTATION_KEY) =>
+        {
+            annotate_issuance_queued(route, None, ctx).await
+        }
+        result => result.map_err(Error::from),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error_event(
+                "Patch".to_owned(),
+                "IssuanceQueued".to_owned(),
+                Some(format!(
+                    "Error removing Route `{}` from the issuance queue: {}",
+                    &route, e
+                )),
+                None,
+                &ctx.route_recorder(route),
+            )
+            .await;
+            Err(e)
+        }
+    }
+}
+
+/// Annotate the [`Certificate`] of a certificate group with a [`Route`] using it, prune its unused `dnsNames`
+/// and publish the related events.
+///
+/// Returns an [`Error`] if the [`Certificate`] couldn't be annotated or pruned.
+async fn annotate_group_certificate(
+    route: &Route,
+    namespace: &str,
+    cert_name: &str,
+    ctx: &ContextData,
+) -> Result<(), Error> {
+    let certificate = match is_cert_annotated(namespace, cert_name, route, ctx).await {
+        Ok(true) => get_certificate(namespace, cert_name, ctx).await,
+        Ok(false) | Err(_) => match annotate_cert(namespace, cert_name, route, ctx, true).await {
+            Ok(certificate) => {
+                success_event(
+                    "Patch".to_owned(),
+                    "MissingRouteInCertificateAnnotation".to_owned(),
+                    Some(format!(
+                        "Annotated Certificate `{}` for Route `{}`",
+                        &certificate, &route
+                    )),
+                    Some(certificate.object_ref(&())),
+                    &ctx.route_recorder(route),
+                )
+                .await;
+                Ok(certificate)
+            }
+            Err(e) => Err(e),
+        },
+    };
+    let result = match certificate {
+        Ok(certificate) if may_prune_group_certificate(&certificate) => {
+            prune_group_certificate(&certificate, ctx).await.map(|_| ())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error_event(
+                "Patch".to_owned(),
+                "MissingRouteInCertificateAnnotation".to_owned(),
+                Some(format!(
+                    "Error annotating Certificate `{}/{}` requested by Route `{}`: {}",
+                    namespace, cert_name, &route, e
+                )),
+                Some(
+                    TlsSource::Certificate(namespace.to_owned(), cert_name.to_owned()).object_ref(),
+                ),
+                &ctx.route_recorder(route),
+            )
+            .await;
+            Err(e)
+        }
+    }
+}
+
+/// Wait for `SIGTERM` or `SIGINT`, then shut the controller down gracefully.
+///
+/// The controller stops picking up new work once `shutdown` is notified, and its readiness probe fails.
+/// The in-flight reconciles, which publish their events before completing, get `deadline` to finish.
+/// Past the deadline, the [`Route`]s still being reconciled are logged, the leader `Lease` is released if
+/// the replica holds it and the process exits successfully, as the shutdown was requested.
+async fn shutdown_on_signal(
+    deadline: Duration,
+    shutdown: oneshot::Sender<()>,
+    leader_identity: Option<String>,
+    ctx: Arc<ContextData>,
+) {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("Error listening for SIGTERM: {}", e);
+                futures::future::pending::<()>().await
+            }
+        }
+    };
+    tokio::select! {
+        _ = terminate => {}
+        Ok(()) = tokio::signal::ctrl_c() => {}
+    }
+    info!(
+        "Shutting down, waiting up to {:?} for the in-flight reconciles",
+        deadline
+    );
+    record_shutdown();
+    let _ = shutdown.send(());
+
+    tokio::time::sleep(deadline).await;
+    warn!(
+        "The in-flight reconciles didn't finish within {:?}, exiting: {}",
+        deadline,
+        ctx.get_reconciling()
+            .iter()
+            .map(|route| format!("`{}`", route))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if let Some(identity) = leader_identity {
+        if let Err(e) = release_lease(&identity, &ctx).await {
+            error!("Error releasing the leader Lease: {}", e);
+        }
+    }
+    shutdown_tracing();
+    std::process::exit(0);
 }
@@ -0,0 +1,604 @@
+use crate::crd::certificate::{Certificate, CertificateStatusConditionsStatus};
+use crate::crd::route::Route;
+use crate::health::{record_activity, HealthProbes, HEALTHZ_PATH, READYZ_PATH};
+use crate::route::{get_route_cluster_issuer, get_route_tls_source};
+use crate::types::{ContextData, Error, TlsSource};
+use crate::CERT_ANNOTATION_KEY;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
+use kube::runtime::{controller::Action, reflector::Store};
+use kube::ResourceExt;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, LazyLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tower::{Layer, Service};
+use tracing::error;
+
+pub const METRICS_PATH: &str = "/metrics";
+const METRICS_PREFIX: &str = "cert_manager_routes";
+/// The reason of the reconciles that didn't publish any event.
+const UP_TO_DATE_REASON: &str = "UpToDate";
+
+static RECONCILES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        format!("{}_reconcile_total", METRICS_PREFIX),
+        "Number of Route reconciles by outcome and reason of the last event",
+        &["outcome", "reason"]
+    )
+    .unwrap()
+});
+static RECONCILE_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        format!("{}_reconcile_duration_seconds", METRICS_PREFIX),
+        "Duration of the Route reconciles by outcome and reason of the last event",
+        &["outcome", "reason"]
+    )
+    .unwrap()
+});
+static TLS_PATCHES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        format!("{}_tls_patches_total", METRICS_PREFIX),
+        "Number of TLS patches applied to Routes, `inline` or with an `external` certificate",
+        &["mode"]
+    )
+    .unwrap()
+});
+static API_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        format!("{}_api_errors_total", METRICS_PREFIX),
+        "Number of failed Kubernetes API calls by verb and kind",
+        &["verb", "kind"]
+    )
+    .unwrap()
+});
+static MANAGED_ROUTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        format!("{}_managed_routes", METRICS_PREFIX),
+        "Number of Routes managed by the controller by namespace and ClusterIssuer",
+        &["namespace", "issuer"]
+    )
+    .unwrap()
+});
+static CERTIFICATES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        format!("{}_certificates", METRICS_PREFIX),
+        "Number of Certificates used by Routes by Ready condition status",
+        &["ready"]
+    )
+    .unwrap()
+});
+static CERTIFICATE_EXPIRY: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        format!("{}_certificate_expiry_seconds", METRICS_PREFIX),
+        "Seconds until the Certificate of a host used by Routes expires",
+        &["namespace", "certificate", "host"]
+    )
+    .unwrap()
+});
+static ISSUANCE_QUEUE_DEPTH: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        format!("{}_issuance_queue_depth", METRICS_PREFIX),
+        "Number of Routes waiting for the issuance budget by ClusterIssuer and registered domain",
+        &["key"]
+    )
+    .unwrap()
+});
+static ISSUER_AUDIT_ROUTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        format!("{}_issuer_audit_routes", METRICS_PREFIX),
+        "Number of Routes by result of the last ClusterIssuer audit, `allowed` or `forbidden`",
+        &["result"]
+    )
+    .unwrap()
+});
+
+/// Whether the reconcile running on a task published an error event, and the reason of its last event.
+#[derive(Debug, Default)]
+struct ReconcileOutcome {
+    failed: bool,
+    reason: Option<String>,
+}
+
+tokio::task_local! {
+    static RECONCILE_OUTCOME: RefCell<ReconcileOutcome>;
+}
+
+/// Record an event published by the controller.
+///
+/// The event sets the outcome of the reconcile running on the current task, if any.
+///
+/// ### Arguments
+///
+/// * `reason` - The reason of the event.
+/// * `failed` - Whether the event is an error event.
+pub fn record_event(reason: &str, failed: bool) {
+    let _ = RECONCILE_OUTCOME.try_with(|outcome| {
+        let mut outcome = outcome.borrow_mut();
+        outcome.failed |= failed;
+        outcome.reason = Some(reason.to_owned());
+    });
+}
+
+/// Get the verb and the kind of a Kubernetes API call from its method and URI.
+///
+/// The kind is the plural resource name of the path, such as `certificates`,
+/// and the verb the one of the Kubernetes RBAC, such as `get`, `list` or `patch`.
+///
+/// ### Arguments
+///
+/// * `method` - The HTTP method of the call.
+/// * `uri` - The URI of the call.
+///
+/// ### Returns
+///
+/// A tuple containing the verb and the kind of the call.
+///
+/// ### Example
+///
+/// ```rust
+/// let (verb, kind) = get_api_call_labels(&Method::GET, &uri);
+/// println!("{} {}", verb, kind); // list certificates
+/// ```
+pub fn get_api_call_labels(method: &Method, uri: &Uri) -> (String, String) {
+    let segments = uri
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let resource = match segments.first() {
+        Some(&"api") => segments.get(2..),
+        Some(&"apis") => segments.get(3..),
+        _ => None,
+    }
+    .unwrap_or_default();
+    let (kind, name) = match resource {
+        ["namespaces", _, kind, rest @ ..] => (*kind, rest.first()),
+        [kind, rest @ ..] => (*kind, rest.first()),
+        [] => ("", None),
+    };
+    let watch = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .any(|param| param == "watch=true" || param == "watch=1");
+    let verb = match *method {
+        Method::GET if watch => "watch".to_owned(),
+        Method::GET if name.is_none() => "list".to_owned(),
+        Method::GET => "get".to_owned(),
+        Method::POST => "create".to_owned(),
+        Method::PUT => "update".to_owned(),
+        Method::PATCH => "patch".to_owned(),
+        Method::DELETE => "delete".to_owned(),
+        ref method => method.as_str().to_lowercase(),
+    };
+    (verb, kind.to_owned())
+}
+
+#[test]
+fn test_get_api_call_labels() {
+    let labels = |method: Method, uri: &str| {
+        let (verb, kind) = get_api_call_labels(&method, &uri.parse().unwrap());
+        format!("{} {}", verb, kind)
+    };
+    assert_eq!(
+        labels(
+            Method::GET,
+            "/apis/cert-manager.io/v1/namespaces/default/certificates/app-cert"
+        ),
+        "get certificates"
+    );
+    assert_eq!(
+        labels(
+            Method::GET,
+            "/apis/cert-manager.io/v1/certificates?labelSelector=a%3Db"
+        ),
+        "list certificates"
+    );
+    assert_eq!(
+        labels(
+            Method::GET,
+            "/apis/route.openshift.io/v1/routes?watch=true&resourceVersion=1"
+        ),
+        "watch routes"
+    );
+    assert_eq!(
+        labels(
+            Method::PATCH,
+            "/apis/route.openshift.io/v1/namespaces/default/routes/app/status"
+        ),
+        "patch routes"
+    );
+    assert_eq!(
+        labels(Method::PUT, "/api/v1/namespaces/default"),
+        "update namespaces"
+    );
+    assert_eq!(
+        labels(Method::POST, "/api/v1/namespaces/default/secrets"),
+        "create secrets"
+    );
+    assert_eq!(
+        labels(
+            Method::DELETE,
+            "/apis/coordination.k8s.io/v1/namespaces/cert-manager/leases/controller"
+        ),
+        "delete leases"
+    );
+}
+
+/// The [`Layer`] of the Kubernetes client counting the failed API calls, see [`ApiErrors`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ApiErrorsLayer;
+
+impl<S> Layer<S> for ApiErrorsLayer {
+    type Service = ApiErrors<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiErrors { inner }
+    }
+}
+
+/// A Kubernetes client [`Service`] counting the failed API calls by verb and kind (see [`get_api_call_labels()`]).
+///
+/// The calls fail when no response is received or the response has an error status.
+/// `get` calls answered with `404 Not Found` aren't counted, as they are how the existence of an object is checked.
+#[derive(Clone, Debug)]
+pub struct ApiErrors<S> {
+    inner: S,
+}
+
+impl<S, B> Service<Request<Body>> for ApiErrors<S>
+where
+    S: Service<Request<Body>, Response = Response<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let (verb, kind) = get_api_call_labels(request.method(), request.uri());
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            let failed = match &response {
+                Ok(response) => {
+                    let status = response.status();
+                    (status.is_client_error() || status.is_server_error())
+                        && !(verb == "get" && status == StatusCode::NOT_FOUND)
+                }
+                Err(_) => true,
+            };
+            if failed {
+                API_ERRORS.with_label_values(&[&verb, &kind]).inc();
+            }
+            response
+        })
+    }
+}
+
+/// Record a TLS patch applied to a [`Route`].
+///
+/// ### Arguments
+///
+/// * `mode` - `inline` or `external`, depending on whether the TLS data was copied into the [`Route`].
+pub fn record_tls_patch(mode: &str) {
+    TLS_PATCHES.with_label_values(&[mode]).inc();
+}
+
+/// Record the result of an audit of the `ClusterIssuer`s requested by the [`Route`]s.
+///
+/// ### Arguments
+///
+/// * `allowed` - The number of [`Route`]s requesting an allowed `ClusterIssuer`, or none.
+/// * `forbidden` - The number of [`Route`]s requesting a forbidden `ClusterIssuer`.
+pub fn record_issuer_audit(allowed: usize, forbidden: usize) {
+    ISSUER_AUDIT_ROUTES
+        .with_label_values(&["allowed"])
+        .set(allowed as i64);
+    ISSUER_AUDIT_ROUTES
+        .with_label_values(&["forbidden"])
+        .set(forbidden as i64);
+}
+
+/// Run a reconcile and record its outcome and duration.
+///
+/// The outcome is `error` if the reconcile failed or published an error event, `success` otherwise.
+/// The reason is the one of the last event published by the reconcile, or `UpToDate` if it didn't publish any.
+///
+/// ### Arguments
+///
+/// * `reconcile` - The reconcile [`Future`].
+///
+/// ### Returns
+///
+/// The result of the reconcile.
+///
+/// ### Example
+///
+/// ```rust
+/// controller.run(|route, ctx| observe_reconcile(reconcile(route, ctx)), error_policy, context)
+/// ```
+pub async fn observe_reconcile<F>(reconcile: F) -> Result<Action, Error>
+where
+    F: Future<Output = Result<Action, Error>>,
+{
+    let start = Instant::now();
+    let (result, outcome) = RECONCILE_OUTCOME
+        .scope(RefCell::default(), async move {
+            let result = reconcile.await;
+            (result, RECONCILE_OUTCOME.with(|outcome| outcome.take()))
+        })
+        .await;
+    let labels = [
+        if result.is_err() || outcome.failed {
+            "error"
+        } else {
+            "success"
+        },
+        outcome.reason.as_deref().unwrap_or(UP_TO_DATE_REASON),
+    ];
+    RECONCILES.with_label_values(&labels).inc();
+    RECONCILE_DURATION
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+    record_activity();
+    result
+}
+
+/// Get the status of the `Ready` condition of a [`Certificate`].
+///
+/// ### Arguments
+///
+/// * `cert` - The [`Certificate`].
+///
+/// ### Returns
+///
+/// `True`, `False` or `Unknown` if the [`Certificate`] has no `Ready` condition yet.
+pub fn get_certificate_ready_status(cert: &Certificate) -> &'static str {
+    let condition = cert
+        .status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .and_then(|conditions| {
+            conditions
+                .iter()
+                .find(|condition| condition.r#type == "Ready")
+        });
+    match condition.map(|condition| &condition.status) {
+        Some(CertificateStatusConditionsStatus::True) => "True",
+        Some(CertificateStatusConditionsStatus::False) => "False",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+fn load_certificate(status: serde_json::Value) -> Certificate {
+    serde_json::from_value(serde_json::json!({
+        "apiVersion": "cert-manager.io/v1",
+        "kind": "Certificate",
+        "metadata": { "name": "hello-cert", "namespace": "cert-manager" },
+        "spec": {
+            "secretName": "hello-tls",
+            "dnsNames": ["hello.example.com"],
+            "issuerRef": { "name": "letsencrypt" }
+        },
+        "status": status
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_get_certificate_ready_status() {
+    let cert = load_certificate(serde_json::json!({}));
+    assert_eq!(get_certificate_ready_status(&cert), "Unknown");
+    let cert = load_certificate(serde_json::json!({
+        "conditions": [{ "type": "Issuing", "status": "True" }]
+    }));
+    assert_eq!(get_certificate_ready_status(&cert), "Unknown");
+    let cert = load_certificate(serde_json::json!({
+        "conditions": [
+            { "type": "Issuing", "status": "True" },
+            { "type": "Ready", "status": "False" }
+        ]
+    }));
+    assert_eq!(get_certificate_ready_status(&cert), "False");
+    let cert = load_certificate(serde_json::json!({
+        "conditions": [{ "type": "Ready", "status": "True" }]
+    }));
+    assert_eq!(get_certificate_ready_status(&cert), "True");
+}
+
+/// Get the number of seconds until a [`Certificate`] expires.
+///
+/// ### Arguments
+///
+/// * `cert` - The [`Certificate`].
+/// * `now` - The current time.
+///
+/// ### Returns
+///
+/// The number of seconds until the `notAfter` time of the [`Certificate`], negative once it expired,
+/// or [`None`] if it wasn't issued yet.
+pub fn get_certificate_expiry_seconds(cert: &Certificate, now: DateTime<Utc>) -> Option<i64> {
+    let not_after = cert.status.as_ref()?.not_after.as_ref()?;
+    let not_after = DateTime::parse_from_rfc3339(not_after).ok()?;
+    Some((not_after.with_timezone(&Utc) - now).num_seconds())
+}
+
+#[test]
+fn test_get_certificate_expiry_seconds() {
+    let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    let cert = load_certificate(serde_json::json!({}));
+    assert_eq!(get_certificate_expiry_seconds(&cert, now), None);
+    let cert = load_certificate(serde_json::json!({ "notAfter": "2024-01-02T00:00:00Z" }));
+    assert_eq!(get_certificate_expiry_seconds(&cert, now), Some(86400));
+    let cert = load_certificate(serde_json::json!({ "notAfter": "2023-12-31T23:59:00Z" }));
+    assert_eq!(get_certificate_expiry_seconds(&cert, now), Some(-60));
+    let cert = load_certificate(serde_json::json!({ "notAfter": "tomorrow" }));
+    assert_eq!(get_certificate_expiry_seconds(&cert, now), None);
+}
+
+/// Update the gauges describing the state of the cluster from the caches.
+///
+/// The gauges are reset first, so that the deleted [`Route`]s and [`Certificate`]s are no longer reported.
+/// The failures of the deleted [`Route`]s are forgotten too, see [`ContextData::prune_failures()`].
+/// Only the [`Certificate`]s annotated with [`CERT_ANNOTATION_KEY`] are reported, as they are used by [`Route`]s.
+async fn update_state_metrics(
+    routes: &[Store<Route>],
+    certificates: &[Store<Certificate>],
+    ctx: &ContextData,
+) {
+    let mut managed_routes = BTreeMap::<(String, String), i64>::new();
+    let mut existing_routes = HashSet::new();
+    for route in routes.iter().flat_map(|routes| routes.state()) {
+        existing_routes.insert(route.to_string());
+        let defaults = ctx.get_issuer_defaults(&route);
+        let issuer = match get_route_tls_source(&route, &ctx.cert_manager_namespace, &defaults) {
+            Some(Ok(TlsSource::Certificate(_, _))) => {
+                get_route_cluster_issuer(&route, &defaults).unwrap_or_default()
+            }
+            Some(Ok(TlsSource::Secret(_, _))) => String::new(),
+            _ => continue,
+        };
+        *managed_routes
+            .entry((route.namespace().unwrap_or_default(), issuer))
+            .or_default() += 1;
+    }
+    ctx.prune_failures(&existing_routes);
+    MANAGED_ROUTES.reset();
+    for ((namespace, issuer), count) in managed_routes {
+        MANAGED_ROUTES
+            .with_label_values(&[&namespace, &issuer])
+            .set(count);
+    }
+
+    let now = Utc::now();
+    CERTIFICATES.reset();
+    CERTIFICATE_EXPIRY.reset();
+    for cert in certificates
+        .iter()
+        .flat_map(|certificates| certificates.state())
+        .filter(|cert| cert.annotations().contains_key(CERT_ANNOTATION_KEY))
+    {
+        CERTIFICATES
+            .with_label_values(&[get_certificate_ready_status(&cert)])
+            .inc();
+        if let Some(seconds) = get_certificate_expiry_seconds(&cert, now) {
+            let namespace = cert.namespace().unwrap_or_default();
+            for host in cert.spec.dns_names.iter().flatten() {
+                CERTIFICATE_EXPIRY
+                    .with_label_values(&[&namespace, &cert.name_any(), host])
+                    .set(seconds);
+            }
+        }
+    }
+
+    ISSUANCE_QUEUE_DEPTH.reset();
+    for (key, queued) in &ctx.budget_state.lock().await.queued {
+        ISSUANCE_QUEUE_DEPTH
+            .with_label_values(&[key])
+            .set(queued.len() as i64);
+    }
+}
+
+/// Answer a health probe with `200 OK`, or `503 Service Unavailable` if it fails.
+fn probe_response(healthy: bool) -> Response<Body> {
+    let (status, body) = match healthy {
+        true => (StatusCode::OK, "ok"),
+        false => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+    };
+    Response::builder()
+        .status(status)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Handle a request to the metrics server.
+///
+/// Only `GET` requests are accepted. The metrics are served on [`METRICS_PATH`] in the Prometheus text format,
+/// the liveness probe on [`HEALTHZ_PATH`] and the readiness probe on [`READYZ_PATH`].
+async fn handle_request(
+    request: Request<Body>,
+    probes: HealthProbes,
+    ctx: Arc<ContextData>,
+) -> Result<Response<Body>, hyper::Error> {
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, METRICS_PATH) => {}
+        (&Method::GET, HEALTHZ_PATH) => {
+            return Ok(probe_response(probes.is_alive(ctx.is_leader())))
+        }
+        (&Method::GET, READYZ_PATH) => return Ok(probe_response(probes.is_ready())),
+        _ => {
+            return Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap())
+        }
+    }
+    update_state_metrics(&probes.routes, &probes.certificates, &ctx).await;
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        return Ok(Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(format!("Error encoding the metrics: {}", e)))
+            .unwrap());
+    }
+    Ok(Response::builder()
+        .header("Content-Type", encoder.format_type())
+        .body(Body::from(buffer))
+        .unwrap())
+}
+
+/// Serve the Prometheus metrics and the health probes of the controller over HTTP.
+///
+/// ### Arguments
+///
+/// * `port` - The port to listen on.
+/// * `probes` - The [`HealthProbes`], whose caches are also used to compute the metrics.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`std::io::Error`] if the server couldn't listen.
+///
+/// ### Example
+///
+/// ```rust
+/// tokio::spawn(run_metrics(8080, probes, ctx.clone()));
+/// ```
+pub async fn run_metrics(
+    port: u16,
+    probes: HealthProbes,
+    ctx: Arc<ContextData>,
+) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let (probes, ctx) = (probes.clone(), ctx.clone());
+        tokio::spawn(async move {
+            let service =
+                service_fn(move |request| handle_request(request, probes.clone(), ctx.clone()));
+            if let Err(e) = Http::new().serve_connection(stream, service).await {
+                error!("Error serving a metrics connection: {}", e);
+            }
+        });
+    }
+}
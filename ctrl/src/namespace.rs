@@ -0,0 +1,192 @@
+use crate::route::parse_bool;
+use crate::{
+    ALLOWED_ISSUER_LABEL_PREFIX, CLUSTER_ISSUER_ANNOTATION_KEY,
+    NAMESPACE_MANAGE_ROUTES_ANNOTATION_KEY,
+};
+use k8s_openapi::api::core::v1::Namespace;
+use kube::ResourceExt;
+use std::collections::HashSet;
+
+/// The `ClusterIssuer`s a [`Route`](crate::crd::route::Route) falls back to
+/// when it doesn't request one with the [`CLUSTER_ISSUER_ANNOTATION_KEY`] annotation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IssuerDefaults {
+    /// The `ClusterIssuer` of the `kubernetes.io/tls-acme` compatibility mode, if it is enabled.
+    pub tls_acme_issuer: Option<String>,
+    /// The `ClusterIssuer` set on the [`Route`](crate::crd::route::Route)'s [`Namespace`].
+    pub namespace_issuer: Option<String>,
+    /// Whether every [`Route`](crate::crd::route::Route) of the [`Namespace`] is managed by default.
+    pub manage_all_routes: bool,
+    /// The `ClusterIssuer` of the `RoutesCertificatePolicy` rule applying to the [`Route`](crate::crd::route::Route).
+    pub policy_issuer: Option<String>,
+    /// The certificate mode of the `RoutesCertificatePolicy` rule applying to the [`Route`](crate::crd::route::Route).
+    pub policy_certificate_mode: Option<String>,
+}
+
+/// Get the [`IssuerDefaults`] of the [`Route`](crate::crd::route::Route)s of a [`Namespace`].
+///
+/// The [`Namespace`] may carry a [`CLUSTER_ISSUER_ANNOTATION_KEY`] annotation used by its `kubernetes.io/tls-acme`
+/// [`Route`](crate::crd::route::Route)s and, with a truthy [`NAMESPACE_MANAGE_ROUTES_ANNOTATION_KEY`] annotation,
+/// by all of its [`Route`](crate::crd::route::Route)s.
+/// The `policy_issuer` and `policy_certificate_mode` are left unset, they are resolved per [`Route`](crate::crd::route::Route).
+///
+/// ### Arguments
+///
+/// * `namespace` - The [`Namespace`], or [`None`] if it isn't known.
+/// * `tls_acme_issuer` - The `ClusterIssuer` of the `kubernetes.io/tls-acme` compatibility mode, if it is enabled.
+///
+/// ### Returns
+///
+/// The [`IssuerDefaults`] of the [`Namespace`].
+///
+/// ### Example
+///
+/// ```rust
+/// let defaults = get_issuer_defaults(Some(&namespace), ctx.config().tls_acme_cluster_issuer.as_ref());
+/// println!("Namespace ClusterIssuer: {:?}", defaults.namespace_issuer);
+/// ```
+pub fn get_issuer_defaults(
+    namespace: Option<&Namespace>,
+    tls_acme_issuer: Option<&String>,
+) -> IssuerDefaults {
+    let namespace_issuer = namespace.and_then(|namespace| {
+        namespace
+            .annotations()
+            .get(CLUSTER_ISSUER_ANNOTATION_KEY)
+            .filter(|issuer| !issuer.is_empty())
+            .cloned()
+    });
+    let manage_all_routes = namespace
+        .and_then(|namespace| {
+            namespace
+                .annotations()
+                .get(NAMESPACE_MANAGE_ROUTES_ANNOTATION_KEY)
+        })
+        .and_then(|value| parse_bool(value))
+        .unwrap_or(false);
+    IssuerDefaults {
+        tls_acme_issuer: tls_acme_issuer.cloned(),
+        namespace_issuer,
+        manage_all_routes,
+        policy_issuer: None,
+        policy_certificate_mode: None,
+    }
+}
+
+#[test]
+fn test_get_issuer_defaults() {
+    assert_eq!(get_issuer_defaults(None, None), IssuerDefaults::default());
+    assert_eq!(
+        get_issuer_defaults(None, Some(&"acme".to_owned())).tls_acme_issuer,
+        Some("acme".to_owned())
+    );
+
+    let mut namespace = Namespace::default();
+    namespace.annotations_mut().insert(
+        NAMESPACE_MANAGE_ROUTES_ANNOTATION_KEY.to_owned(),
+        "true".to_owned(),
+    );
+    assert_eq!(
+        get_issuer_defaults(Some(&namespace), None),
+        IssuerDefaults {
+            manage_all_routes: true,
+            ..Default::default()
+        }
+    );
+
+    namespace.annotations_mut().insert(
+        CLUSTER_ISSUER_ANNOTATION_KEY.to_owned(),
+        "issuer".to_owned(),
+    );
+    assert_eq!(
+        get_issuer_defaults(Some(&namespace), None),
+        IssuerDefaults {
+            tls_acme_issuer: None,
+            namespace_issuer: Some("issuer".to_owned()),
+            manage_all_routes: true,
+            policy_issuer: None,
+            policy_certificate_mode: None,
+        }
+    );
+
+    namespace.annotations_mut().insert(
+        NAMESPACE_MANAGE_ROUTES_ANNOTATION_KEY.to_owned(),
+        "false".to_owned(),
+    );
+    assert!(!get_issuer_defaults(Some(&namespace), None).manage_all_routes);
+}
+
+/// Check whether the [`Route`](crate::crd::route::Route)s of a [`Namespace`] may request a `ClusterIssuer`.
+///
+/// A `ClusterIssuer` is allowed if it is listed in the controller's allowlist,
+/// or if the [`Namespace`] has a truthy `allowed-issuers.cert-manager.io/<issuer>` label
+/// (see [`ALLOWED_ISSUER_LABEL_PREFIX`]).
+///
+/// ### Arguments
+///
+/// * `issuer` - The name of the `ClusterIssuer`.
+/// * `namespace` - The [`Namespace`], or [`None`] if it isn't known.
+/// * `allowed_issuers` - The `ClusterIssuer`s every [`Namespace`] may request.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the `ClusterIssuer` is allowed.
+///
+/// ### Example
+///
+/// ```rust
+/// if !is_issuer_allowed(&issuer, Some(&namespace), &allowed_issuers) {
+///     println!("ClusterIssuer `{}` is forbidden", issuer);
+/// }
+/// ```
+pub fn is_issuer_allowed(
+    issuer: &str,
+    namespace: Option<&Namespace>,
+    allowed_issuers: &HashSet<String>,
+) -> bool {
+    allowed_issuers.contains(issuer)
+        || namespace.is_some_and(|namespace| {
+            namespace
+                .labels()
+                .get(&format!("{}{}", ALLOWED_ISSUER_LABEL_PREFIX, issuer))
+                .and_then(|value| parse_bool(value))
+                .unwrap_or(false)
+        })
+}
+
+#[test]
+fn test_is_issuer_allowed() {
+    let allowed_issuers = HashSet::from(["letsencrypt-prod".to_owned()]);
+    assert!(is_issuer_allowed(
+        "letsencrypt-prod",
+        None,
+        &allowed_issuers
+    ));
+    assert!(!is_issuer_allowed("commercial-ca", None, &allowed_issuers));
+
+    let mut namespace = Namespace::default();
+    assert!(!is_issuer_allowed(
+        "commercial-ca",
+        Some(&namespace),
+        &allowed_issuers
+    ));
+    namespace.labels_mut().insert(
+        format!("{}commercial-ca", ALLOWED_ISSUER_LABEL_PREFIX),
+        "true".to_owned(),
+    );
+    assert!(is_issuer_allowed(
+        "commercial-ca",
+        Some(&namespace),
+        &allowed_issuers
+    ));
+    assert!(is_issuer_allowed(
+        "commercial-ca",
+        Some(&namespace),
+        &HashSet::new()
+    ));
+    assert!(!is_issuer_allowed(
+        "internal-ca",
+        Some(&namespace),
+        &HashSet::new()
+    ));
+}
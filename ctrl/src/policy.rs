@@ -0,0 +1,400 @@
+use crate::crd::policy::{RoutesCertificatePolicy, RoutesCertificatePolicyRule};
+use crate::crd::route::Route;
+use crate::route::{
+    parse_insecure_edge_termination_policy, parse_termination, validate_tls_settings,
+    RouteTlsSettings,
+};
+use k8s_openapi::api::core::v1::Namespace;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::ResourceExt;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Check whether a set of labels matches a [`LabelSelector`].
+///
+/// ### Arguments
+///
+/// * `selector` - The [`LabelSelector`] to match.
+/// * `labels` - The labels to check.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the labels match all of the `matchLabels` and `matchExpressions`.
+///
+/// ### Example
+///
+/// ```rust
+/// if selector_matches(&selector, route.labels()) {
+///     println!("Route `{}` is selected", route);
+/// }
+/// ```
+pub fn selector_matches(selector: &LabelSelector, labels: &BTreeMap<String, String>) -> bool {
+    let match_labels = selector
+        .match_labels
+        .iter()
+        .flatten()
+        .all(|(key, value)| labels.get(key) == Some(value));
+    let match_expressions = selector
+        .match_expressions
+        .iter()
+        .flatten()
+        .all(|expression| {
+            let values = expression.values.clone().unwrap_or_default();
+            match expression.operator.as_str() {
+                "In" => labels
+                    .get(&expression.key)
+                    .is_some_and(|value| values.contains(value)),
+                "NotIn" => labels
+                    .get(&expression.key)
+                    .is_none_or(|value| !values.contains(value)),
+                "Exists" => labels.contains_key(&expression.key),
+                "DoesNotExist" => !labels.contains_key(&expression.key),
+                _ => false,
+            }
+        });
+    match_labels && match_expressions
+}
+
+#[test]
+fn test_selector_matches() {
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelectorRequirement;
+
+    let labels = BTreeMap::from([
+        ("env".to_owned(), "prod".to_owned()),
+        ("team".to_owned(), "web".to_owned()),
+    ]);
+    assert!(selector_matches(&LabelSelector::default(), &labels));
+
+    let selector = LabelSelector {
+        match_labels: Some(BTreeMap::from([("env".to_owned(), "prod".to_owned())])),
+        ..Default::default()
+    };
+    assert!(selector_matches(&selector, &labels));
+    assert!(!selector_matches(&selector, &BTreeMap::new()));
+
+    let expression = |key: &str, operator: &str, values: Option<Vec<&str>>| LabelSelector {
+        match_expressions: Some(vec![LabelSelectorRequirement {
+            key: key.to_owned(),
+            operator: operator.to_owned(),
+            values: values.map(|values| values.iter().map(|v| v.to_string()).collect()),
+        }]),
+        ..Default::default()
+    };
+    assert!(selector_matches(
+        &expression("env", "In", Some(vec!["prod", "staging"])),
+        &labels
+    ));
+    assert!(!selector_matches(
+        &expression("env", "In", Some(vec!["dev"])),
+        &labels
+    ));
+    assert!(selector_matches(
+        &expression("env", "NotIn", Some(vec!["dev"])),
+        &labels
+    ));
+    assert!(selector_matches(
+        &expression("tier", "NotIn", Some(vec!["dev"])),
+        &labels
+    ));
+    assert!(selector_matches(
+        &expression("team", "Exists", None),
+        &labels
+    ));
+    assert!(!selector_matches(
+        &expression("team", "DoesNotExist", None),
+        &labels
+    ));
+    assert!(!selector_matches(
+        &expression("team", "Unknown", None),
+        &labels
+    ));
+}
+
+/// Check whether a hostname ends with a host suffix.
+///
+/// The suffix may be written `internal.corp`, `.internal.corp` or `*.internal.corp`
+/// and matches `internal.corp` as well as all of its subdomains.
+///
+/// ### Arguments
+///
+/// * `hostname` - The hostname to check.
+/// * `suffix` - The host suffix.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the hostname ends with the suffix.
+///
+/// ### Example
+///
+/// ```rust
+/// assert!(host_matches_suffix("app.internal.corp", "*.internal.corp"));
+/// ```
+pub fn host_matches_suffix(hostname: &str, suffix: &str) -> bool {
+    let suffix = suffix.trim_start_matches("*").trim_start_matches(".");
+    let hostname = hostname.to_lowercase();
+    let suffix = suffix.to_lowercase();
+    !suffix.is_empty() && (hostname == suffix || hostname.ends_with(&format!(".{}", suffix)))
+}
+
+#[test]
+fn test_host_matches_suffix() {
+    assert!(host_matches_suffix("app.internal.corp", "internal.corp"));
+    assert!(host_matches_suffix("app.internal.corp", ".internal.corp"));
+    assert!(host_matches_suffix("a.b.internal.corp", "*.internal.corp"));
+    assert!(host_matches_suffix("internal.corp", "*.internal.corp"));
+    assert!(host_matches_suffix("App.Internal.Corp", "internal.corp"));
+    assert!(!host_matches_suffix("appinternal.corp", "internal.corp"));
+    assert!(!host_matches_suffix("app.example.com", "internal.corp"));
+    assert!(!host_matches_suffix("app.example.com", "*."));
+}
+
+/// Check whether a [`RoutesCertificatePolicyRule`] applies to a [`Route`].
+///
+/// ### Arguments
+///
+/// * `rule` - The [`RoutesCertificatePolicyRule`] to check.
+/// * `route` - The [`Route`] to check.
+/// * `namespace` - The [`Route`]'s [`Namespace`], or [`None`] if it isn't known.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether all of the rule's selectors match the [`Route`].
+///
+/// ### Example
+///
+/// ```rust
+/// if rule_matches(&rule, &route, Some(&namespace)) {
+///     println!("Rule applies to Route `{}`", route);
+/// }
+/// ```
+pub fn rule_matches(
+    rule: &RoutesCertificatePolicyRule,
+    route: &Route,
+    namespace: Option<&Namespace>,
+) -> bool {
+    let host = rule.host_suffix.as_ref().is_none_or(|suffix| {
+        route
+            .spec
+            .host
+            .as_ref()
+            .is_some_and(|host| host_matches_suffix(host, suffix))
+    });
+    let namespace = rule.namespace_selector.as_ref().is_none_or(|selector| {
+        namespace.is_some_and(|namespace| selector_matches(selector, namespace.labels()))
+    });
+    let labels = rule
+        .route_selector
+        .as_ref()
+        .is_none_or(|selector| selector_matches(selector, route.labels()));
+    let router = rule.router_name.as_ref().is_none_or(|router_name| {
+        route
+            .status
+            .as_ref()
+            .and_then(|status| status.ingress.as_ref())
+            .is_some_and(|ingresses| {
+                ingresses
+                    .iter()
+                    .any(|ingress| ingress.router_name.as_ref() == Some(router_name))
+            })
+    });
+    host && namespace && labels && router
+}
+
+/// Get the first [`RoutesCertificatePolicyRule`] applying to a [`Route`].
+///
+/// The policies are evaluated by decreasing priority, then by name, and the rules of a policy in order.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to get the rule for.
+/// * `namespace` - The [`Route`]'s [`Namespace`], or [`None`] if it isn't known.
+/// * `policies` - The cluster's [`RoutesCertificatePolicy`]s.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the matching [`RoutesCertificatePolicyRule`].
+///
+/// ### Example
+///
+/// ```rust
+/// if let Some(rule) = get_route_policy_rule(&route, Some(&namespace), &ctx.policies.state()) {
+///     println!("ClusterIssuer: {:?}", rule.issuer);
+/// }
+/// ```
+pub fn get_route_policy_rule(
+    route: &Route,
+    namespace: Option<&Namespace>,
+    policies: &[Arc<RoutesCertificatePolicy>],
+) -> Option<RoutesCertificatePolicyRule> {
+    let mut policies = policies.iter().collect::<Vec<_>>();
+    policies.sort_by(|a, b| {
+        b.spec
+            .priority
+            .cmp(&a.spec.priority)
+            .then_with(|| a.name_any().cmp(&b.name_any()))
+    });
+    policies
+        .into_iter()
+        .flat_map(|policy| policy.spec.rules.iter())
+        .find(|rule| rule_matches(rule, route, namespace))
+        .cloned()
+}
+
+#[test]
+fn test_get_route_policy_rule() {
+    use crate::crd::policy::RoutesCertificatePolicySpec;
+    use crate::crd::route::{RouteStatus, RouteStatusIngress};
+
+    let rule = |issuer: &str| RoutesCertificatePolicyRule {
+        issuer: Some(issuer.to_owned()),
+        ..Default::default()
+    };
+    let internal = RoutesCertificatePolicyRule {
+        host_suffix: Some("*.internal.corp".to_owned()),
+        ..rule("internal-ca")
+    };
+    let dmz = RoutesCertificatePolicyRule {
+        router_name: Some("dmz".to_owned()),
+        ..rule("public-ca")
+    };
+    let mut public = RoutesCertificatePolicy::new(
+        "public",
+        RoutesCertificatePolicySpec {
+            priority: 0,
+            rules: vec![internal.clone(), rule("letsencrypt-prod")],
+        },
+    );
+    let routers = Arc::new(RoutesCertificatePolicy::new(
+        "routers",
+        RoutesCertificatePolicySpec {
+            priority: 10,
+            rules: vec![dmz.clone()],
+        },
+    ));
+
+    let mut route = Route::new_test_route("test", "test", "app.internal.corp", None, None);
+    let policies = vec![Arc::new(public.clone()), routers.clone()];
+    assert_eq!(
+        get_route_policy_rule(&route, None, &policies),
+        Some(internal)
+    );
+    assert_eq!(get_route_policy_rule(&route, None, &[]), None);
+
+    route.spec.host = Some("app.example.com".to_owned());
+    assert_eq!(
+        get_route_policy_rule(&route, None, &policies),
+        Some(rule("letsencrypt-prod"))
+    );
+
+    route.status = Some(RouteStatus {
+        ingress: Some(vec![RouteStatusIngress {
+            conditions: None,
+            host: None,
+            router_canonical_hostname: None,
+            router_name: Some("dmz".to_owned()),
+            wildcard_policy: None,
+        }]),
+    });
+    assert_eq!(get_route_policy_rule(&route, None, &policies), Some(dmz));
+
+    let prod = RoutesCertificatePolicyRule {
+        namespace_selector: Some(LabelSelector {
+            match_labels: Some(BTreeMap::from([("env".to_owned(), "prod".to_owned())])),
+            ..Default::default()
+        }),
+        ..rule("prod-ca")
+    };
+    public.spec.rules = vec![prod.clone()];
+    let policies = vec![Arc::new(public)];
+    let mut namespace = Namespace::default();
+    assert_eq!(get_route_policy_rule(&route, None, &policies), None);
+    assert_eq!(
+        get_route_policy_rule(&route, Some(&namespace), &policies),
+        None
+    );
+    namespace
+        .labels_mut()
+        .insert("env".to_owned(), "prod".to_owned());
+    assert_eq!(
+        get_route_policy_rule(&route, Some(&namespace), &policies),
+        Some(prod)
+    );
+}
+
+/// Apply the TLS termination defaults of a [`RoutesCertificatePolicyRule`] to the controller's defaults.
+///
+/// ### Arguments
+///
+/// * `rule` - The [`RoutesCertificatePolicyRule`] applying to the [`Route`], if any.
+/// * `defaults` - The controller's default [`RouteTlsSettings`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the default [`RouteTlsSettings`] of the [`Route`]
+/// or a [`String`] describing why the rule is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let defaults = apply_policy_tls_settings(rule.as_ref(), &ctx.config().tls_settings())?;
+/// let settings = get_route_tls_settings(&route, &defaults)?;
+/// ```
+pub fn apply_policy_tls_settings(
+    rule: Option<&RoutesCertificatePolicyRule>,
+    defaults: &RouteTlsSettings,
+) -> Result<RouteTlsSettings, String> {
+    let mut settings = defaults.clone();
+    let rule = match rule {
+        Some(rule) => rule,
+        None => return Ok(settings),
+    };
+    if let Some(value) = &rule.termination {
+        settings.termination = parse_termination(value).ok_or(format!(
+            "Invalid policy termination `{}`, expected one of `edge`, `reencrypt`",
+            value
+        ))?;
+    }
+    if let Some(value) = &rule.insecure_edge_termination_policy {
+        settings.insecure_edge_termination_policy = Some(
+            parse_insecure_edge_termination_policy(value).ok_or(format!(
+                "Invalid policy insecure edge termination policy `{}`, expected one of `Allow`, `None`, `Redirect`",
+                value
+            ))?,
+        );
+    }
+    validate_tls_settings(&settings)?;
+    Ok(settings)
+}
+
+#[test]
+fn test_apply_policy_tls_settings() {
+    use crate::crd::route::{RouteTlsInsecureEdgeTerminationPolicy, RouteTlsTermination};
+
+    let defaults = RouteTlsSettings::default();
+    assert_eq!(
+        apply_policy_tls_settings(None, &defaults),
+        Ok(defaults.clone())
+    );
+
+    let rule = RoutesCertificatePolicyRule {
+        termination: Some("reencrypt".to_owned()),
+        insecure_edge_termination_policy: Some("None".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(
+        apply_policy_tls_settings(Some(&rule), &defaults),
+        Ok(RouteTlsSettings {
+            termination: RouteTlsTermination::Reencrypt,
+            insecure_edge_termination_policy: Some(RouteTlsInsecureEdgeTerminationPolicy::None),
+            external_certificate: false,
+        })
+    );
+
+    for termination in ["passthrough", "foo"] {
+        let rule = RoutesCertificatePolicyRule {
+            termination: Some(termination.to_owned()),
+            ..Default::default()
+        };
+        assert!(apply_policy_tls_settings(Some(&rule), &defaults).is_err());
+    }
+}
@@ -1,23 +1,62 @@
-use crate::crd::route::{Route, RouteSpec, RouteTo, RouteToKind, RouteTlsTermination, RouteTlsInsecureEdgeTerminationPolicy};
-use crate::tools::{format_route_update_annotation, get_secret_tls_data, resource_to_string};
-use crate::types::ContextData;
-use crate::{CLUSTER_ISSUER_ANNOTATION_KEY, FINALIZER};
+use crate::crd::route::{
+    Route, RouteSpec, RouteTlsInsecureEdgeTerminationPolicy, RouteTlsTermination, RouteTo,
+    RouteToKind,
+};
+use crate::namespace::IssuerDefaults;
+use crate::secret::{grant_router_access, is_secret_mirrored, mirror_secret};
+use crate::tools::{
+    api_span, decode_tls_data, format_cert_name, format_route_update_annotation,
+    format_secret_name, get_namespace, get_secret_tls_data, resource_to_string,
+};
+use crate::types::{ContextData, Error, TlsSource};
+use crate::{
+    CERTIFICATE_MODE_ANNOTATION_KEY, CERTIFICATE_NAME_ANNOTATION_KEY,
+    CLUSTER_ISSUER_ANNOTATION_KEY, ISSUANCE_QUEUED_ANNOTATION_KEY, MIRROR_SECRET_ANNOTATION_KEY,
+    ROUTE_EXTERNAL_CERTIFICATE_ANNOTATION_KEY, ROUTE_INSECURE_POLICY_ANNOTATION_KEY,
+    ROUTE_OPT_OUT_ANNOTATION_KEY, ROUTE_TERMINATION_ANNOTATION_KEY, TLS_ACME_ANNOTATION_KEY,
+    TLS_ACME_MIGRATED_ANNOTATION_KEY, TLS_SECRET_ANNOTATION_KEY,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
 use kube::api::ObjectMeta;
-use kube::core::object::HasSpec;
 use kube::{
     api::{Patch, PatchParams},
     Api, ResourceExt,
 };
 use serde_json;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use tracing::Instrument;
+
+pub const DEFAULT_TERMINATION: RouteTlsTermination = RouteTlsTermination::Edge;
+pub const DEFAULT_INSECURE_EDGE_TERMINATION_POLICY: RouteTlsInsecureEdgeTerminationPolicy =
+    RouteTlsInsecureEdgeTerminationPolicy::Redirect;
+pub const TLS_CRT: &str = "tls.crt";
+pub const TLS_KEY: &str = "tls.key";
+pub const CA_CRT: &str = "ca.crt";
+const ROUTE_UPDATE_ANNOTATION_KEY: &str = "cert-manager.io/updates";
+const MAX_RESOURCE_NAME_LEN: usize = 253;
+const MAX_DNS_LABEL_LEN: usize = 63;
 
-const DEFAULT_TERMINATION: RouteTlsTermination = RouteTlsTermination::Edge;
-const DEFAULT_INSECURE_EDGE_TERMINATION_POLICY: RouteTlsInsecureEdgeTerminationPolicy = RouteTlsInsecureEdgeTerminationPolicy::Redirect;
-pub const TLS_CRT: &'static str = "tls.crt";
-pub const TLS_KEY: &'static str = "tls.key";
-const CA_CRT: &'static str = "ca.crt";
-const ROUTE_UPDATE_ANNOTATION_KEY: &'static str = "cert-manager.io/updates";
+/// The TLS termination settings applied to a [`Route`] when its TLS is populated.
+///
+/// When `external_certificate` is set, the [`TlsSource`]'s [`Secret`] is mirrored into the [`Route`]'s namespace
+/// and referenced through `spec.tls.externalCertificate` instead of embedding the key and certificate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteTlsSettings {
+    pub termination: RouteTlsTermination,
+    pub insecure_edge_termination_policy: Option<RouteTlsInsecureEdgeTerminationPolicy>,
+    pub external_certificate: bool,
+}
+
+impl Default for RouteTlsSettings {
+    fn default() -> Self {
+        Self {
+            termination: DEFAULT_TERMINATION,
+            insecure_edge_termination_policy: Some(DEFAULT_INSECURE_EDGE_TERMINATION_POLICY),
+            external_certificate: false,
+        }
+    }
+}
 
 impl Route {
     /// Create a new test [`Route`] with some default values.
@@ -41,17 +80,17 @@ impl Route {
     /// println!("Created Route: {}", route); // Created Route: namespace:name
     /// ```
     pub fn new_test_route(
-        name: &String,
-        namespace: &String,
-        hostname: &String,
+        name: &str,
+        namespace: &str,
+        hostname: &str,
         cert_manager_issuer: Option<&String>,
         annotation_key: Option<&String>,
     ) -> Self {
         Route {
             status: None,
             metadata: ObjectMeta {
-                name: Some(name.clone()),
-                namespace: Some(namespace.clone()),
+                name: Some(name.to_owned()),
+                namespace: Some(namespace.to_owned()),
                 annotations: match cert_manager_issuer {
                     Some(issuer) => {
                         let mut annotations = BTreeMap::new();
@@ -63,7 +102,7 @@ impl Route {
                 ..Default::default()
             },
             spec: RouteSpec {
-                host: Some(hostname.clone()),
+                host: Some(hostname.to_owned()),
                 path: None,
                 to: RouteTo {
                     kind: RouteToKind::Service,
@@ -87,18 +126,285 @@ impl fmt::Display for Route {
         write!(
             f,
             "{}",
-            resource_to_string(&self.name_any(), &self.namespace().unwrap())
+            resource_to_string(&self.name_any(), &self.namespace().unwrap_or_default())
         )
     }
 }
 
+/// Get the host of a [`Route`].
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the host, or an [`Error::InvalidRoute`] if the [`Route`] has no host.
+///
+/// ### Example
+///
+/// ```rust
+/// let host = get_route_host(&route)?;
+/// println!("Host: {}", host);
+/// ```
+pub fn get_route_host(route: &Route) -> Result<&str, Error> {
+    route
+        .spec
+        .host
+        .as_deref()
+        .ok_or_else(|| Error::InvalidRoute(route.to_string(), "`spec.host` isn't set".to_owned()))
+}
+
+#[test]
+fn test_get_route_host() {
+    let mut route = Route::new_test_route("world", "hello", "example.com", None, None);
+    assert_eq!(get_route_host(&route).unwrap(), "example.com");
+    route.spec.host = None;
+    assert!(matches!(
+        get_route_host(&route),
+        Err(Error::InvalidRoute(name, _)) if name == "hello/world"
+    ));
+
+    let route: Route =
+        serde_json::from_str(include_str!("../fixtures/routes/cluster-scoped-route.json")).unwrap();
+    assert_eq!(route.to_string(), "/world");
+}
+
+/// Get the `ClusterIssuer` a [`Route`] requests its [`Certificate`] from.
+///
+/// The [`CLUSTER_ISSUER_ANNOTATION_KEY`] annotation takes precedence. A [`Route`] with a truthy
+/// [`TLS_ACME_ANNOTATION_KEY`] annotation requests its [`Namespace`]'s `ClusterIssuer`, else the one of its
+/// `RoutesCertificatePolicy` rule, else the one of the `kubernetes.io/tls-acme` compatibility mode.
+/// Any other [`Route`] requests its [`Namespace`]'s `ClusterIssuer`, else the one of its `RoutesCertificatePolicy` rule,
+/// if the [`Namespace`] manages all of its [`Route`]s and the [`Route`] didn't opt out with the
+/// [`ROUTE_OPT_OUT_ANNOTATION_KEY`] annotation.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to get the `ClusterIssuer` for.
+/// * `defaults` - The [`IssuerDefaults`] of the [`Route`]'s [`Namespace`].
+///
+/// ### Returns
+///
+/// An [`Option`] containing the name of the `ClusterIssuer`.
+///
+/// ### Example
+///
+/// ```rust
+/// if let Some(issuer) = get_route_cluster_issuer(&route, &ctx.get_issuer_defaults(&route)) {
+///     println!("ClusterIssuer: {}", issuer);
+/// }
+/// ```
+pub fn get_route_cluster_issuer(route: &Route, defaults: &IssuerDefaults) -> Option<String> {
+    match route.annotations().get(CLUSTER_ISSUER_ANNOTATION_KEY) {
+        Some(issuer) => Some(issuer.clone()),
+        None if is_tls_acme_route(route) => defaults
+            .namespace_issuer
+            .clone()
+            .or(defaults.policy_issuer.clone())
+            .or(defaults.tls_acme_issuer.clone()),
+        None if defaults.manage_all_routes && !is_route_opted_out(route) => defaults
+            .namespace_issuer
+            .clone()
+            .or(defaults.policy_issuer.clone()),
+        None => None,
+    }
+}
+
+#[test]
+fn test_get_route_cluster_issuer() {
+    let tls_acme = IssuerDefaults {
+        tls_acme_issuer: Some("acme".to_owned()),
+        ..Default::default()
+    };
+    let namespace = IssuerDefaults {
+        tls_acme_issuer: Some("acme".to_owned()),
+        namespace_issuer: Some("namespace".to_owned()),
+        manage_all_routes: false,
+        policy_issuer: None,
+        policy_certificate_mode: None,
+    };
+    let manage_all = IssuerDefaults {
+        manage_all_routes: true,
+        ..namespace.clone()
+    };
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"issuer".to_owned()),
+        Some(&CLUSTER_ISSUER_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_cluster_issuer(&route, &manage_all),
+        Some("issuer".to_owned())
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"true".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_cluster_issuer(&route, &tls_acme),
+        Some("acme".to_owned())
+    );
+    assert_eq!(
+        get_route_cluster_issuer(&route, &namespace),
+        Some("namespace".to_owned())
+    );
+    assert_eq!(
+        get_route_cluster_issuer(&route, &IssuerDefaults::default()),
+        None
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"false".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(get_route_cluster_issuer(&route, &tls_acme), None);
+
+    let route = Route::new_test_route("test", "test", "test", None, None);
+    assert_eq!(get_route_cluster_issuer(&route, &namespace), None);
+    assert_eq!(
+        get_route_cluster_issuer(&route, &manage_all),
+        Some("namespace".to_owned())
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"true".to_owned()),
+        Some(&ROUTE_OPT_OUT_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(get_route_cluster_issuer(&route, &manage_all), None);
+
+    let route = Route::new_test_route("test", "test", "test", None, None);
+    let policy = IssuerDefaults {
+        manage_all_routes: true,
+        policy_issuer: Some("policy".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(
+        get_route_cluster_issuer(&route, &policy),
+        Some("policy".to_owned())
+    );
+    assert_eq!(
+        get_route_cluster_issuer(
+            &route,
+            &IssuerDefaults {
+                manage_all_routes: false,
+                ..policy
+            }
+        ),
+        None
+    );
+}
+
+/// Check whether a [`Route`] opted out of its [`Namespace`]'s management
+/// with a truthy [`ROUTE_OPT_OUT_ANNOTATION_KEY`] annotation.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to check.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the [`Route`] opted out.
+///
+/// ### Example
+///
+/// ```rust
+/// if is_route_opted_out(&route) {
+///     println!("Route `{}` isn't managed by its Namespace", route);
+/// }
+/// ```
+pub fn is_route_opted_out(route: &Route) -> bool {
+    route
+        .annotations()
+        .get(ROUTE_OPT_OUT_ANNOTATION_KEY)
+        .and_then(|value| parse_bool(value))
+        .unwrap_or(false)
+}
+
+/// Check whether a [`Route`] requests its certificate only through the [`TLS_ACME_ANNOTATION_KEY`] annotation
+/// used by openshift-acme.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to check.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the [`Route`] has a truthy [`TLS_ACME_ANNOTATION_KEY`] annotation
+/// and no [`CLUSTER_ISSUER_ANNOTATION_KEY`] annotation.
+///
+/// ### Example
+///
+/// ```rust
+/// if is_tls_acme_route(&route) {
+///     println!("Route `{}` is migrated from openshift-acme", route);
+/// }
+/// ```
+pub fn is_tls_acme_route(route: &Route) -> bool {
+    let annotations = route.annotations();
+    !annotations.contains_key(CLUSTER_ISSUER_ANNOTATION_KEY)
+        && annotations
+            .get(TLS_ACME_ANNOTATION_KEY)
+            .and_then(|value| parse_bool(value))
+            .unwrap_or(false)
+}
+
+#[test]
+fn test_is_tls_acme_route() {
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"true".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert!(is_tls_acme_route(&route));
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"false".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert!(!is_tls_acme_route(&route));
+
+    let mut route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"true".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    route.annotations_mut().insert(
+        CLUSTER_ISSUER_ANNOTATION_KEY.to_owned(),
+        "issuer".to_owned(),
+    );
+    assert!(!is_tls_acme_route(&route));
+}
+
 /// Check whether a [`Route`] is should be handled by the controller.
 ///
-/// A [`Route`] is valid if it has a [`spec.host`] and an [`ISSUER_ANNOTATION_KEY`] annotation.
+/// A [`Route`] is valid if it has a [`spec.host`] and either a `ClusterIssuer` (see [`get_route_cluster_issuer()`]),
+/// a [`CERTIFICATE_NAME_ANNOTATION_KEY`] or a [`TLS_SECRET_ANNOTATION_KEY`] annotation.
 ///
 /// ### Arguments
 ///
 /// * `route` - The [`Route`] to check.
+/// * `defaults` - The [`IssuerDefaults`] of the [`Route`]'s [`Namespace`].
 ///
 /// ### Returns
 ///
@@ -107,171 +413,1223 @@ impl fmt::Display for Route {
 /// ### Example
 ///
 /// ```rust
-/// let valid = is_valid_route(&route).await?;
+/// let valid = is_valid_route(&route, &ctx.get_issuer_defaults(&route));
 /// println!("Valid Route: {}", valid);
 /// ```
-pub fn is_valid_route(route: &Route) -> bool {
-    if route.spec.host == None
-        || route.metadata.annotations == None
-        || route
-            .metadata
-            .annotations
-            .as_ref()
-            .unwrap()
-            .get(CLUSTER_ISSUER_ANNOTATION_KEY)
-            == None
-    {
-        false
-    } else {
-        true
-    }
+pub fn is_valid_route(route: &Route, defaults: &IssuerDefaults) -> bool {
+    route.spec.host.is_some()
+        && (get_route_cluster_issuer(route, defaults).is_some()
+            || route
+                .annotations()
+                .contains_key(CERTIFICATE_NAME_ANNOTATION_KEY)
+            || route.annotations().contains_key(TLS_SECRET_ANNOTATION_KEY))
 }
 
 #[test]
 fn test_is_valid_route() {
+    let route = Route::new_test_route("test_name", "test_ns", "test_host", None, None);
+    assert!(!is_valid_route(&route, &IssuerDefaults::default()));
     let route = Route::new_test_route(
-        &"test_name".to_owned(),
-        &"test_ns".to_owned(),
-        &"test_host".to_owned(),
-        None,
-        None,
-    );
-    assert_eq!(is_valid_route(&route), false);
-    let route = Route::new_test_route(
-        &"test".to_owned(),
-        &"test".to_owned(),
-        &"test".to_owned(),
+        "test",
+        "test",
+        "test",
         Some(&"test".to_owned()),
         Some(&"foo".to_owned()),
     );
-    assert_eq!(is_valid_route(&route), false);
+    assert!(!is_valid_route(&route, &IssuerDefaults::default()));
 
     let route = Route::new_test_route(
-        &"test".to_owned(),
-        &"test".to_owned(),
-        &"test".to_owned(),
+        "test",
+        "test",
+        "test",
         Some(&"test".to_owned()),
         Some(&CLUSTER_ISSUER_ANNOTATION_KEY.to_owned()),
     );
-    assert_eq!(is_valid_route(&route), true);
+    assert!(is_valid_route(&route, &IssuerDefaults::default()));
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"test/test-tls".to_owned()),
+        Some(&TLS_SECRET_ANNOTATION_KEY.to_owned()),
+    );
+    assert!(is_valid_route(&route, &IssuerDefaults::default()));
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "test",
+        Some(&"true".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert!(!is_valid_route(&route, &IssuerDefaults::default()));
+    assert!(is_valid_route(
+        &route,
+        &IssuerDefaults {
+            tls_acme_issuer: Some("acme".to_owned()),
+            ..Default::default()
+        }
+    ));
+}
+
+/// Whether the [`Certificate`] created for a [`Route`] is shared with the other [`Route`]s of its host.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CertificateMode {
+    /// The [`Certificate`] of the host, in the cert-manager namespace, is shared by all of its [`Route`]s.
+    Shared,
+    /// The [`Route`] gets its own [`Certificate`] and private key, in its namespace.
+    Dedicated,
 }
 
-/// Populate the TLS section of a [`Route`] with the data from a [`Certificate`].
+/// Get the [`CertificateMode`] of a [`Route`].
+///
+/// The [`CERTIFICATE_MODE_ANNOTATION_KEY`] annotation of the [`Route`] overrides the mode of its
+/// `RoutesCertificatePolicy` rule. The accepted values are `shared`, the default, and `dedicated` (case insensitive).
 ///
 /// ### Arguments
 ///
-/// * `route` - The [`Route`] to populate.
-/// * `cert_name` - The name of the [`Certificate`] to use.
-/// * `ctx` - The [`ContextData`].
+/// * `route` - The [`Route`] to get the [`CertificateMode`] for.
+/// * `defaults` - The [`IssuerDefaults`] of the [`Route`].
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing `()` or a [`kube::Error`].
+/// A [`Result`] containing the [`CertificateMode`] or a [`String`] describing why the mode is invalid.
 ///
 /// ### Example
 ///
 /// ```rust
-/// match populate_route_tls(&route, &cert_name, &ctx).await {
-///     Ok(_) => println!("Route TLS populated"),
-///     Err(e) => eprintln!("Error populating Route TLS: {}", e),
-/// }
-pub async fn populate_route_tls(
+/// let mode = get_certificate_mode(&route, &ctx.get_issuer_defaults(&route))?;
+/// println!("Certificate mode: {:?}", mode);
+/// ```
+pub fn get_certificate_mode(
     route: &Route,
-    cert_name: &str,
-    ctx: &ContextData,
-) -> Result<(), kube::Error> {
-    let data = get_secret_tls_data(&cert_name, &ctx).await?;
-    let cert = std::str::from_utf8(&data.get(TLS_CRT).unwrap().0).unwrap();
-    let key = std::str::from_utf8(&data.get(TLS_KEY).unwrap().0).unwrap();
-    let ca = if data.get(CA_CRT) == None {
-        None
-    } else {
-        Some(std::str::from_utf8(&data.get(TLS_CRT).unwrap().0).unwrap())
-    };
-    let routes = Api::<Route>::namespaced(ctx.client.clone(), &route.namespace().unwrap());
-    let (termination, insecure_edge_termination_policy) = match route.spec().tls.is_some() {
-        true => (
-            &route.spec().tls.as_ref().unwrap().termination,
-            &route.spec().tls.as_ref().unwrap().insecure_edge_termination_policy
-        ),
-        false => (&DEFAULT_TERMINATION, &Some(DEFAULT_INSECURE_EDGE_TERMINATION_POLICY))
-    };
-    let patch = serde_json::json!({
-        "metadata":{
-            "annotations": {
-                ROUTE_UPDATE_ANNOTATION_KEY: format_route_update_annotation(route.annotations().get(ROUTE_UPDATE_ANNOTATION_KEY))
-            },
+    defaults: &IssuerDefaults,
+) -> Result<CertificateMode, String> {
+    match route
+        .annotations()
+        .get(CERTIFICATE_MODE_ANNOTATION_KEY)
+        .or(defaults.policy_certificate_mode.as_ref())
+    {
+        Some(value) => match value.trim().to_lowercase().as_str() {
+            "shared" => Ok(CertificateMode::Shared),
+            "dedicated" => Ok(CertificateMode::Dedicated),
+            _ => Err(format!(
+                "Invalid certificate mode `{}`, expected one of `shared`, `dedicated`",
+                value
+            )),
         },
-        "spec": {
-            "tls": {
-                "termination": termination,
-                "insecureEdgeTerminationPolicy": insecure_edge_termination_policy,
-                "key": key,
-                "certificate": cert,
-                "caCertificate": ca.unwrap_or_default()
-            }
-        }
-    });
-    let _ = routes
-        .patch(
-            &route.name_any(),
-            &PatchParams::default(),
-            &Patch::Merge(&patch),
-        )
-        .await?;
-    Ok(())
+        None => Ok(CertificateMode::Shared),
+    }
+}
+
+#[test]
+fn test_get_certificate_mode() {
+    let mut route = Route::new_test_route("test", "test", "example.com", None, None);
+    let policy = IssuerDefaults {
+        policy_certificate_mode: Some("dedicated".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(
+        get_certificate_mode(&route, &IssuerDefaults::default()),
+        Ok(CertificateMode::Shared)
+    );
+    assert_eq!(
+        get_certificate_mode(&route, &policy),
+        Ok(CertificateMode::Dedicated)
+    );
+    route.annotations_mut().insert(
+        CERTIFICATE_MODE_ANNOTATION_KEY.to_owned(),
+        "Shared".to_owned(),
+    );
+    assert_eq!(
+        get_certificate_mode(&route, &policy),
+        Ok(CertificateMode::Shared)
+    );
+    route.annotations_mut().insert(
+        CERTIFICATE_MODE_ANNOTATION_KEY.to_owned(),
+        "private".to_owned(),
+    );
+    assert!(get_certificate_mode(&route, &policy).is_err());
 }
 
-/// Check whether a [`Route`]'s TLS is up to date the latest related [`Certificate`].
+/// Format the name of the dedicated [`Certificate`] of a [`Route`] in the format `route-dedicated-cert`.
 ///
 /// ### Arguments
 ///
-/// * `route` - The [`Route`] to check.
-/// * `cert_name` - The name of the [`Certificate`] to use.
-/// * `ctx` - The [`ContextData`].
+/// * `route_name` - The name of the [`Route`].
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing a [`bool`] indicating whether the [`Route`]'s TLS is up to date or a [`kube::Error`].
+/// A [`String`] containing the formatted [`Certificate`] name.
 ///
 /// ### Example
 ///
 /// ```rust
-/// let up_to_date = is_tls_up_to_date(&route, &cert_name, &ctx).await?;
-/// println!("TLS up to date: {}", up_to_date);
+/// let cert_name = format_dedicated_cert_name("shop");
+/// println!("{}", cert_name); // shop-dedicated-cert
 /// ```
-pub async fn is_tls_up_to_date(
+pub fn format_dedicated_cert_name(route_name: &str) -> String {
+    format_cert_name(&format!("{}-dedicated", route_name))
+}
+
+#[test]
+fn test_format_dedicated_cert_name() {
+    assert_eq!(format_dedicated_cert_name("shop"), "shop-dedicated-cert");
+}
+
+/// Get the [`TlsSource`] a [`Route`] should be populated from.
+///
+/// The [`TlsSource`] is resolved in the following order:
+/// 1. The [`TLS_SECRET_ANNOTATION_KEY`] annotation, in the `namespace/name` or `name` format,
+///    references an existing [`Secret`]. The namespace defaults to the [`Route`]'s one.
+/// 2. The [`CERTIFICATE_NAME_ANNOTATION_KEY`] annotation, in the `namespace/name` or `name` format,
+///    references an existing [`Certificate`]. The namespace must be either the cert-manager namespace,
+///    which is the default, or the [`Route`]'s one.
+/// 3. The [`Certificate`] of the cert-manager namespace named after the [`Route`]'s host
+///    in the [`format_cert_name()`] format, shared by the [`Route`]s of that host.
+///    A [`Route`] in the [`CertificateMode::Dedicated`] mode gets its own [`Certificate`] in its namespace instead,
+///    named after the [`Route`] (see [`format_dedicated_cert_name()`]).
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to get the [`TlsSource`] for.
+/// * `cert_manager_namespace` - The namespace where the controller manages the [`Certificate`]s.
+/// * `defaults` - The [`IssuerDefaults`] of the [`Route`]'s [`Namespace`].
+///
+/// ### Returns
+///
+/// [`None`] if the [`Route`] isn't handled by the controller (see [`is_valid_route()`]),
+/// else a [`Result`] containing the [`TlsSource`] or a [`String`] describing why the annotation is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let defaults = ctx.get_issuer_defaults(&route);
+/// if let Some(Ok(source)) = get_route_tls_source(&route, &ctx.cert_manager_namespace, &defaults) {
+///     println!("TLS source: {}", source);
+/// }
+/// ```
+pub fn get_route_tls_source(
     route: &Route,
-    cert_name: &str,
-    ctx: &ContextData,
-) -> Result<bool, kube::Error> {
-    let secret_data = get_secret_tls_data(cert_name, &ctx).await?;
-    if let Some(tls) = route.clone().spec.tls {
-        if tls.key == None || tls.certificate == None {
-            return Ok(false);
-        }
-        if tls.certificate.unwrap()
-            != std::str::from_utf8(&secret_data.get(TLS_CRT).unwrap().0).unwrap()
-            || tls.key.unwrap()
-                != std::str::from_utf8(&secret_data.get(TLS_KEY).unwrap().0).unwrap()
-        {
-            return Ok(false);
-        }
-        if secret_data.get(CA_CRT) != None {
-            if tls.ca_certificate == None
-                || tls.ca_certificate.unwrap()
-                    != std::str::from_utf8(&secret_data.get(CA_CRT).unwrap().0).unwrap()
+    cert_manager_namespace: &str,
+    defaults: &IssuerDefaults,
+) -> Option<Result<TlsSource, String>> {
+    if !is_valid_route(route, defaults) {
+        return None;
+    }
+    let route_namespace = route.namespace().unwrap_or_default();
+    let annotations = route.annotations();
+    if let Some(value) = annotations.get(TLS_SECRET_ANNOTATION_KEY) {
+        return Some(
+            parse_resource_reference(value, &route_namespace)
+                .map(|(namespace, name)| TlsSource::Secret(namespace, name))
+                .ok_or(format!(
+                    "Invalid `{}` annotation value `{}`, expected `namespace/name` or `name`",
+                    TLS_SECRET_ANNOTATION_KEY, value
+                )),
+        );
+    }
+    if let Some(value) = annotations.get(CERTIFICATE_NAME_ANNOTATION_KEY) {
+        return Some(match parse_resource_reference(value, cert_manager_namespace) {
+            Some((namespace, name))
+                if namespace == cert_manager_namespace || namespace == route_namespace =>
             {
-                return Ok(false);
+                Ok(TlsSource::Certificate(namespace, name))
             }
-        }
-        Ok(true)
+            Some((namespace, _)) => Err(format!(
+                "Invalid `{}` annotation value `{}`, the Certificate must be in the `{}` or `{}` namespace, not `{}`",
+                CERTIFICATE_NAME_ANNOTATION_KEY, value, cert_manager_namespace, route_namespace, namespace
+            )),
+            None => Err(format!(
+                "Invalid `{}` annotation value `{}`, expected `namespace/name` or `name`",
+                CERTIFICATE_NAME_ANNOTATION_KEY, value
+            )),
+        });
+    }
+    Some(match get_certificate_mode(route, defaults) {
+        Ok(CertificateMode::Shared) => Ok(TlsSource::Certificate(
+            cert_manager_namespace.to_owned(),
+            format_cert_name(route.spec.host.as_deref().unwrap_or_default()),
+        )),
+        Ok(CertificateMode::Dedicated) => Ok(TlsSource::Certificate(
+            route_namespace,
+            format_dedicated_cert_name(&route.name_any()),
+        )),
+        Err(e) => Err(e),
+    })
+}
+
+/// Parse a reference to a namespaced resource in the `namespace/name` or `name` format.
+fn parse_resource_reference(value: &str, default_namespace: &str) -> Option<(String, String)> {
+    let (namespace, name) = match value.trim().split_once("/") {
+        Some((namespace, name)) => (namespace.to_owned(), name.to_owned()),
+        None => (default_namespace.to_owned(), value.trim().to_owned()),
+    };
+    if namespace.is_empty() || name.is_empty() || name.contains("/") {
+        None
     } else {
-        Ok(false)
+        Some((namespace, name))
+    }
+}
+
+#[test]
+fn test_get_route_tls_source() {
+    let route = Route::new_test_route("test", "test", "example.com", None, None);
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        None
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"true".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        None
+    );
+    assert_eq!(
+        get_route_tls_source(
+            &route,
+            "cert-manager",
+            &IssuerDefaults {
+                tls_acme_issuer: Some("acme".to_owned()),
+                ..Default::default()
+            }
+        ),
+        Some(Ok(TlsSource::Certificate(
+            "cert-manager".to_owned(),
+            "example.com-cert".to_owned()
+        )))
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"issuer".to_owned()),
+        Some(&CLUSTER_ISSUER_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        Some(Ok(TlsSource::Certificate(
+            "cert-manager".to_owned(),
+            "example.com-cert".to_owned()
+        )))
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"secrets/example-tls".to_owned()),
+        Some(&TLS_SECRET_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        Some(Ok(TlsSource::Secret(
+            "secrets".to_owned(),
+            "example-tls".to_owned()
+        )))
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"example-tls".to_owned()),
+        Some(&TLS_SECRET_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        Some(Ok(TlsSource::Secret(
+            "test".to_owned(),
+            "example-tls".to_owned()
+        )))
+    );
+
+    for value in ["/example-tls", "secrets/", "a/b/c"] {
+        let route = Route::new_test_route(
+            "test",
+            "test",
+            "example.com",
+            Some(&value.to_owned()),
+            Some(&TLS_SECRET_ANNOTATION_KEY.to_owned()),
+        );
+        assert!(matches!(
+            get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+            Some(Err(_))
+        ));
     }
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"wildcard-cert".to_owned()),
+        Some(&CERTIFICATE_NAME_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        Some(Ok(TlsSource::Certificate(
+            "cert-manager".to_owned(),
+            "wildcard-cert".to_owned()
+        )))
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"test/my-cert".to_owned()),
+        Some(&CERTIFICATE_NAME_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        Some(Ok(TlsSource::Certificate(
+            "test".to_owned(),
+            "my-cert".to_owned()
+        )))
+    );
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"other/my-cert".to_owned()),
+        Some(&CERTIFICATE_NAME_ANNOTATION_KEY.to_owned()),
+    );
+    assert!(matches!(
+        get_route_tls_source(&route, "cert-manager", &IssuerDefaults::default()),
+        Some(Err(_))
+    ));
+
+    let route = Route::new_test_route(
+        "test",
+        "test",
+        "example.com",
+        Some(&"true".to_owned()),
+        Some(&TLS_ACME_ANNOTATION_KEY.to_owned()),
+    );
+    assert_eq!(
+        get_route_tls_source(
+            &route,
+            "cert-manager",
+            &IssuerDefaults {
+                tls_acme_issuer: Some("acme".to_owned()),
+                policy_certificate_mode: Some("dedicated".to_owned()),
+                ..Default::default()
+            }
+        ),
+        Some(Ok(TlsSource::Certificate(
+            "test".to_owned(),
+            "test-dedicated-cert".to_owned()
+        )))
+    );
+}
+
+/// Validate that a [`Route`]'s host can name the [`Certificate`] and [`Secret`] created for it.
+///
+/// The host must be a lowercase RFC 1123 subdomain short enough for the [`format_cert_name()`] name
+/// to remain a valid resource name.
+///
+/// ### Arguments
+///
+/// * `hostname` - The host to validate.
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`String`] describing why the host is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// validate_hostname("example.com")?;
+/// ```
+pub fn validate_hostname(hostname: &str) -> Result<(), String> {
+    let max_len = MAX_RESOURCE_NAME_LEN - format_cert_name("").len();
+    if hostname.is_empty() || hostname.len() > max_len {
+        return Err(format!(
+            "Host `{}` must be between 1 and {} characters long",
+            hostname, max_len
+        ));
+    }
+    for label in hostname.split(".") {
+        let valid = !label.is_empty()
+            && label.len() <= MAX_DNS_LABEL_LEN
+            && label
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !label.starts_with("-")
+            && !label.ends_with("-");
+        if !valid {
+            return Err(format!(
+                "Host `{}` must be a lowercase DNS name with labels of at most {} characters",
+                hostname, MAX_DNS_LABEL_LEN
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_validate_hostname() {
+    assert!(validate_hostname("example.com").is_ok());
+    assert!(validate_hostname("my-app.apps.example.com").is_ok());
+    assert!(validate_hostname("").is_err());
+    assert!(validate_hostname("*.example.com").is_err());
+    assert!(validate_hostname("Example.com").is_err());
+    assert!(validate_hostname("-app.example.com").is_err());
+    assert!(validate_hostname("app..example.com").is_err());
+    assert!(validate_hostname(&format!("{}.example.com", "a".repeat(64))).is_err());
+    assert!(validate_hostname(&format!("{}.example.com", "a".repeat(63))).is_ok());
+    let long = vec!["a".repeat(60); 4].join(".");
+    assert!(validate_hostname(&long).is_ok());
+    assert!(validate_hostname(&format!("{}.example", long)).is_err());
+}
+
+/// Parse a [`RouteTlsTermination`] from its string representation.
+///
+/// The accepted values are `edge`, `reencrypt` and `passthrough` (case insensitive).
+///
+/// ### Arguments
+///
+/// * `value` - The value to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the parsed [`RouteTlsTermination`] or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let termination = parse_termination("reencrypt");
+/// println!("Termination: {:?}", termination); // Some(Reencrypt)
+/// ```
+pub fn parse_termination(value: &str) -> Option<RouteTlsTermination> {
+    match value.trim().to_lowercase().as_str() {
+        "edge" => Some(RouteTlsTermination::Edge),
+        "reencrypt" => Some(RouteTlsTermination::Reencrypt),
+        "passthrough" => Some(RouteTlsTermination::Passthrough),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_termination() {
+    assert_eq!(parse_termination("edge"), Some(RouteTlsTermination::Edge));
+    assert_eq!(
+        parse_termination("Reencrypt"),
+        Some(RouteTlsTermination::Reencrypt)
+    );
+    assert_eq!(
+        parse_termination(" passthrough "),
+        Some(RouteTlsTermination::Passthrough)
+    );
+    assert_eq!(parse_termination(""), None);
+    assert_eq!(parse_termination("foo"), None);
+}
+
+/// Parse a [`RouteTlsInsecureEdgeTerminationPolicy`] from its string representation.
+///
+/// The accepted values are `Allow`, `None` and `Redirect` (case insensitive).
+///
+/// ### Arguments
+///
+/// * `value` - The value to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the parsed [`RouteTlsInsecureEdgeTerminationPolicy`] or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let policy = parse_insecure_edge_termination_policy("Allow");
+/// println!("Insecure policy: {:?}", policy); // Some(Allow)
+/// ```
+pub fn parse_insecure_edge_termination_policy(
+    value: &str,
+) -> Option<RouteTlsInsecureEdgeTerminationPolicy> {
+    match value.trim().to_lowercase().as_str() {
+        "allow" => Some(RouteTlsInsecureEdgeTerminationPolicy::Allow),
+        "none" => Some(RouteTlsInsecureEdgeTerminationPolicy::None),
+        "redirect" => Some(RouteTlsInsecureEdgeTerminationPolicy::Redirect),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_insecure_edge_termination_policy() {
+    assert_eq!(
+        parse_insecure_edge_termination_policy("Allow"),
+        Some(RouteTlsInsecureEdgeTerminationPolicy::Allow)
+    );
+    assert_eq!(
+        parse_insecure_edge_termination_policy("none"),
+        Some(RouteTlsInsecureEdgeTerminationPolicy::None)
+    );
+    assert_eq!(
+        parse_insecure_edge_termination_policy("REDIRECT"),
+        Some(RouteTlsInsecureEdgeTerminationPolicy::Redirect)
+    );
+    assert_eq!(parse_insecure_edge_termination_policy(""), None);
+    assert_eq!(parse_insecure_edge_termination_policy("Disable"), None);
+}
+
+/// Parse a boolean annotation value.
+///
+/// The accepted values are `true` and `false` (case insensitive).
+///
+/// ### Arguments
+///
+/// * `value` - The value to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the parsed [`bool`] or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let enabled = parse_bool("True");
+/// println!("Enabled: {:?}", enabled); // Some(true)
+/// ```
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_bool() {
+    assert_eq!(parse_bool("true"), Some(true));
+    assert_eq!(parse_bool("False"), Some(false));
+    assert_eq!(parse_bool("yes"), None);
+    assert_eq!(parse_bool(""), None);
+}
+
+/// The terminations of the [`Route`]s whose TLS can be populated by the controller.
+const SUPPORTED_TERMINATIONS: &str = "`edge`, `reencrypt`";
+
+/// Check whether a termination and an insecure edge termination policy can be applied together
+/// to a [`Route`] whose TLS is populated by the controller.
+///
+/// ### Arguments
+///
+/// * `settings` - The [`RouteTlsSettings`] to validate.
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`String`] describing why the settings are invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// match validate_tls_settings(&settings) {
+///     Ok(_) => println!("Valid TLS settings"),
+///     Err(e) => eprintln!("Invalid TLS settings: {}", e),
+/// }
+/// ```
+pub fn validate_tls_settings(settings: &RouteTlsSettings) -> Result<(), String> {
+    match settings.termination {
+        RouteTlsTermination::Passthrough => Err(format!(
+            "`passthrough` termination is not supported since the TLS is terminated by the backend and cannot be populated with a certificate, expected one of {}",
+            SUPPORTED_TERMINATIONS
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn test_validate_tls_settings() {
+    assert!(validate_tls_settings(&RouteTlsSettings::default()).is_ok());
+    assert!(validate_tls_settings(&RouteTlsSettings {
+        termination: RouteTlsTermination::Reencrypt,
+        insecure_edge_termination_policy: Some(RouteTlsInsecureEdgeTerminationPolicy::Allow),
+        external_certificate: true,
+    })
+    .is_ok());
+    assert!(validate_tls_settings(&RouteTlsSettings {
+        termination: RouteTlsTermination::Passthrough,
+        insecure_edge_termination_policy: Some(RouteTlsInsecureEdgeTerminationPolicy::Redirect),
+        external_certificate: false,
+    })
+    .is_err());
+}
+
+/// Get the [`RouteTlsSettings`] to apply to a [`Route`].
+///
+/// The settings are resolved in the following order:
+/// 1. The [`ROUTE_TERMINATION_ANNOTATION_KEY`], [`ROUTE_INSECURE_POLICY_ANNOTATION_KEY`]
+///    and [`ROUTE_EXTERNAL_CERTIFICATE_ANNOTATION_KEY`] annotations.
+/// 2. The [`Route`]'s current TLS settings, if any.
+/// 3. The controller's default settings.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to get the settings for.
+/// * `defaults` - The controller's default [`RouteTlsSettings`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`RouteTlsSettings`] or a [`String`] describing why the settings are invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let settings = get_route_tls_settings(&route, &ctx.config().tls_settings())?;
+/// println!("Termination: {:?}", settings.termination);
+/// ```
+pub fn get_route_tls_settings(
+    route: &Route,
+    defaults: &RouteTlsSettings,
+) -> Result<RouteTlsSettings, String> {
+    let annotations = route.annotations();
+    let mut settings = match route.spec.tls.as_ref() {
+        Some(tls) => RouteTlsSettings {
+            termination: tls.termination.clone(),
+            insecure_edge_termination_policy: tls.insecure_edge_termination_policy.clone(),
+            external_certificate: defaults.external_certificate,
+        },
+        None => defaults.clone(),
+    };
+    if let Some(value) = annotations.get(ROUTE_TERMINATION_ANNOTATION_KEY) {
+        settings.termination = parse_termination(value).ok_or(format!(
+            "Invalid `{}` annotation value `{}`, expected one of {}",
+            ROUTE_TERMINATION_ANNOTATION_KEY, value, SUPPORTED_TERMINATIONS
+        ))?;
+    }
+    if let Some(value) = annotations.get(ROUTE_INSECURE_POLICY_ANNOTATION_KEY) {
+        settings.insecure_edge_termination_policy =
+            Some(parse_insecure_edge_termination_policy(value).ok_or(format!(
+                "Invalid `{}` annotation value `{}`, expected one of `Allow`, `None`, `Redirect`",
+                ROUTE_INSECURE_POLICY_ANNOTATION_KEY, value
+            ))?);
+    }
+    if let Some(value) = annotations.get(ROUTE_EXTERNAL_CERTIFICATE_ANNOTATION_KEY) {
+        settings.external_certificate = parse_bool(value).ok_or(format!(
+            "Invalid `{}` annotation value `{}`, expected one of `true`, `false`",
+            ROUTE_EXTERNAL_CERTIFICATE_ANNOTATION_KEY, value
+        ))?;
+    }
+    validate_tls_settings(&settings)?;
+    Ok(settings)
+}
+
+#[test]
+fn test_get_route_tls_settings() {
+    let defaults = RouteTlsSettings::default();
+    let mut route = Route::new_test_route("test", "test", "test", None, None);
+    assert_eq!(
+        get_route_tls_settings(&route, &defaults),
+        Ok(defaults.clone())
+    );
+
+    route.annotations_mut().insert(
+        ROUTE_TERMINATION_ANNOTATION_KEY.to_owned(),
+        "reencrypt".to_owned(),
+    );
+    route.annotations_mut().insert(
+        ROUTE_INSECURE_POLICY_ANNOTATION_KEY.to_owned(),
+        "None".to_owned(),
+    );
+    assert_eq!(
+        get_route_tls_settings(&route, &defaults),
+        Ok(RouteTlsSettings {
+            termination: RouteTlsTermination::Reencrypt,
+            insecure_edge_termination_policy: Some(RouteTlsInsecureEdgeTerminationPolicy::None),
+            external_certificate: false,
+        })
+    );
+
+    route.annotations_mut().insert(
+        ROUTE_EXTERNAL_CERTIFICATE_ANNOTATION_KEY.to_owned(),
+        "true".to_owned(),
+    );
+    assert_eq!(
+        get_route_tls_settings(&route, &defaults).map(|s| s.external_certificate),
+        Ok(true)
+    );
+
+    route.annotations_mut().insert(
+        ROUTE_TERMINATION_ANNOTATION_KEY.to_owned(),
+        "passthrough".to_owned(),
+    );
+    assert!(get_route_tls_settings(&route, &defaults).is_err());
+
+    route.annotations_mut().insert(
+        ROUTE_TERMINATION_ANNOTATION_KEY.to_owned(),
+        "foo".to_owned(),
+    );
+    assert!(get_route_tls_settings(&route, &defaults).is_err());
+}
+
+/// Check whether a [`Route`] already uses `passthrough` TLS, without a [`ROUTE_TERMINATION_ANNOTATION_KEY`]
+/// annotation asking for another termination.
+///
+/// The TLS of such a [`Route`] is terminated by its backend, so the controller leaves it alone.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to check.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the [`Route`] uses `passthrough` TLS.
+///
+/// ### Example
+///
+/// ```rust
+/// if is_passthrough_route(&route) {
+///     return Ok(Action::await_change());
+/// }
+/// ```
+pub fn is_passthrough_route(route: &Route) -> bool {
+    !route
+        .annotations()
+        .contains_key(ROUTE_TERMINATION_ANNOTATION_KEY)
+        && route
+            .spec
+            .tls
+            .as_ref()
+            .is_some_and(|tls| tls.termination == RouteTlsTermination::Passthrough)
+}
+
+#[test]
+fn test_is_passthrough_route() {
+    let mut route = Route::new_test_route("test", "test", "test", None, None);
+    assert!(!is_passthrough_route(&route));
+    route.spec.tls = Some(crate::crd::route::RouteTls {
+        ca_certificate: None,
+        certificate: None,
+        destination_ca_certificate: None,
+        external_certificate: None,
+        insecure_edge_termination_policy: None,
+        key: None,
+        termination: RouteTlsTermination::Passthrough,
+    });
+    assert!(is_passthrough_route(&route));
+    assert_eq!(
+        get_route_tls_settings(&route, &RouteTlsSettings::default()),
+        Err(format!(
+            "`passthrough` termination is not supported since the TLS is terminated by the backend and cannot be populated with a certificate, expected one of {}",
+            SUPPORTED_TERMINATIONS
+        ))
+    );
+    route.annotations_mut().insert(
+        ROUTE_TERMINATION_ANNOTATION_KEY.to_owned(),
+        "edge".to_owned(),
+    );
+    assert!(!is_passthrough_route(&route));
+    assert!(get_route_tls_settings(&route, &RouteTlsSettings::default()).is_ok());
+}
+
+/// Get the names of the [`Secret`]s mirrored into a [`Route`]'s namespace for its use.
+///
+/// It contains the [`MIRROR_SECRET_ANNOTATION_KEY`] annotation value, if any,
+/// and the [`format_secret_name()`] of the [`Route`]'s host when it uses an external certificate.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] using the mirrored [`Secret`]s.
+/// * `settings` - The [`RouteTlsSettings`] of the [`Route`].
+///
+/// ### Returns
+///
+/// A [`HashSet`] of the mirrored [`Secret`] names.
+///
+/// ### Example
+///
+/// ```rust
+/// let secret_names = get_mirrored_secret_names(&route, &settings);
+/// println!("Mirrored Secrets: {:?}", secret_names);
+/// ```
+pub fn get_mirrored_secret_names(route: &Route, settings: &RouteTlsSettings) -> HashSet<String> {
+    let mut names = HashSet::new();
+    if let Some(name) = route.annotations().get(MIRROR_SECRET_ANNOTATION_KEY) {
+        names.insert(name.clone());
+    }
+    if let (true, Some(host)) = (settings.external_certificate, route.spec.host.as_ref()) {
+        names.insert(format_secret_name(host));
+    }
+    names
+}
+
+#[test]
+fn test_get_mirrored_secret_names() {
+    let mut settings = RouteTlsSettings::default();
+    let mut route = Route::new_test_route("test", "test", "example.com", None, None);
+    assert!(get_mirrored_secret_names(&route, &settings).is_empty());
+
+    route
+        .annotations_mut()
+        .insert(MIRROR_SECRET_ANNOTATION_KEY.to_owned(), "my-tls".to_owned());
+    assert_eq!(
+        get_mirrored_secret_names(&route, &settings),
+        HashSet::from(["my-tls".to_owned()])
+    );
+
+    settings.external_certificate = true;
+    assert_eq!(
+        get_mirrored_secret_names(&route, &settings),
+        HashSet::from(["my-tls".to_owned(), "example.com-tls".to_owned()])
+    );
+}
+
+/// Build the [`Error`] of a [`Route`] whose [`TlsSource`] doesn't contain a valid TLS key pair.
+fn invalid_tls_data(route: &Route, source: &TlsSource, reason: String) -> Error {
+    Error::InvalidRoute(route.to_string(), format!("the {} {}", source, reason))
+}
+
+/// Populate the TLS section of a [`Route`] with the data from a [`TlsSource`].
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to populate.
+/// * `source` - The [`TlsSource`] to use.
+/// * `settings` - The [`RouteTlsSettings`] to apply.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// match populate_route_tls(&route, &source, &settings, &ctx).await {
+///     Ok(_) => println!("Route TLS populated"),
+///     Err(e) => eprintln!("Error populating Route TLS: {}", e),
+/// }
+pub async fn populate_route_tls(
+    route: &Route,
+    source: &TlsSource,
+    settings: &RouteTlsSettings,
+    ctx: &ContextData,
+) -> Result<(), Error> {
+    let data = get_secret_tls_data(source, ctx).await?;
+    let key_pair =
+        decode_tls_data(&data).map_err(|reason| invalid_tls_data(route, source, reason))?;
+    let namespace = get_namespace(route)?;
+    let routes = Api::<Route>::namespaced(ctx.client.clone(), &namespace);
+    let patch = serde_json::json!({
+        "metadata":{
+            "annotations": {
+                ROUTE_UPDATE_ANNOTATION_KEY: format_route_update_annotation(route.annotations().get(ROUTE_UPDATE_ANNOTATION_KEY))
+            },
+        },
+        "spec": {
+            "tls": {
+                "termination": settings.termination,
+                "insecureEdgeTerminationPolicy": settings.insecure_edge_termination_policy,
+                "externalCertificate": null,
+                "key": key_pair.key,
+                "certificate": key_pair.certificate,
+                "caCertificate": key_pair.ca_certificate.unwrap_or_default()
+            }
+        }
+    });
+    let _ = routes
+        .patch(
+            &route.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
+        .await?;
+    Ok(())
+}
+
+/// Populate the TLS section of a [`Route`] with a reference to a mirror of a [`TlsSource`]'s [`Secret`].
+///
+/// The [`Secret`] is mirrored into the [`Route`]'s namespace under the [`format_secret_name()`] name,
+/// the router is granted read access to it and the [`Route`] references it through `spec.tls.externalCertificate`
+/// instead of embedding the key and certificate.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to populate.
+/// * `source` - The [`TlsSource`] to use.
+/// * `settings` - The [`RouteTlsSettings`] to apply.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing a [`bool`] indicating whether the cluster supports `spec.tls.externalCertificate`
+/// or an [`Error`]. When it is `false`, the [`Route`] is left untouched and must be populated
+/// with [`populate_route_tls()`] instead.
+///
+/// ### Example
+///
+/// ```rust
+/// match populate_route_external_certificate(&route, &source, &settings, &ctx).await {
+///     Ok(true) => println!("Route TLS populated"),
+///     Ok(false) => println!("externalCertificate is not supported"),
+///     Err(e) => eprintln!("Error populating Route TLS: {}", e),
+/// }
+/// ```
+pub async fn populate_route_external_certificate(
+    route: &Route,
+    source: &TlsSource,
+    settings: &RouteTlsSettings,
+    ctx: &ContextData,
+) -> Result<bool, Error> {
+    let namespace = get_namespace(route)?;
+    let secret_name = format_secret_name(get_route_host(route)?);
+    let _ = mirror_secret(source, route, &secret_name, ctx).await?;
+    grant_router_access(&namespace, &secret_name, ctx).await?;
+    let routes = Api::<Route>::namespaced(ctx.client.clone(), &namespace);
+    let patch = serde_json::json!({
+        "metadata":{
+            "annotations": {
+                ROUTE_UPDATE_ANNOTATION_KEY: format_route_update_annotation(route.annotations().get(ROUTE_UPDATE_ANNOTATION_KEY))
+            },
+        },
+        "spec": {
+            "tls": {
+                "termination": settings.termination,
+                "insecureEdgeTerminationPolicy": settings.insecure_edge_termination_policy,
+                "externalCertificate": {
+                    "name": secret_name,
+                },
+                "key": null,
+                "certificate": null,
+                "caCertificate": null
+            }
+        }
+    });
+    // An API server that doesn't know the field may drop it instead of rejecting it, which would leave the Route
+    // without any key pair, so the patch is tried with a dry run before the inline key pair is removed
+    let dry_run = PatchParams {
+        dry_run: true,
+        ..Default::default()
+    };
+    match routes
+        .patch(&route.name_any(), &dry_run, &Patch::Merge(&patch))
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
+        .await
+    {
+        Ok(route) if has_external_certificate(&route, &secret_name) => {}
+        Ok(_) => return Ok(false),
+        Err(kube::Error::Api(e)) if e.code == 422 && e.message.contains("externalCertificate") => {
+            return Ok(false)
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let route = routes
+        .patch(
+            &route.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
+        .await?;
+    Ok(has_external_certificate(&route, &secret_name))
+}
+
+/// Check whether a [`Route`] references a [`Secret`] through `spec.tls.externalCertificate`.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to check, as returned by the API server.
+/// * `secret_name` - The name of the [`Secret`].
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the [`Route`] references the [`Secret`].
+///
+/// ### Example
+///
+/// ```rust
+/// let supported = has_external_certificate(&patched, "example.com-tls");
+/// println!("externalCertificate stored: {}", supported);
+/// ```
+pub fn has_external_certificate(route: &Route, secret_name: &str) -> bool {
+    route
+        .spec
+        .tls
+        .as_ref()
+        .and_then(|tls| tls.external_certificate.as_ref())
+        .and_then(|certificate| certificate.name.as_deref())
+        == Some(secret_name)
+}
+
+#[test]
+fn test_has_external_certificate() {
+    let route: Route = serde_json::from_value(serde_json::json!({
+        "apiVersion": "route.openshift.io/v1",
+        "kind": "Route",
+        "metadata": { "name": "test", "namespace": "test" },
+        "spec": {
+            "host": "example.com",
+            "to": { "kind": "Service", "name": "test" },
+            "tls": {
+                "termination": "edge",
+                "externalCertificate": { "name": "example.com-tls" }
+            }
+        }
+    }))
+    .unwrap();
+    assert!(has_external_certificate(&route, "example.com-tls"));
+    assert!(!has_external_certificate(&route, "other.com-tls"));
+    let route = Route::new_test_route("test", "test", "test", None, None);
+    assert!(!has_external_certificate(&route, "example.com-tls"));
+}
+
+/// Check whether a [`Route`]'s TLS is up to date the latest related [`TlsSource`]
+/// and the [`RouteTlsSettings`] it should use.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to check.
+/// * `source` - The [`TlsSource`] to use.
+/// * `settings` - The [`RouteTlsSettings`] the [`Route`] should use.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing a [`bool`] indicating whether the [`Route`]'s TLS is up to date or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let up_to_date = is_tls_up_to_date(&route, &source, &settings, &ctx).await?;
+/// println!("TLS up to date: {}", up_to_date);
+/// ```
+pub async fn is_tls_up_to_date(
+    route: &Route,
+    source: &TlsSource,
+    settings: &RouteTlsSettings,
+    ctx: &ContextData,
+) -> Result<bool, Error> {
+    if let Some(tls) = route.clone().spec.tls {
+        if tls.termination != settings.termination
+            || tls.insecure_edge_termination_policy != settings.insecure_edge_termination_policy
+        {
+            return Ok(false);
+        }
+        if settings.external_certificate {
+            let secret_name = format_secret_name(get_route_host(route)?);
+            if tls.key.is_some()
+                || tls.certificate.is_some()
+                || tls.external_certificate.and_then(|c| c.name) != Some(secret_name.clone())
+            {
+                return Ok(false);
+            }
+            return is_secret_mirrored(source, route, &secret_name, ctx).await;
+        }
+        if tls.external_certificate.is_some() {
+            return Ok(false);
+        }
+        let secret_data = get_secret_tls_data(source, ctx).await?;
+        let key_pair = decode_tls_data(&secret_data)
+            .map_err(|reason| invalid_tls_data(route, source, reason))?;
+        Ok(tls.certificate.as_deref() == Some(key_pair.certificate)
+            && tls.key.as_deref() == Some(key_pair.key)
+            && key_pair
+                .ca_certificate
+                .is_none_or(|ca_certificate| tls.ca_certificate.as_deref() == Some(ca_certificate)))
+    } else {
+        Ok(false)
+    }
+}
+
+/// Mark a [`Route`] migrated from openshift-acme with the [`TLS_ACME_MIGRATED_ANNOTATION_KEY`] annotation.
+///
+/// The annotation value is the time at which the [`Route`] was first populated by the controller.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to mark.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// mark_tls_acme_migrated(&route, &ctx).await?;
+/// ```
+pub async fn mark_tls_acme_migrated(route: &Route, ctx: &ContextData) -> Result<(), Error> {
+    let namespace = get_namespace(route)?;
+    let routes = Api::<Route>::namespaced(ctx.client.clone(), &namespace);
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": {
+                TLS_ACME_MIGRATED_ANNOTATION_KEY: Utc::now().to_rfc3339()
+            }
+        }
+    });
+    let _ = routes
+        .patch(
+            &route.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
+        .await?;
+    Ok(())
+}
+
+/// Set or remove the [`ISSUANCE_QUEUED_ANNOTATION_KEY`] annotation of a [`Route`] waiting for the issuance budget.
+///
+/// The annotation value is the estimated time at which the [`Route`]'s [`Certificate`] will be created.
+/// The [`Route`] is only patched if the annotation changes.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to annotate.
+/// * `ready_at` - The estimated time, or [`None`] to remove the annotation.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// annotate_issuance_queued(&route, Some(Utc::now()), &ctx).await?;
+/// ```
+pub async fn annotate_issuance_queued(
+    route: &Route,
+    ready_at: Option<DateTime<Utc>>,
+    ctx: &ContextData,
+) -> Result<(), Error> {
+    let value = ready_at.map(|ready_at| ready_at.to_rfc3339_opts(SecondsFormat::Secs, true));
+    if route.annotations().get(ISSUANCE_QUEUED_ANNOTATION_KEY) == value.as_ref() {
+        return Ok(());
+    }
+    let namespace = get_namespace(route)?;
+    let routes = Api::<Route>::namespaced(ctx.client.clone(), &namespace);
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": {
+                ISSUANCE_QUEUED_ANNOTATION_KEY: value
+            }
+        }
+    });
+    let _ = routes
+        .patch(
+            &route.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
+        .await?;
+    Ok(())
 }
 
-/// Add the [`FINALIZER`] to a [`Route`].
+/// Add the [`ContextData::finalizer`] to a [`Route`].
 ///
 /// ### Arguments
 ///
@@ -280,7 +1638,7 @@ pub async fn is_tls_up_to_date(
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing `()` or a [`kube::Error`].
+/// A [`Result`] containing `()` or an [`Error`].
 ///
 /// ### Example
 ///
@@ -290,11 +1648,12 @@ pub async fn is_tls_up_to_date(
 ///   Err(e) => eprintln!("Error adding finalizer to Route: {}", e),
 /// }
 /// ```
-pub async fn add_finalizer(route: &Route, ctx: &ContextData) -> Result<(), kube::Error> {
-    let routes = Api::<Route>::namespaced(ctx.client.clone(), &route.namespace().unwrap());
+pub async fn add_finalizer(route: &Route, ctx: &ContextData) -> Result<(), Error> {
+    let namespace = get_namespace(route)?;
+    let routes = Api::<Route>::namespaced(ctx.client.clone(), &namespace);
     let patch = serde_json::json!({
         "metadata":{
-            "finalizers": [FINALIZER],
+            "finalizers": [&ctx.finalizer],
         }
     });
     let _ = routes
@@ -303,6 +1662,7 @@ pub async fn add_finalizer(route: &Route, ctx: &ContextData) -> Result<(), kube:
             &PatchParams::default(),
             &Patch::Merge(&patch),
         )
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
         .await?;
     Ok(())
 }
@@ -316,7 +1676,7 @@ pub async fn add_finalizer(route: &Route, ctx: &ContextData) -> Result<(), kube:
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing `()` or a [`kube::Error`].
+/// A [`Result`] containing `()` or an [`Error`].
 ///
 /// ### Example
 ///
@@ -326,8 +1686,9 @@ pub async fn add_finalizer(route: &Route, ctx: &ContextData) -> Result<(), kube:
 ///   Err(e) => eprintln!("Error removing finalizers from Route: {}", e),
 /// }
 /// ```
-pub async fn remove_finalizer(route: &Route, ctx: &ContextData) -> Result<(), kube::Error> {
-    let routes = Api::<Route>::namespaced(ctx.client.clone(), &route.namespace().unwrap());
+pub async fn remove_finalizer(route: &Route, ctx: &ContextData) -> Result<(), Error> {
+    let namespace = get_namespace(route)?;
+    let routes = Api::<Route>::namespaced(ctx.client.clone(), &namespace);
     let patch = serde_json::json!({
         "metadata":{
             "finalizers": null,
@@ -339,6 +1700,7 @@ pub async fn remove_finalizer(route: &Route, ctx: &ContextData) -> Result<(), ku
             &PatchParams::default(),
             &Patch::Merge(&patch),
         )
+        .instrument(api_span("patch", "Route", &namespace, &route.name_any()))
         .await?;
     Ok(())
 }
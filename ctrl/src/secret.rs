@@ -0,0 +1,626 @@
+use crate::crd::certificate::Certificate;
+use crate::crd::route::Route;
+use crate::tools::{format_cert_annotation, get_namespace, get_secret_tls_data};
+use crate::types::{ContextData, Error, TlsSource};
+use crate::{
+    CERTIFICATE_NAME_ANNOTATION_KEY, CERT_ANNOTATION_KEY, CONTROLLER_NAME,
+    MIRROR_SECRET_ANNOTATION_KEY, SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY,
+};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::api::rbac::v1::{PolicyRule, Role, RoleBinding, RoleRef, Subject};
+use kube::{
+    api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams, Preconditions},
+    Api, ResourceExt,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+const TLS_SECRET_TYPE: &str = "kubernetes.io/tls";
+/// The name of the `Role` and `RoleBinding` granting the router access to the mirrored [`Secret`]s of a namespace,
+/// fixed so that the controller's own access to them can be restricted by name.
+pub const ROUTER_ROLE_NAME: &str = "cert-manager-routes-router";
+pub const MANAGED_BY_LABEL_KEY: &str = "app.kubernetes.io/managed-by";
+
+/// Get the labels marking a resource as owned by the controller.
+pub(crate) fn owner_labels() -> BTreeMap<String, String> {
+    BTreeMap::from([(MANAGED_BY_LABEL_KEY.to_owned(), CONTROLLER_NAME.to_owned())])
+}
+
+/// Check whether a resource is owned by the controller.
+///
+/// ### Arguments
+///
+/// * `resource` - The resource to check.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the resource carries the controller's owner labels.
+///
+/// ### Example
+///
+/// ```rust
+/// let owned = is_owned(&secret);
+/// println!("Secret owned by the controller: {}", owned);
+/// ```
+pub fn is_owned<K: ResourceExt>(resource: &K) -> bool {
+    resource.labels().get(MANAGED_BY_LABEL_KEY) == Some(&CONTROLLER_NAME.to_owned())
+}
+
+#[test]
+fn test_is_owned() {
+    let mut secret = Secret::default();
+    assert!(!is_owned(&secret));
+    secret
+        .labels_mut()
+        .insert(MANAGED_BY_LABEL_KEY.to_owned(), "someone-else".to_owned());
+    assert!(!is_owned(&secret));
+    secret.metadata.labels = Some(owner_labels());
+    assert!(is_owned(&secret));
+}
+
+/// Check whether a [`Secret`] or a [`Certificate`] may be used by the [`Route`]s of a namespace.
+///
+/// It may always be used by the [`Route`]s of its own namespace.
+/// The [`Route`]s of other namespaces may only use it if the namespace is listed in its
+/// [`SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY`] comma-separated annotation, or if the annotation is `*`.
+///
+/// ### Arguments
+///
+/// * `resource` - The [`Secret`] or [`Certificate`] to check.
+/// * `namespace` - The namespace of the [`Route`]s.
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the resource is shared with the namespace.
+///
+/// ### Example
+///
+/// ```rust
+/// let shared = is_shared_with(&secret, "hello");
+/// println!("Secret shared with `hello`: {}", shared);
+/// ```
+pub fn is_shared_with<K: ResourceExt>(resource: &K, namespace: &str) -> bool {
+    if resource.namespace().as_deref() == Some(namespace) {
+        return true;
+    }
+    match resource
+        .annotations()
+        .get(SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY)
+    {
+        Some(allowed) => allowed
+            .split(",")
+            .map(|ns| ns.trim())
+            .any(|ns| ns == "*" || ns == namespace),
+        None => false,
+    }
+}
+
+#[test]
+fn test_is_shared_with() {
+    let mut secret = Secret::default();
+    secret.metadata.namespace = Some("secrets".to_owned());
+    assert!(is_shared_with(&secret, "secrets"));
+    assert!(!is_shared_with(&secret, "hello"));
+    secret.annotations_mut().insert(
+        SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY.to_owned(),
+        "foo, hello".to_owned(),
+    );
+    assert!(is_shared_with(&secret, "hello"));
+    assert!(!is_shared_with(&secret, "world"));
+    secret.annotations_mut().insert(
+        SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY.to_owned(),
+        "*".to_owned(),
+    );
+    assert!(is_shared_with(&secret, "world"));
+
+    let certificate: Certificate = serde_json::from_value(serde_json::json!({
+        "apiVersion": "cert-manager.io/v1",
+        "kind": "Certificate",
+        "metadata": {
+            "name": "platform-cert",
+            "namespace": "cert-manager",
+            "annotations": { SECRET_ALLOWED_NAMESPACES_ANNOTATION_KEY: "hello" }
+        },
+        "spec": { "secretName": "platform-tls", "issuerRef": { "name": "letsencrypt" } }
+    }))
+    .unwrap();
+    assert!(is_shared_with(&certificate, "hello"));
+    assert!(!is_shared_with(&certificate, "world"));
+}
+
+/// Check whether a [`Route`] may use the [`Secret`] or the [`Certificate`] of a [`TlsSource`].
+///
+/// The [`Route`]s may use the resources of their own namespace. A [`Secret`] of another namespace,
+/// or a [`Certificate`] of another namespace referenced by the [`CERTIFICATE_NAME_ANNOTATION_KEY`] annotation,
+/// must be shared with the [`Route`]'s namespace (see [`is_shared_with()`]).
+/// The other [`TlsSource::Certificate`]s are managed by the controller for the [`Route`]'s host and may always be used.
+///
+/// ### Arguments
+///
+/// * `source` - The [`TlsSource`] the [`Route`] requests.
+/// * `route` - The [`Route`] requesting the [`TlsSource`].
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing a [`bool`] indicating whether the [`Route`] may use the [`TlsSource`] or a [`kube::Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let allowed = can_route_use_source(&source, &route, &ctx).await?;
+/// println!("Route `{}` may use {}: {}", &route, &source, allowed);
+/// ```
+pub async fn can_route_use_source(
+    source: &TlsSource,
+    route: &Route,
+    ctx: &ContextData,
+) -> Result<bool, kube::Error> {
+    let route_namespace = route.namespace().unwrap_or_default();
+    match source {
+        TlsSource::Certificate(namespace, _) | TlsSource::Secret(namespace, _)
+            if *namespace == route_namespace =>
+        {
+            Ok(true)
+        }
+        TlsSource::Certificate(namespace, name)
+            if route
+                .annotations()
+                .contains_key(CERTIFICATE_NAME_ANNOTATION_KEY) =>
+        {
+            let certificate = Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
+                .get(name)
+                .await?;
+            Ok(is_shared_with(&certificate, &route_namespace))
+        }
+        TlsSource::Certificate(_, _) => Ok(true),
+        TlsSource::Secret(namespace, name) => {
+            let secret = Api::<Secret>::namespaced(ctx.client.clone(), namespace)
+                .get(name)
+                .await?;
+            Ok(is_shared_with(&secret, &route_namespace))
+        }
+    }
+}
+
+/// Add or remove a [`Secret`] from the ones the router's `Role` grants access to.
+///
+/// ### Arguments
+///
+/// * `role` - The `Role` of the router.
+/// * `secret_name` - The name of the [`Secret`].
+/// * `granted` - Whether the router should have access to the [`Secret`].
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the `Role` changed.
+///
+/// ### Example
+///
+/// ```rust
+/// if update_router_role(&mut role, "example.com-tls", true) {
+///     roles.replace(ROUTER_ROLE_NAME, &PostParams::default(), &role).await?;
+/// }
+/// ```
+pub fn update_router_role(role: &mut Role, secret_name: &str, granted: bool) -> bool {
+    let mut names = get_router_role_secret_names(role);
+    let changed = match granted {
+        true => names.insert(secret_name.to_owned()),
+        false => names.remove(secret_name),
+    };
+    role.rules = match names.is_empty() {
+        true => None,
+        false => Some(vec![PolicyRule {
+            api_groups: Some(vec!["".to_owned()]),
+            resources: Some(vec!["secrets".to_owned()]),
+            resource_names: Some(names.into_iter().collect()),
+            verbs: vec!["get".to_owned(), "list".to_owned(), "watch".to_owned()],
+            ..Default::default()
+        }]),
+    };
+    changed
+}
+
+/// Get the names of the [`Secret`]s the router's `Role` grants access to.
+fn get_router_role_secret_names(role: &Role) -> BTreeSet<String> {
+    role.rules
+        .iter()
+        .flatten()
+        .flat_map(|rule| rule.resource_names.iter().flatten().cloned())
+        .collect()
+}
+
+#[test]
+fn test_update_router_role() {
+    let mut role = Role::default();
+    assert!(update_router_role(&mut role, "b.example.com-tls", true));
+    assert!(update_router_role(&mut role, "a.example.com-tls", true));
+    assert!(!update_router_role(&mut role, "a.example.com-tls", true));
+    assert_eq!(
+        role.rules.as_ref().unwrap()[0].resource_names,
+        Some(vec![
+            "a.example.com-tls".to_owned(),
+            "b.example.com-tls".to_owned()
+        ])
+    );
+    assert!(update_router_role(&mut role, "a.example.com-tls", false));
+    assert!(!update_router_role(&mut role, "c.example.com-tls", false));
+    assert!(update_router_role(&mut role, "b.example.com-tls", false));
+    assert_eq!(role.rules, None);
+}
+
+/// Mirror the TLS [`Secret`] of a [`TlsSource`] into a [`Route`]'s namespace.
+///
+/// The mirrored [`Secret`] is created if it doesn't exist yet, else its data is updated.
+/// It is marked with the controller's owner labels and annotated with the [`Route`]s using it,
+/// in the same format as the [`CERT_ANNOTATION_KEY`] annotation of a [`Certificate`].
+/// As several [`Route`]s may share the mirrored [`Secret`], it is updated with its `resourceVersion`
+/// and retried on conflicts, so that no [`Route`] is dropped from the annotation.
+///
+/// An existing [`Secret`] that isn't owned by the controller is never overwritten.
+///
+/// ### Arguments
+///
+/// * `source` - The [`TlsSource`] whose [`Secret`] is mirrored.
+/// * `route` - The [`Route`] using the mirrored [`Secret`].
+/// * `secret_name` - The name of the mirrored [`Secret`].
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the mirrored [`Secret`] or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let secret = mirror_secret(&source, &route, &secret_name, &ctx).await?;
+/// println!("Mirrored Secret: {:?}", secret.metadata.name);
+/// ```
+pub async fn mirror_secret(
+    source: &TlsSource,
+    route: &Route,
+    secret_name: &str,
+    ctx: &ContextData,
+) -> Result<Secret, Error> {
+    let namespace = get_namespace(route)?;
+    let secrets = Api::<Secret>::namespaced(ctx.client.clone(), &namespace);
+    let data = get_secret_tls_data(source, ctx).await?;
+    loop {
+        let result = match secrets.get_opt(secret_name).await? {
+            Some(existing) if !is_owned(&existing) => {
+                return Err(Error::InvalidAnnotation(
+                    MIRROR_SECRET_ANNOTATION_KEY.to_owned(),
+                    format!(
+                        "Secret `{}/{}` already exists and isn't managed by the controller",
+                        &namespace, secret_name
+                    ),
+                ));
+            }
+            Some(mut secret) => {
+                let annotation = format_cert_annotation(
+                    secret.annotations().get(CERT_ANNOTATION_KEY),
+                    route,
+                    true,
+                );
+                secret
+                    .annotations_mut()
+                    .insert(CERT_ANNOTATION_KEY.to_owned(), annotation);
+                secret.data = Some(data.clone());
+                secrets
+                    .replace(secret_name, &PostParams::default(), &secret)
+                    .await
+            }
+            None => {
+                let secret = Secret {
+                    metadata: ObjectMeta {
+                        name: Some(secret_name.to_owned()),
+                        namespace: Some(namespace.clone()),
+                        labels: Some(owner_labels()),
+                        annotations: Some(BTreeMap::from([(
+                            CERT_ANNOTATION_KEY.to_owned(),
+                            format_cert_annotation(None, route, true),
+                        )])),
+                        ..Default::default()
+                    },
+                    type_: Some(TLS_SECRET_TYPE.to_owned()),
+                    data: Some(data.clone()),
+                    ..Default::default()
+                };
+                secrets.create(&PostParams::default(), &secret).await
+            }
+        };
+        match result {
+            Ok(secret) => return Ok(secret),
+            Err(kube::Error::Api(e)) if e.code == 409 => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Check whether a mirrored [`Secret`] holds the same TLS data as its [`TlsSource`]'s [`Secret`]
+/// and is annotated with a [`Route`].
+///
+/// ### Arguments
+///
+/// * `source` - The [`TlsSource`] whose [`Secret`] is mirrored.
+/// * `route` - The [`Route`] using the mirrored [`Secret`].
+/// * `secret_name` - The name of the mirrored [`Secret`].
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing a [`bool`] indicating whether the mirrored [`Secret`] is up to date or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let up_to_date = is_secret_mirrored(&source, &route, &secret_name, &ctx).await?;
+/// println!("Secret up to date: {}", up_to_date);
+/// ```
+pub async fn is_secret_mirrored(
+    source: &TlsSource,
+    route: &Route,
+    secret_name: &str,
+    ctx: &ContextData,
+) -> Result<bool, Error> {
+    let data = get_secret_tls_data(source, ctx).await?;
+    match Api::<Secret>::namespaced(ctx.client.clone(), &get_namespace(route)?)
+        .get_opt(secret_name)
+        .await?
+    {
+        Some(secret) => Ok(secret.data == Some(data) && is_secret_used_by(&secret, route)),
+        None => Ok(false),
+    }
+}
+
+/// Check whether a mirrored [`Secret`] is annotated with a [`Route`].
+fn is_secret_used_by(secret: &Secret, route: &Route) -> bool {
+    match secret.annotations().get(CERT_ANNOTATION_KEY) {
+        Some(annotation) => annotation.split(",").any(|r| r == route.to_string()),
+        None => false,
+    }
+}
+
+/// Release the mirrored [`Secret`]s a [`Route`] doesn't use anymore.
+///
+/// The [`Route`] is removed from the annotation of every mirrored [`Secret`] of its namespace
+/// that isn't in `keep`. A mirrored [`Secret`] no longer used by any [`Route`] is deleted,
+/// and the router's access to it is revoked.
+/// The mirrored [`Secret`]s annotated with the [`Route`] are found in the caches of the [`ContextData`],
+/// then updated or deleted with their `resourceVersion` and retried on conflicts (see [`mirror_secret()`]).
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] releasing the [`Secret`]s.
+/// * `keep` - The names of the mirrored [`Secret`]s the [`Route`] still uses.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the names of the released [`Secret`]s or an [`Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// let released = release_mirrored_secrets(&route, &HashSet::new(), &ctx).await?;
+/// println!("Released Secrets: {:?}", released);
+/// ```
+pub async fn release_mirrored_secrets(
+    route: &Route,
+    keep: &HashSet<String>,
+    ctx: &ContextData,
+) -> Result<Vec<String>, Error> {
+    let namespace = get_namespace(route)?;
+    let secrets = Api::<Secret>::namespaced(ctx.client.clone(), &namespace);
+    for store in &ctx.mirrored_secrets {
+        let _ = store.wait_until_ready().await;
+    }
+    let used = ctx
+        .mirrored_secrets
+        .iter()
+        .flat_map(|store| store.state())
+        .filter(|secret| {
+            secret.namespace().as_ref() == Some(&namespace)
+                && is_owned(secret.as_ref())
+                && is_secret_used_by(secret, route)
+                && !keep.contains(&secret.name_any())
+        })
+        .map(|secret| secret.name_any())
+        .collect::<Vec<_>>();
+    let mut released = vec![];
+    for name in used {
+        loop {
+            let mut secret = match secrets.get_opt(&name).await? {
+                Some(secret) if is_secret_used_by(&secret, route) => secret,
+                _ => break,
+            };
+            let annotation =
+                format_cert_annotation(secret.annotations().get(CERT_ANNOTATION_KEY), route, false);
+            let result = if annotation.is_empty() {
+                // The Secret is only deleted if no other Route started using it in the meantime
+                let params = DeleteParams {
+                    preconditions: Some(Preconditions {
+                        resource_version: secret.resource_version(),
+                        uid: None,
+                    }),
+                    ..Default::default()
+                };
+                match secrets.delete(&name, &params).await {
+                    Ok(_) => revoke_router_access(&namespace, &name, ctx).await,
+                    Err(e) => Err(e),
+                }
+            } else {
+                secret
+                    .annotations_mut()
+                    .insert(CERT_ANNOTATION_KEY.to_owned(), annotation);
+                secrets
+                    .replace(&name, &PostParams::default(), &secret)
+                    .await
+                    .map(|_| ())
+            };
+            match result {
+                Ok(_) => break,
+                Err(kube::Error::Api(e)) if e.code == 409 => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        released.push(name);
+    }
+    Ok(released)
+}
+
+/// Grant the OpenShift router service account read access to a single [`Secret`].
+///
+/// The [`Secret`] is added to the `Role` named [`ROUTER_ROLE_NAME`] in its namespace, which is bound to the
+/// router's `ServiceAccount`, as required for a [`Route`] to reference the [`Secret`]
+/// through `spec.tls.externalCertificate`. The `Role` is shared by the [`Secret`]s of the namespace,
+/// so it is updated with its `resourceVersion` and retried on conflicts.
+///
+/// ### Arguments
+///
+/// * `namespace` - The namespace of the [`Secret`].
+/// * `secret_name` - The name of the [`Secret`].
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`kube::Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// match grant_router_access(&namespace, &secret_name, &ctx).await {
+///     Ok(_) => println!("Router granted access to the Secret"),
+///     Err(e) => eprintln!("Error granting the router access to the Secret: {}", e),
+/// }
+/// ```
+pub async fn grant_router_access(
+    namespace: &str,
+    secret_name: &str,
+    ctx: &ContextData,
+) -> Result<(), kube::Error> {
+    let params = PatchParams::apply(CONTROLLER_NAME).force();
+    let metadata = ObjectMeta {
+        name: Some(ROUTER_ROLE_NAME.to_owned()),
+        namespace: Some(namespace.to_owned()),
+        labels: Some(owner_labels()),
+        ..Default::default()
+    };
+    let roles = Api::<Role>::namespaced(ctx.client.clone(), namespace);
+    // Server-side apply creates the Role if needed without owning its rules, which are updated below
+    let _ = roles
+        .patch(
+            ROUTER_ROLE_NAME,
+            &params,
+            &Patch::Apply(&Role {
+                metadata: metadata.clone(),
+                rules: None,
+            }),
+        )
+        .await?;
+    loop {
+        let mut role = roles.get(ROUTER_ROLE_NAME).await?;
+        if !update_router_role(&mut role, secret_name, true) {
+            break;
+        }
+        match roles
+            .replace(ROUTER_ROLE_NAME, &PostParams::default(), &role)
+            .await
+        {
+            Ok(_) => break,
+            Err(kube::Error::Api(e)) if e.code == 409 => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let config = ctx.config();
+    let role_binding = RoleBinding {
+        metadata,
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_owned(),
+            kind: "Role".to_owned(),
+            name: ROUTER_ROLE_NAME.to_owned(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".to_owned(),
+            name: config.router_service_account_name.clone(),
+            namespace: Some(config.router_service_account_namespace.clone()),
+            ..Default::default()
+        }]),
+    };
+    let _ = Api::<RoleBinding>::namespaced(ctx.client.clone(), namespace)
+        .patch(ROUTER_ROLE_NAME, &params, &Patch::Apply(&role_binding))
+        .await?;
+    Ok(())
+}
+
+/// Revoke the OpenShift router service account's access to a [`Secret`].
+///
+/// The [`Secret`] is removed from the `Role` named [`ROUTER_ROLE_NAME`] in its namespace.
+/// The `Role` and its `RoleBinding` are deleted once the router doesn't need access to any [`Secret`] of the namespace.
+/// Missing resources are ignored.
+///
+/// ### Arguments
+///
+/// * `namespace` - The namespace of the [`Secret`].
+/// * `secret_name` - The name of the [`Secret`].
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`kube::Error`].
+///
+/// ### Example
+///
+/// ```rust
+/// match revoke_router_access(&namespace, &secret_name, &ctx).await {
+///     Ok(_) => println!("Router access to the Secret revoked"),
+///     Err(e) => eprintln!("Error revoking the router access to the Secret: {}", e),
+/// }
+/// ```
+pub async fn revoke_router_access(
+    namespace: &str,
+    secret_name: &str,
+    ctx: &ContextData,
+) -> Result<(), kube::Error> {
+    let roles = Api::<Role>::namespaced(ctx.client.clone(), namespace);
+    loop {
+        let mut role = match roles.get_opt(ROUTER_ROLE_NAME).await? {
+            Some(role) => role,
+            None => return Ok(()),
+        };
+        if !update_router_role(&mut role, secret_name, false) {
+            return Ok(());
+        }
+        if role.rules.is_some() {
+            match roles
+                .replace(ROUTER_ROLE_NAME, &PostParams::default(), &role)
+                .await
+            {
+                Ok(_) => return Ok(()),
+                Err(kube::Error::Api(e)) if e.code == 409 => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        // The Role is only deleted if no other Secret was granted in the meantime
+        let params = DeleteParams {
+            preconditions: Some(Preconditions {
+                resource_version: role.resource_version(),
+                uid: None,
+            }),
+            ..Default::default()
+        };
+        match roles.delete(ROUTER_ROLE_NAME, &params).await {
+            Ok(_) => break,
+            Err(kube::Error::Api(e)) if e.code == 404 => break,
+            Err(kube::Error::Api(e)) if e.code == 409 => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    match Api::<RoleBinding>::namespaced(ctx.client.clone(), namespace)
+        .delete(ROUTER_ROLE_NAME, &DeleteParams::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(e)) if e.code == 404 => Ok(()),
+        Err(e) => Err(e),
+    }
+}
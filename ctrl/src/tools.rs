@@ -1,12 +1,14 @@
 use crate::crd::{certificate::Certificate, route::Route};
-use crate::route::{TLS_CRT, TLS_KEY};
-use crate::types::ContextData;
+use crate::route::{CA_CRT, TLS_CRT, TLS_KEY};
+use crate::types::{ContextData, Error, ErrorClass, TlsKeyPair, TlsSource};
 use chrono::Utc;
 use k8s_openapi::api::core::v1::Secret;
 use k8s_openapi::ByteString;
-use kube::Api;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Api, Client, Resource, ResourceExt};
 use std::collections::BTreeMap;
-use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{info_span, Instrument, Span};
 
 /// Format a resource to a string in the format `namespace:name`.
 ///
@@ -35,10 +37,69 @@ fn test_resource_to_string() {
     assert_ne!(resource_to_string("name", "namespace"), "name/namespace");
 }
 
+/// Create an [`Api`] of the objects of a namespace, or of the whole cluster.
+///
+/// ### Arguments
+///
+/// * `client` - The Kubernetes [`Client`].
+/// * `namespace` - The namespace of the objects, or [`None`] for the whole cluster.
+///
+/// ### Returns
+///
+/// The [`Api`] of the objects.
+///
+/// ### Example
+///
+/// ```rust
+/// let routes = scoped_api::<Route>(ctx.client.clone(), Some("hello"));
+/// ```
+pub fn scoped_api<K>(client: Client, namespace: Option<&str>) -> Api<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>,
+    K::DynamicType: Default,
+{
+    match namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    }
+}
+
+/// Create the span of a Kubernetes API call.
+///
+/// The span is named `<verb> <kind>` when exported over OTLP, and is a child of the
+/// `reconcile` span of the [`Route`] being reconciled.
+///
+/// ### Arguments
+///
+/// * `verb` - The verb of the call, such as `get` or `patch`.
+/// * `kind` - The kind of the object.
+/// * `namespace` - The namespace of the object.
+/// * `name` - The name of the object, empty for a `list`.
+///
+/// ### Returns
+///
+/// A [`Span`] to instrument the call with.
+///
+/// ### Example
+///
+/// ```rust
+/// let cert = api.get(&name).instrument(api_span("get", "Certificate", &namespace, &name)).await?;
+/// ```
+pub fn api_span(verb: &str, kind: &str, namespace: &str, name: &str) -> Span {
+    info_span!(
+        "api",
+        otel.name = %format!("{} {}", verb, kind),
+        verb,
+        kind,
+        namespace,
+        name
+    )
+}
+
 /// Format a [`Certificate`] annotation value in the format `namespace:name(,namespace:name)*`.
 ///
 /// If the annotation doesn't exists yet, the annotation value will contain a single route.
-/// Else, the route will be appended to the annotation value.
+/// Else, the route will be appended to the annotation value, preserving the order of the existing routes.
 ///
 /// ### Arguments
 ///
@@ -64,16 +125,18 @@ pub fn format_cert_annotation(
         Some(cert_annotation) if cert_annotation.is_empty() && add => route.to_string(),
         None if add => route.to_string(),
         Some(cert_annotation) => {
-            let mut annotations: HashSet<String> = HashSet::new();
+            let mut annotations: Vec<String> = vec![];
             cert_annotation.split(",").for_each(|annotation| {
-                annotations.insert(annotation.to_owned());
+                if !annotation.is_empty() && !annotations.contains(&annotation.to_owned()) {
+                    annotations.push(annotation.to_owned());
+                }
             });
-            if add {
-                annotations.insert(route.to_string());
-            } else {
-                annotations.remove(&route.to_string());
+            if add && !annotations.contains(&route.to_string()) {
+                annotations.push(route.to_string());
+            } else if !add {
+                annotations.retain(|annotation| annotation != &route.to_string());
             }
-            annotations.into_iter().collect::<Vec<String>>().join(",")
+            annotations.join(",")
         }
         _ => String::new(),
     }
@@ -81,13 +144,7 @@ pub fn format_cert_annotation(
 
 #[test]
 fn test_format_cert_annotation() {
-    let route = Route::new_test_route(
-        &"world".to_owned(),
-        &"hello".to_owned(),
-        &"example.com".to_owned(),
-        None,
-        None,
-    );
+    let route = Route::new_test_route("world", "hello", "example.com", None, None);
     assert_eq!(format_cert_annotation(None, &route, true), "hello/world");
     assert_eq!(
         format_cert_annotation(Some(&"".to_owned()), &route, true),
@@ -101,6 +158,10 @@ fn test_format_cert_annotation() {
         format_cert_annotation(Some(&"foo/bar,alice/bob".to_owned()), &route, true),
         "foo/bar,alice/bob,hello/world"
     );
+    assert_eq!(
+        format_cert_annotation(Some(&"hello/world,foo/bar".to_owned()), &route, true),
+        "hello/world,foo/bar"
+    );
     assert_eq!(format_cert_annotation(None, &route, false), "");
     assert_eq!(
         format_cert_annotation(Some(&"".to_owned()), &route, false),
@@ -168,42 +229,202 @@ fn test_format_secret_name() {
     assert_ne!(format_secret_name("example.com"), "example.com");
 }
 
-/// Get the TLS data from a [`Secret`]'s [`Certificate`].
+/// Get the namespace of a namespaced resource.
+///
+/// ### Arguments
+///
+/// * `resource` - The resource.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the namespace, or an [`Error::MissingNamespace`] if the resource is cluster-scoped.
+///
+/// ### Example
+///
+/// ```rust
+/// let namespace = get_namespace(&route)?;
+/// println!("Namespace: {}", namespace);
+/// ```
+pub fn get_namespace<K: ResourceExt>(resource: &K) -> Result<String, Error> {
+    resource
+        .namespace()
+        .ok_or_else(|| Error::MissingNamespace(resource.name_any()))
+}
+
+#[test]
+fn test_get_namespace() {
+    let route = Route::new_test_route("world", "hello", "example.com", None, None);
+    assert_eq!(get_namespace(&route).unwrap(), "hello");
+    let route: Route =
+        serde_json::from_str(include_str!("../fixtures/routes/cluster-scoped-route.json")).unwrap();
+    assert!(matches!(
+        get_namespace(&route),
+        Err(Error::MissingNamespace(name)) if name == "world"
+    ));
+}
+
+/// Get the TLS data from a [`TlsSource`]'s [`Secret`].
+///
+/// For a [`TlsSource::Certificate`], the [`Secret`] is the one referenced by the [`Certificate`].
 ///
 /// ### Arguments
 ///
-/// * `cert_name` - The name of the [`Certificate`] to extract the TLS data from.
+/// * `source` - The [`TlsSource`] to extract the TLS data from.
 /// * `ctx` - The [`ContextData`].
 ///
 /// ### Returns
 ///
-/// A [`Result`] containing a [`BTreeMap`] of the TLS data or a [`kube::Error`].
+/// A [`Result`] containing a [`BTreeMap`] of the TLS data, or an [`Error`]:
+/// [`Error::IssuerNotReady`] if the [`Certificate`] wasn't issued yet,
+/// [`Error::InvalidSecretData`] if the existing [`Secret`] doesn't contain a valid key pair.
 ///
 /// ### Example
 ///
 /// ```rust
-/// let tls_data = get_secret_tls_data(&cert_name, &ctx).await?;
+/// let tls_data = get_secret_tls_data(&TlsSource::Certificate(namespace, cert_name), &ctx).await?;
 /// println!("TLS data: {:?}", tls_data);
 /// ```
 pub async fn get_secret_tls_data(
-    cert_name: &str,
+    source: &TlsSource,
     ctx: &ContextData,
-) -> Result<BTreeMap<std::string::String, ByteString>, kube::Error> {
-    let certificate =
-        Api::<Certificate>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
-            .get(&cert_name)
-            .await?;
-    let secret = Api::<Secret>::namespaced(ctx.client.clone(), &ctx.cert_manager_namespace)
-        .get(&certificate.spec.secret_name)
-        .await?;
-    let data = secret.data.unwrap();
-    if data.get(TLS_CRT) == None || data.get(TLS_KEY) == None {
-        Err(kube::error::Error::Discovery(
-            kube::error::DiscoveryError::MissingResource("tls".to_owned()),
-        ))
-    } else {
-        Ok(data)
-    }
+) -> Result<BTreeMap<std::string::String, ByteString>, Error> {
+    let data = match source {
+        TlsSource::Certificate(namespace, cert_name) => {
+            let certificate = Api::<Certificate>::namespaced(ctx.client.clone(), namespace)
+                .get(cert_name)
+                .instrument(api_span("get", "Certificate", namespace, cert_name))
+                .await?;
+            let not_ready = |reason: String| {
+                Error::IssuerNotReady(resource_to_string(cert_name, namespace), reason)
+            };
+            let secret = Api::<Secret>::namespaced(ctx.client.clone(), namespace)
+                .get_opt(&certificate.spec.secret_name)
+                .instrument(api_span(
+                    "get",
+                    "Secret",
+                    namespace,
+                    &certificate.spec.secret_name,
+                ))
+                .await?
+                .ok_or_else(|| {
+                    not_ready(format!(
+                        "Secret `{}` doesn't exist",
+                        certificate.spec.secret_name
+                    ))
+                })?;
+            validate_tls_data(secret.data.unwrap_or_default()).map_err(|reason| {
+                not_ready(format!(
+                    "Secret `{}` {}",
+                    certificate.spec.secret_name, reason
+                ))
+            })?
+        }
+        TlsSource::Secret(namespace, name) => {
+            let secret = Api::<Secret>::namespaced(ctx.client.clone(), namespace)
+                .get(name)
+                .instrument(api_span("get", "Secret", namespace, name))
+                .await?;
+            validate_tls_data(secret.data.unwrap_or_default()).map_err(|reason| {
+                Error::InvalidSecretData(resource_to_string(name, namespace), reason)
+            })?
+        }
+    };
+    Ok(data)
+}
+
+/// Check that the data of a [`Secret`] contains a valid TLS key pair.
+///
+/// ### Returns
+///
+/// A [`Result`] containing the data, or a [`String`] describing why the key pair is invalid.
+fn validate_tls_data(
+    data: BTreeMap<std::string::String, ByteString>,
+) -> Result<BTreeMap<std::string::String, ByteString>, String> {
+    decode_tls_data(&data)?;
+    Ok(data)
+}
+
+#[test]
+fn test_validate_tls_data() {
+    let secret: Secret =
+        serde_json::from_str(include_str!("../fixtures/secrets/empty-secret.json")).unwrap();
+    assert_eq!(secret.data, None);
+    assert_eq!(
+        validate_tls_data(secret.data.unwrap_or_default()),
+        Err("is missing `tls.crt`".to_owned())
+    );
+
+    let mut data = BTreeMap::new();
+    data.insert(TLS_CRT.to_owned(), ByteString(b"cert".to_vec()));
+    assert_eq!(
+        validate_tls_data(data.clone()),
+        Err("is missing `tls.key`".to_owned())
+    );
+    data.insert(TLS_KEY.to_owned(), ByteString(b"key".to_vec()));
+    assert_eq!(validate_tls_data(data.clone()), Ok(data));
+}
+
+/// Decode the PEM-encoded TLS key pair of the data of a [`Secret`].
+///
+/// ### Arguments
+///
+/// * `data` - The data of the [`Secret`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing the [`TlsKeyPair`], or a [`String`] describing why the key pair is missing or invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let key_pair = decode_tls_data(&secret.data.unwrap_or_default())?;
+/// println!("Certificate: {}", key_pair.certificate);
+/// ```
+pub fn decode_tls_data(
+    data: &BTreeMap<std::string::String, ByteString>,
+) -> Result<TlsKeyPair<'_>, String> {
+    let decode = |key: &str| match data.get(key) {
+        Some(value) => std::str::from_utf8(&value.0)
+            .map(Some)
+            .map_err(|_| format!("has a `{}` that isn't valid UTF-8", key)),
+        None => Ok(None),
+    };
+    let required = |key: &str| decode(key)?.ok_or_else(|| format!("is missing `{}`", key));
+    Ok(TlsKeyPair {
+        certificate: required(TLS_CRT)?,
+        key: required(TLS_KEY)?,
+        ca_certificate: decode(CA_CRT)?,
+    })
+}
+
+#[test]
+fn test_decode_tls_data() {
+    let secret: Secret =
+        serde_json::from_str(include_str!("../fixtures/secrets/non-utf8-secret.json")).unwrap();
+    let data = secret.data.unwrap_or_default();
+    assert_eq!(
+        decode_tls_data(&data),
+        Err("has a `tls.key` that isn't valid UTF-8".to_owned())
+    );
+
+    let mut data = BTreeMap::new();
+    data.insert(TLS_CRT.to_owned(), ByteString(b"cert".to_vec()));
+    data.insert(TLS_KEY.to_owned(), ByteString(b"key".to_vec()));
+    assert_eq!(
+        decode_tls_data(&data),
+        Ok(TlsKeyPair {
+            certificate: "cert",
+            key: "key",
+            ca_certificate: None,
+        })
+    );
+    data.insert(CA_CRT.to_owned(), ByteString(b"ca".to_vec()));
+    assert_eq!(decode_tls_data(&data).unwrap().ca_certificate, Some("ca"));
+    data.insert(CA_CRT.to_owned(), ByteString(vec![0xff, 0xfe, 0xfd]));
+    assert_eq!(
+        decode_tls_data(&data),
+        Err("has a `ca.crt` that isn't valid UTF-8".to_owned())
+    );
 }
 
 /// Format a [`Route`] update annotation value in the format `timestamp(,timestamp)*`.
@@ -231,3 +452,259 @@ pub fn format_route_update_annotation(annotation: Option<&String>) -> String {
         None => format!("{}", Utc::now()),
     }
 }
+
+/// Parse a duration in the Go format used by cert-manager, such as `2160h` or `1h30m`.
+///
+/// The accepted units are `ns`, `us`, `ms`, `s`, `m` and `h`, and each value may have a fractional part.
+///
+/// ### Arguments
+///
+/// * `value` - The value to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the [`Duration`], or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let duration = parse_duration("1h30m");
+/// println!("{:?}", duration); // Some(5400s)
+/// ```
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    if value.is_empty() {
+        return None;
+    }
+    let mut seconds = 0f64;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number = rest[..number_len].parse::<f64>().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+        seconds += number * unit;
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(
+        parse_duration("2160h"),
+        Some(Duration::from_secs(2160 * 3600))
+    );
+    assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("1.5h"), Some(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+    assert_eq!(parse_duration(""), None);
+    assert_eq!(parse_duration("90"), None);
+    assert_eq!(parse_duration("90d"), None);
+    assert_eq!(parse_duration("h"), None);
+}
+
+/// Parse an interval, either a duration such as `1h30m` or a number of seconds.
+///
+/// ### Arguments
+///
+/// * `value` - The interval to parse.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the [`Duration`], or [`None`] if the value is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let interval = parse_interval("3600");
+/// println!("{:?}", interval); // Some(3600s)
+/// ```
+pub fn parse_interval(value: &str) -> Option<Duration> {
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => parse_duration(value),
+    }
+}
+
+#[test]
+fn test_parse_interval() {
+    assert_eq!(parse_interval("3600"), Some(Duration::from_secs(3600)));
+    assert_eq!(parse_interval("1h"), Some(Duration::from_secs(3600)));
+    assert_eq!(parse_interval("-1"), None);
+    assert_eq!(parse_interval(""), None);
+}
+
+/// Format a [`Duration`] as a string that [`parse_duration()`] parses, such as `1h30m`.
+///
+/// ### Arguments
+///
+/// * `duration` - The [`Duration`] to format.
+///
+/// ### Returns
+///
+/// A [`String`] containing the formatted duration.
+///
+/// ### Example
+///
+/// ```rust
+/// let duration = format_duration(Duration::from_secs(5400));
+/// println!("{}", duration); // 1h30m
+/// ```
+pub fn format_duration(duration: Duration) -> String {
+    if duration.subsec_nanos() != 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+    let seconds = duration.as_secs();
+    let mut value = String::new();
+    if seconds >= 3600 {
+        value.push_str(&format!("{}h", seconds / 3600));
+    }
+    if seconds % 3600 >= 60 {
+        value.push_str(&format!("{}m", seconds % 3600 / 60));
+    }
+    if !seconds.is_multiple_of(60) || value.is_empty() {
+        value.push_str(&format!("{}s", seconds % 60));
+    }
+    value
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(Duration::from_secs(168 * 3600)), "168h");
+    assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+    assert_eq!(format_duration(Duration::from_secs(3601)), "1h1s");
+    assert_eq!(format_duration(Duration::from_secs(25)), "25s");
+    assert_eq!(format_duration(Duration::ZERO), "0s");
+    assert_eq!(format_duration(Duration::from_millis(1500)), "1500ms");
+}
+
+/// Get the delay before retrying a failed reconciliation.
+///
+/// The delay grows exponentially with the number of consecutive failures, from a base and up to a cap that
+/// depend on the [`ErrorClass`], and is spread by up to 20% either way to avoid retrying many objects at once.
+///
+/// ### Arguments
+///
+/// * `class` - The [`ErrorClass`] of the error.
+/// * `attempt` - The number of previous consecutive failures.
+/// * `jitter` - A random number between `0` and `1`.
+///
+/// ### Returns
+///
+/// An [`Option`] containing the delay, or [`None`] if the reconciliation shouldn't be retried until the object changes.
+///
+/// ### Example
+///
+/// ```rust
+/// let delay = get_backoff_duration(ErrorClass::Conflict, 0, 0.5);
+/// println!("{:?}", delay); // Some(1s)
+/// ```
+pub fn get_backoff_duration(class: ErrorClass, attempt: u32, jitter: f64) -> Option<Duration> {
+    let (base, cap): (u64, u64) = match class {
+        ErrorClass::Conflict => (1, 30),
+        ErrorClass::Transient => (5, 300),
+        ErrorClass::NotReady => (30, 900),
+        ErrorClass::Invalid => return None,
+    };
+    let delay = base.saturating_mul(2u64.saturating_pow(attempt)).min(cap) as f64;
+    Some(Duration::from_secs_f64(delay * (0.8 + 0.4 * jitter)))
+}
+
+#[test]
+fn test_get_backoff_duration() {
+    assert_eq!(
+        get_backoff_duration(ErrorClass::Conflict, 0, 0.5),
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(
+        get_backoff_duration(ErrorClass::Conflict, 3, 0.5),
+        Some(Duration::from_secs(8))
+    );
+    assert_eq!(
+        get_backoff_duration(ErrorClass::Conflict, 64, 0.5),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+        get_backoff_duration(ErrorClass::Transient, 1, 0.0),
+        Some(Duration::from_secs(8))
+    );
+    assert_eq!(
+        get_backoff_duration(ErrorClass::NotReady, 10, 1.0),
+        Some(Duration::from_secs(1080))
+    );
+    assert_eq!(get_backoff_duration(ErrorClass::Invalid, 0, 0.5), None);
+}
+
+/// Join the items of a list, summarizing the ones past a length as `and N more`.
+///
+/// The event notes are limited to 1 KiB, so the lists they contain are capped.
+///
+/// ### Arguments
+///
+/// * `items` - The items of the list.
+/// * `max_len` - The maximum length of the joined list.
+///
+/// ### Returns
+///
+/// A [`String`] containing the joined list.
+///
+/// ### Example
+///
+/// ```rust
+/// let list = format_capped_list(&["a".to_owned(), "b".to_owned(), "c".to_owned()], 16);
+/// println!("{}", list); // a, b and 1 more
+/// ```
+pub fn format_capped_list(items: &[String], max_len: usize) -> String {
+    let joined = items.join(", ");
+    if joined.len() <= max_len {
+        return joined;
+    }
+    let mut list = String::new();
+    for (index, item) in items.iter().enumerate() {
+        let separator = if index == 0 { "" } else { ", " };
+        // Keep room for the summary of the items following this one
+        let summary_len = match items.len() - index - 1 {
+            0 => 0,
+            more => format!(" and {} more", more).len(),
+        };
+        if list.len() + separator.len() + item.len() + summary_len > max_len {
+            let more = items.len() - index;
+            return match list.is_empty() {
+                true => format!("{} more", more),
+                false => format!("{} and {} more", list, more),
+            };
+        }
+        list.push_str(separator);
+        list.push_str(item);
+    }
+    list
+}
+
+#[test]
+fn test_format_capped_list() {
+    let items = ["a", "b", "a-long-item"].map(|item| item.to_owned());
+    assert_eq!(format_capped_list(&items, 100), "a, b, a-long-item");
+    assert_eq!(format_capped_list(&items, 17), "a, b, a-long-item");
+    assert_eq!(format_capped_list(&items, 16), "a, b and 1 more");
+    assert_eq!(format_capped_list(&items, 14), "a and 2 more");
+    assert_eq!(format_capped_list(&items, 0), "3 more");
+    assert_eq!(format_capped_list(&[], 0), "");
+    let items = (0..100)
+        .map(|index| format!("item-{}", index))
+        .collect::<Vec<_>>();
+    assert!(format_capped_list(&items, 512).len() <= 512);
+}
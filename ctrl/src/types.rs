@@ -1,21 +1,733 @@
-use kube::{runtime::events::Recorder, Client};
+use crate::budget::{
+    acquire_issuance, dequeue_route, get_budget_key, save_budget_state, BudgetState, IssuanceBudget,
+};
+use crate::certificate::{get_certificate_group, CertificateGroup};
+use crate::config::Config;
+use crate::crd::certificate::Certificate;
+use crate::crd::policy::{RoutesCertificatePolicy, RoutesCertificatePolicyRule};
+use crate::crd::route::Route;
+use crate::namespace::{get_issuer_defaults, is_issuer_allowed, IssuerDefaults};
+use crate::policy::get_route_policy_rule;
+use crate::route::{
+    get_certificate_mode, get_route_cluster_issuer, get_route_tls_source, CertificateMode,
+};
+use crate::tools::resource_to_string;
+use crate::CERTIFICATE_NAME_ANNOTATION_KEY;
+use chrono::{DateTime, TimeZone, Utc};
+use k8s_openapi::api::core::v1::{Namespace, ObjectReference, Secret};
+use kube::runtime::events::{Recorder, Reporter};
+use kube::runtime::reflector::{ObjectRef, Store};
+use kube::{Client, Resource, ResourceExt};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, PoisonError, RwLock,
+};
+use tokio::sync::Mutex;
 
+/// The errors of the reconciliation of a [`Route`].
+///
+/// The [`ErrorClass`] of an error decides how the [`Route`] is retried.
 #[derive(thiserror::Error, Debug)]
-pub enum Error {}
+pub enum Error {
+    /// A Kubernetes API call failed.
+    #[error("Kubernetes API error: {0}")]
+    Kube(#[source] kube::Error),
+    /// An update was rejected because the object changed concurrently.
+    #[error("Conflicting update: {0}")]
+    Conflict(#[source] kube::Error),
+    /// The controller isn't allowed to perform a Kubernetes API call.
+    #[error("Forbidden API call: {0}")]
+    Forbidden(#[source] kube::Error),
+    /// An object doesn't exist (yet).
+    #[error("Object not found: {0}")]
+    NotFound(#[source] kube::Error),
+    /// A `Secret`, by namespace and name, doesn't contain a valid TLS key pair.
+    #[error("Invalid Secret `{0}`: {1}")]
+    InvalidSecretData(String, String),
+    /// A `Certificate`, by namespace and name, wasn't issued by its `ClusterIssuer` yet.
+    #[error("Certificate `{0}` isn't issued yet: {1}")]
+    IssuerNotReady(String, String),
+    /// The [`Route`], by namespace and name, can't be reconciled as is.
+    #[error("Invalid Route `{0}`: {1}")]
+    InvalidRoute(String, String),
+    /// An annotation of the [`Route`] is invalid.
+    #[error("Invalid annotation `{0}`: {1}")]
+    InvalidAnnotation(String, String),
+    /// An object, by name, that should be namespaced has no namespace.
+    #[error("`{0}` isn't namespaced")]
+    MissingNamespace(String),
+}
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Classify the [`kube::Error`]s by their HTTP status code.
+impl From<kube::Error> for Error {
+    fn from(error: kube::Error) -> Self {
+        match &error {
+            kube::Error::Api(response) if response.code == 409 => Error::Conflict(error),
+            kube::Error::Api(response) if response.code == 403 => Error::Forbidden(error),
+            kube::Error::Api(response) if response.code == 404 => Error::NotFound(error),
+            _ => Error::Kube(error),
+        }
+    }
+}
+
+/// How a failed reconciliation is retried.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorClass {
+    /// Concurrent updates, retried quickly.
+    Conflict,
+    /// Transient API errors, retried with a moderate backoff.
+    Transient,
+    /// Objects that aren't ready yet, retried with a slow backoff.
+    NotReady,
+    /// Invalid configurations, not retried until an object changes.
+    Invalid,
+}
+
+impl Error {
+    /// Get the [`ErrorClass`] of the error.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::Conflict(_) => ErrorClass::Conflict,
+            Error::Kube(_) => ErrorClass::Transient,
+            Error::Forbidden(_) | Error::NotFound(_) | Error::IssuerNotReady(_, _) => {
+                ErrorClass::NotReady
+            }
+            Error::InvalidSecretData(_, _)
+            | Error::InvalidRoute(_, _)
+            | Error::InvalidAnnotation(_, _)
+            | Error::MissingNamespace(_) => ErrorClass::Invalid,
+        }
+    }
+}
+
+#[test]
+fn test_error_class() {
+    let api_error = |code: u16| {
+        Error::from(kube::Error::Api(kube::error::ErrorResponse {
+            status: "Failure".to_owned(),
+            message: String::new(),
+            reason: String::new(),
+            code,
+        }))
+    };
+    assert_eq!(api_error(409).class(), ErrorClass::Conflict);
+    assert_eq!(api_error(500).class(), ErrorClass::Transient);
+    assert_eq!(api_error(403).class(), ErrorClass::NotReady);
+    assert_eq!(api_error(404).class(), ErrorClass::NotReady);
+    assert_eq!(
+        Error::IssuerNotReady("cert-manager/hello-cert".to_owned(), String::new()).class(),
+        ErrorClass::NotReady
+    );
+    assert_eq!(
+        Error::InvalidRoute("hello/world".to_owned(), String::new()).class(),
+        ErrorClass::Invalid
+    );
+    assert_eq!(
+        Error::MissingNamespace("world".to_owned()).class(),
+        ErrorClass::Invalid
+    );
+}
+
 pub struct ContextData {
     pub client: Client,
     pub cert_manager_namespace: String,
+    /// The recorder of the events about the controller itself, in the cert-manager namespace.
     pub recorder: Recorder,
+    /// The reporter of the events, used to record events about each `Route`.
+    pub reporter: Reporter,
+    /// The live [`Config`], swapped when the configuration `ConfigMap` changes.
+    pub config: RwLock<Arc<Config>>,
+    /// The cache of the cluster's `Namespace`s by name, used to resolve their [`IssuerDefaults`].
+    /// It is kept up to date by the `Namespace` watch before the related `Route`s are reconciled.
+    pub namespaces: RwLock<HashMap<String, Namespace>>,
+    /// The cache of the cluster's [`RoutesCertificatePolicy`]s.
+    pub policies: Store<RoutesCertificatePolicy>,
+    /// The caches of the [`Certificate`]s, one per watched scope.
+    pub certificates: Vec<Store<Certificate>>,
+    /// The caches of the TLS `Secret`s mirrored by the controller, one per watched scope.
+    pub mirrored_secrets: Vec<Store<Secret>>,
+    /// The `Route`s using each TLS `Secret` of a [`TlsSource::Secret`], by namespace and name of the `Secret`.
+    /// It is kept up to date by the reconciles, so that a `Secret` event only concerns the `Route`s using it.
+    pub secret_routes: RwLock<HashMap<String, HashSet<ObjectRef<Route>>>>,
+    /// Whether the cluster supports `spec.tls.externalCertificate` on Routes.
+    /// It is unset the first time the cluster drops or rejects the field.
+    pub external_certificate_supported: AtomicBool,
+    /// The budget of `Certificate` creations.
+    /// The creations aren't limited when it is unset.
+    pub issuance_budget: Option<IssuanceBudget>,
+    /// The consumption of the [`IssuanceBudget`], locked until it is saved.
+    pub budget_state: Mutex<BudgetState>,
+    /// The finalizer added to the managed `Route`s.
+    pub finalizer: String,
+    /// The number of consecutive failed reconciliations of the `Route`s, by namespace and name.
+    pub failures: RwLock<HashMap<String, u32>>,
+    /// The `Route`s being reconciled, by namespace and name, logged if they don't finish before the shutdown.
+    pub reconciling: RwLock<HashSet<String>>,
+    /// Whether the replica holds the leader `Lease` and may reconcile the `Route`s.
+    /// It is always set when the leader election is disabled.
+    pub leader: AtomicBool,
 }
 
 impl ContextData {
-    pub fn new(client: Client, cert_manager_namespace: String, recorder: Recorder) -> Self {
+    pub fn new(
+        client: Client,
+        reporter: Reporter,
+        policies: Store<RoutesCertificatePolicy>,
+        certificates: Vec<Store<Certificate>>,
+        mirrored_secrets: Vec<Store<Secret>>,
+        config: &Config,
+    ) -> Self {
+        let reference = ObjectReference {
+            namespace: Some(config.cert_manager_namespace.clone()),
+            ..Default::default()
+        };
         Self {
+            recorder: Recorder::new(client.clone(), reporter.clone(), reference),
             client,
-            cert_manager_namespace,
-            recorder,
+            cert_manager_namespace: config.cert_manager_namespace.clone(),
+            reporter,
+            config: RwLock::new(Arc::new(config.clone())),
+            namespaces: RwLock::new(HashMap::new()),
+            policies,
+            certificates,
+            mirrored_secrets,
+            secret_routes: RwLock::new(HashMap::new()),
+            external_certificate_supported: AtomicBool::new(true),
+            issuance_budget: None,
+            budget_state: Mutex::new(BudgetState::default()),
+            finalizer: config.finalizer.clone(),
+            failures: RwLock::new(HashMap::new()),
+            reconciling: RwLock::new(HashSet::new()),
+            leader: AtomicBool::new(true),
+        }
+    }
+
+    /// Only reconcile the `Route`s once the replica acquired the leader `Lease`.
+    pub fn with_leader_election(self) -> Self {
+        self.leader.store(false, Ordering::Relaxed);
+        self
+    }
+
+    /// Get a [`Recorder`] publishing events about a [`Route`], in its namespace.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] the events are about.
+    pub fn route_recorder(&self, route: &Route) -> Recorder {
+        Recorder::new(
+            self.client.clone(),
+            self.reporter.clone(),
+            route.object_ref(&()),
+        )
+    }
+
+    /// Check whether the replica may reconcile the `Route`s.
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    /// Get the live [`Config`].
+    pub fn config(&self) -> Arc<Config> {
+        self.config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Swap the live [`Config`], for the next reconciles to use it.
+    ///
+    /// ### Arguments
+    ///
+    /// * `config` - The new [`Config`].
+    pub fn update_config(&self, config: Config) {
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+
+    /// Check whether the `Route`s of a namespace are reconciled.
+    ///
+    /// ### Arguments
+    ///
+    /// * `namespace` - The name of the namespace.
+    pub fn is_namespace_watched(&self, namespace: &str) -> bool {
+        let config = self.config();
+        config.watch_namespaces.is_empty()
+            || config
+                .watch_namespaces
+                .iter()
+                .any(|watched| watched == namespace)
+    }
+
+    /// Limit the creations of `Certificate`s with an [`IssuanceBudget`].
+    ///
+    /// ### Arguments
+    ///
+    /// * `budget` - The [`IssuanceBudget`].
+    /// * `state` - The persisted [`BudgetState`].
+    pub fn with_issuance_budget(mut self, budget: IssuanceBudget, state: BudgetState) -> Self {
+        self.issuance_budget = Some(budget);
+        self.budget_state = Mutex::new(state);
+        self
+    }
+
+    /// Update the cached `Namespace`, or remove it once it is being deleted.
+    ///
+    /// ### Arguments
+    ///
+    /// * `namespace` - The `Namespace` to cache.
+    pub fn update_namespace(&self, namespace: &Namespace) {
+        let mut namespaces = self
+            .namespaces
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if namespace.metadata.deletion_timestamp.is_some() {
+            namespaces.remove(&namespace.name_any());
+        } else {
+            namespaces.insert(namespace.name_any(), namespace.clone());
+        }
+    }
+
+    /// Record the TLS `Secret` a [`Route`] uses, if any, replacing the one it used before.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`].
+    /// * `secret` - The namespace and name of the `Secret` the [`Route`] uses, or [`None`].
+    pub fn index_route_secret(&self, route: &Route, secret: Option<(String, String)>) {
+        let route = ObjectRef::from_obj(route);
+        let mut secret_routes = self
+            .secret_routes
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        secret_routes.retain(|_, routes| {
+            routes.remove(&route);
+            !routes.is_empty()
+        });
+        if let Some((namespace, name)) = secret {
+            secret_routes
+                .entry(resource_to_string(&name, &namespace))
+                .or_default()
+                .insert(route);
+        }
+    }
+
+    /// Get the [`Route`]s using a TLS `Secret`.
+    ///
+    /// ### Arguments
+    ///
+    /// * `namespace` - The namespace of the `Secret`.
+    /// * `name` - The name of the `Secret`.
+    pub fn get_secret_routes(&self, namespace: &str, name: &str) -> Vec<ObjectRef<Route>> {
+        self.secret_routes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&resource_to_string(name, namespace))
+            .map(|routes| routes.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Count a failed reconciliation of a [`Route`].
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] that failed to reconcile.
+    ///
+    /// ### Returns
+    ///
+    /// The number of consecutive failures before this one.
+    pub fn record_failure(&self, route: &Route) -> u32 {
+        let mut failures = self
+            .failures
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let count = failures.entry(route.to_string()).or_default();
+        *count += 1;
+        *count - 1
+    }
+
+    /// Forget the failures of a [`Route`] once it is reconciled successfully.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The reconciled [`Route`].
+    pub fn reset_failures(&self, route: &Route) {
+        self.failures
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&route.to_string());
+    }
+
+    /// Forget the failures of the [`Route`]s that no longer exist, like the ones deleted while failing.
+    ///
+    /// ### Arguments
+    ///
+    /// * `routes` - The existing [`Route`]s, by namespace and name.
+    pub fn prune_failures(&self, routes: &HashSet<String>) {
+        self.failures
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|route, _| routes.contains(route));
+    }
+
+    /// Record whether a [`Route`] is being reconciled.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`].
+    /// * `reconciling` - Whether its reconciliation starts or finishes.
+    pub fn record_reconciling(&self, route: &Route, reconciling: bool) {
+        let mut routes = self
+            .reconciling
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if reconciling {
+            routes.insert(route.to_string());
+        } else {
+            routes.remove(&route.to_string());
+        }
+    }
+
+    /// Get the [`Route`]s being reconciled, by namespace and name, sorted.
+    pub fn get_reconciling(&self) -> Vec<String> {
+        let mut routes = self
+            .reconciling
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        routes.sort();
+        routes
+    }
+
+    /// Get the [`RoutesCertificatePolicyRule`] applying to a [`Route`] from the caches.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] to get the rule for.
+    ///
+    /// ### Returns
+    ///
+    /// An [`Option`] containing the first matching [`RoutesCertificatePolicyRule`].
+    pub fn get_route_policy_rule(&self, route: &Route) -> Option<RoutesCertificatePolicyRule> {
+        let namespaces = self
+            .namespaces
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        get_route_policy_rule(
+            route,
+            namespaces.get(&route.namespace().unwrap_or_default()),
+            &self.policies.state(),
+        )
+    }
+
+    /// Get the [`IssuerDefaults`] of a [`Route`] from the caches.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] to get the [`IssuerDefaults`] for.
+    ///
+    /// ### Returns
+    ///
+    /// The [`IssuerDefaults`] of the [`Route`]'s `Namespace` and `RoutesCertificatePolicy` rule.
+    pub fn get_issuer_defaults(&self, route: &Route) -> IssuerDefaults {
+        let rule = self.get_route_policy_rule(route);
+        let policy_issuer = rule.as_ref().and_then(|rule| rule.issuer.clone());
+        let policy_certificate_mode = rule.and_then(|rule| rule.certificate_mode);
+        let namespaces = self
+            .namespaces
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        IssuerDefaults {
+            policy_issuer,
+            policy_certificate_mode,
+            ..get_issuer_defaults(
+                namespaces.get(&route.namespace().unwrap_or_default()),
+                self.config().tls_acme_cluster_issuer.as_ref(),
+            )
+        }
+    }
+
+    /// Get the certificate group of a [`Route`] from its annotations and `RoutesCertificatePolicy` rule.
+    ///
+    /// [`Route`]s referencing an existing `Certificate` or with a dedicated `Certificate` don't belong to any group.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] to get the group for.
+    ///
+    /// ### Returns
+    ///
+    /// A [`Result`] containing the group, if any, or a [`String`] describing why the group is invalid.
+    pub fn get_certificate_group(&self, route: &Route) -> Result<Option<CertificateGroup>, String> {
+        if route
+            .annotations()
+            .contains_key(CERTIFICATE_NAME_ANNOTATION_KEY)
+            || get_certificate_mode(route, &self.get_issuer_defaults(route))
+                == Ok(CertificateMode::Dedicated)
+        {
+            return Ok(None);
+        }
+        get_certificate_group(
+            route,
+            self.get_route_policy_rule(route)
+                .and_then(|rule| rule.certificate_group)
+                .as_ref(),
+        )
+    }
+
+    /// Check whether a [`Route`] may request a `ClusterIssuer`.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] requesting the `ClusterIssuer`.
+    /// * `issuer` - The name of the `ClusterIssuer`.
+    ///
+    /// ### Returns
+    ///
+    /// A [`bool`] indicating whether the `ClusterIssuer` is allowed in the [`Route`]'s `Namespace`.
+    pub fn is_issuer_allowed(&self, route: &Route, issuer: &str) -> bool {
+        match &self.config().allowed_issuers() {
+            Some(allowed_issuers) => {
+                let namespaces = self
+                    .namespaces
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                is_issuer_allowed(
+                    issuer,
+                    namespaces.get(&route.namespace().unwrap_or_default()),
+                    allowed_issuers,
+                )
+            }
+            None => true,
         }
     }
+
+    /// Get the `ClusterIssuer` a [`Route`] requests its `Certificate` from if it isn't allowed in its `Namespace`.
+    ///
+    /// [`Route`]s using an existing `Secret` or referencing an existing `Certificate` don't request any `ClusterIssuer`.
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] to check.
+    ///
+    /// ### Returns
+    ///
+    /// An [`Option`] containing the name of the forbidden `ClusterIssuer`.
+    pub fn get_forbidden_issuer(&self, route: &Route) -> Option<String> {
+        let defaults = self.get_issuer_defaults(route);
+        if route
+            .annotations()
+            .contains_key(CERTIFICATE_NAME_ANNOTATION_KEY)
+            || !matches!(
+                get_route_tls_source(route, &self.cert_manager_namespace, &defaults),
+                Some(Ok(TlsSource::Certificate(_, _)))
+            )
+        {
+            return None;
+        }
+        get_route_cluster_issuer(route, &defaults)
+            .filter(|issuer| !self.is_issuer_allowed(route, issuer))
+    }
+
+    /// Ask the [`IssuanceBudget`] for the creation of a [`Route`]'s `Certificate`, and save the [`BudgetState`].
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] creating the `Certificate`.
+    /// * `issuer` - The `ClusterIssuer` of the `Certificate`.
+    ///
+    /// ### Returns
+    ///
+    /// A [`Result`] containing [`None`] if the `Certificate` may be created,
+    /// the estimated time at which the queued [`Route`] will get the budget, or a [`kube::Error`].
+    pub async fn acquire_issuance(
+        &self,
+        route: &Route,
+        issuer: &str,
+    ) -> Result<Option<DateTime<Utc>>, kube::Error> {
+        let Some(budget) = &self.issuance_budget else {
+            return Ok(None);
+        };
+        let mut state = self.budget_state.lock().await;
+        let mut next_state = state.clone();
+        let now = Utc::now();
+        let result = acquire_issuance(
+            &mut next_state,
+            budget,
+            &get_budget_key(issuer, route.spec.host.as_deref().unwrap_or_default()),
+            &route.to_string(),
+            now.timestamp(),
+        );
+        if next_state != *state {
+            save_budget_state(&next_state, self).await?;
+            *state = next_state;
+        }
+        Ok(result
+            .err()
+            .map(|ready_at| Utc.timestamp_opt(ready_at, 0).single().unwrap_or(now)))
+    }
+
+    /// Remove a [`Route`] from the queues of the [`IssuanceBudget`], and save the [`BudgetState`].
+    ///
+    /// ### Arguments
+    ///
+    /// * `route` - The [`Route`] that no longer waits for the budget.
+    ///
+    /// ### Returns
+    ///
+    /// A [`Result`] containing `()` or a [`kube::Error`].
+    pub async fn dequeue_issuance(&self, route: &Route) -> Result<(), kube::Error> {
+        if self.issuance_budget.is_none() {
+            return Ok(());
+        }
+        let mut state = self.budget_state.lock().await;
+        let mut next_state = state.clone();
+        if dequeue_route(&mut next_state, &route.to_string()) {
+            save_budget_state(&next_state, self).await?;
+            *state = next_state;
+        }
+        Ok(())
+    }
+}
+
+/// Build a [`ContextData`] for the tests, with a client pointing to an unreachable API server.
+#[cfg(test)]
+pub(crate) fn test_context() -> ContextData {
+    use crate::config::Config;
+    use kube::runtime::events::Reporter;
+    use kube::runtime::reflector;
+    use kube::Client;
+
+    let client =
+        Client::try_from(kube::Config::new("http://127.0.0.1:1".parse().unwrap())).unwrap();
+    let reporter = Reporter {
+        controller: crate::CONTROLLER_NAME.into(),
+        instance: None,
+    };
+    ContextData::new(
+        client,
+        reporter,
+        reflector::store().0,
+        vec![],
+        vec![],
+        &Config::default(),
+    )
+}
+
+#[tokio::test]
+async fn test_index_route_secret() {
+    let ctx = test_context();
+    let route = Route::new_test_route("hello", "world", "example.com", None, None);
+    let secret = ("hello".to_owned(), "example.com-tls".to_owned());
+    ctx.index_route_secret(&route, Some(secret.clone()));
+    assert_eq!(
+        ctx.get_secret_routes("hello", "example.com-tls"),
+        vec![ObjectRef::from_obj(&route)]
+    );
+    ctx.index_route_secret(&route, Some(("hello".to_owned(), "other-tls".to_owned())));
+    assert!(ctx.get_secret_routes("hello", "example.com-tls").is_empty());
+    assert_eq!(ctx.get_secret_routes("hello", "other-tls").len(), 1);
+    ctx.index_route_secret(&route, None);
+    assert!(ctx.get_secret_routes("hello", "other-tls").is_empty());
+    assert!(ctx.secret_routes.read().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_record_reconciling() {
+    let ctx = test_context();
+    let hello = Route::new_test_route("hello", "world", "example.com", None, None);
+    let other = Route::new_test_route("other", "world", "example.com", None, None);
+    ctx.record_reconciling(&other, true);
+    ctx.record_reconciling(&hello, true);
+    assert_eq!(
+        ctx.get_reconciling(),
+        vec![hello.to_string(), other.to_string()]
+    );
+    ctx.record_reconciling(&other, false);
+    assert_eq!(ctx.get_reconciling(), vec![hello.to_string()]);
+}
+
+#[tokio::test]
+async fn test_prune_failures() {
+    let ctx = test_context();
+    let hello = Route::new_test_route("hello", "world", "example.com", None, None);
+    let other = Route::new_test_route("other", "world", "example.com", None, None);
+    ctx.record_failure(&hello);
+    ctx.record_failure(&other);
+    ctx.prune_failures(&HashSet::from([hello.to_string()]));
+    assert_eq!(ctx.record_failure(&hello), 1);
+    assert_eq!(ctx.record_failure(&other), 0);
+}
+
+/// The origin of the TLS data populated into a [`Route`](crate::crd::route::Route).
+#[derive(Clone, Debug, PartialEq)]
+pub enum TlsSource {
+    /// A cert-manager `Certificate`, by namespace and name.
+    Certificate(String, String),
+    /// An existing TLS `Secret` not managed by cert-manager, by namespace and name.
+    Secret(String, String),
+}
+
+/// Implement the [`fmt::Display`] trait for a [`TlsSource`].
+impl fmt::Display for TlsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TlsSource::Certificate(namespace, name) => {
+                write!(f, "Certificate `{}/{}`", namespace, name)
+            }
+            TlsSource::Secret(namespace, name) => write!(f, "Secret `{}/{}`", namespace, name),
+        }
+    }
+}
+
+impl TlsSource {
+    /// Get an [`ObjectReference`] to the `Certificate` or `Secret`, used as the secondary object of events.
+    pub fn object_ref(&self) -> ObjectReference {
+        let (api_version, kind, namespace, name) = match self {
+            TlsSource::Certificate(namespace, name) => (
+                Certificate::api_version(&()).into_owned(),
+                Certificate::kind(&()).into_owned(),
+                namespace,
+                name,
+            ),
+            TlsSource::Secret(namespace, name) => {
+                ("v1".to_owned(), "Secret".to_owned(), namespace, name)
+            }
+        };
+        ObjectReference {
+            api_version: Some(api_version),
+            kind: Some(kind),
+            namespace: Some(namespace.clone()),
+            name: Some(name.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[test]
+fn test_tls_source_object_ref() {
+    let reference =
+        TlsSource::Certificate("cert-manager".to_owned(), "example.com-cert".to_owned())
+            .object_ref();
+    assert_eq!(reference.api_version.as_deref(), Some("cert-manager.io/v1"));
+    assert_eq!(reference.kind.as_deref(), Some("Certificate"));
+    assert_eq!(reference.namespace.as_deref(), Some("cert-manager"));
+    assert_eq!(reference.name.as_deref(), Some("example.com-cert"));
+    let reference = TlsSource::Secret("hello".to_owned(), "world-tls".to_owned()).object_ref();
+    assert_eq!(reference.api_version.as_deref(), Some("v1"));
+    assert_eq!(reference.kind.as_deref(), Some("Secret"));
+}
+
+/// The PEM-encoded TLS key pair of a `Secret`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsKeyPair<'a> {
+    /// The certificate, from the `tls.crt` key.
+    pub certificate: &'a str,
+    /// The private key, from the `tls.key` key.
+    pub key: &'a str,
+    /// The CA certificate, from the optional `ca.crt` key.
+    pub ca_certificate: Option<&'a str>,
 }
@@ -0,0 +1,442 @@
+use crate::certificate::get_certificate_settings;
+use crate::crd::route::Route;
+use crate::policy::apply_policy_tls_settings;
+use crate::route::{
+    get_route_cluster_issuer, get_route_tls_settings, get_route_tls_source, validate_hostname,
+};
+use crate::types::{ContextData, TlsSource};
+use crate::{
+    CERTIFICATE_NAME_ANNOTATION_KEY, CLUSTER_ISSUER_ANNOTATION_KEY, ISSUANCE_QUEUED_ANNOTATION_KEY,
+    TLS_ACME_ANNOTATION_KEY, TLS_ACME_MIGRATED_ANNOTATION_KEY,
+};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use kube::{
+    api::{ApiResource, DynamicObject, GroupVersionKind},
+    core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview, Operation},
+    Api, ResourceExt,
+};
+use openssl::error::ErrorStack;
+use openssl::ssl::{Ssl, SslAcceptor, SslFiletype, SslMethod};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_openssl::SslStream;
+use tracing::error;
+
+pub const WEBHOOK_PATH: &str = "/validate-route";
+/// The prefix of the annotations configuring how the controller handles a [`Route`].
+const CONTROLLER_ANNOTATION_PREFIX: &str = "cert-manager.io/";
+
+/// The outcome of the validation of a [`Route`] by the admission webhook.
+///
+/// Any error rejects the [`Route`], while the warnings are only reported to the client.
+#[derive(Debug, Default, PartialEq)]
+pub struct RouteValidation {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Validate a [`Route`] against the rules applied by the controller when reconciling it.
+///
+/// [`Route`]s that aren't handled by the controller are always valid.
+///
+/// ### Arguments
+///
+/// * `route` - The [`Route`] to validate.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// The [`RouteValidation`] of the [`Route`].
+///
+/// ### Example
+///
+/// ```rust
+/// let validation = validate_route(&route, &ctx);
+/// println!("Errors: {:?}", validation.errors);
+/// ```
+pub fn validate_route(route: &Route, ctx: &ContextData) -> RouteValidation {
+    let mut validation = RouteValidation::default();
+    let defaults = ctx.get_issuer_defaults(route);
+    let source = match get_route_tls_source(route, &ctx.cert_manager_namespace, &defaults) {
+        Some(Ok(source)) => source,
+        Some(Err(e)) => {
+            validation.errors.push(e);
+            return validation;
+        }
+        None => return validation,
+    };
+
+    let rule = ctx.get_route_policy_rule(route);
+    if let Err(e) = apply_policy_tls_settings(rule.as_ref(), &ctx.config().tls_settings())
+        .and_then(|defaults| get_route_tls_settings(route, &defaults))
+    {
+        validation.errors.push(e);
+    }
+
+    if !requests_certificate(route, &source) {
+        if route
+            .annotations()
+            .contains_key(CLUSTER_ISSUER_ANNOTATION_KEY)
+        {
+            validation.warnings.push(format!(
+                "The `{}` annotation is ignored, the Route uses the existing {}",
+                CLUSTER_ISSUER_ANNOTATION_KEY, source
+            ));
+        }
+        return validation;
+    }
+    if let Err(e) = validate_hostname(route.spec.host.as_deref().unwrap_or_default()) {
+        validation.errors.push(e);
+    }
+    if let Err(e) = get_certificate_settings(
+        route,
+        rule.as_ref().and_then(|rule| rule.certificate.as_ref()),
+    ) {
+        validation.errors.push(e);
+    }
+    if let Err(e) = ctx.get_certificate_group(route) {
+        validation.errors.push(e);
+    }
+    if let Some(issuer) = ctx.get_forbidden_issuer(route) {
+        validation.errors.push(format!(
+            "ClusterIssuer `{}` isn't allowed in namespace `{}`",
+            issuer,
+            route.namespace().unwrap_or_default()
+        ));
+    }
+    validation
+}
+
+/// Parse an [`AdmissionRequest`] fixture.
+#[cfg(test)]
+fn load_request(fixture: &str) -> AdmissionRequest<Route> {
+    serde_json::from_str::<AdmissionReview<Route>>(fixture)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_validate_route() {
+    let ctx = crate::types::test_context();
+    let route = load_request(include_str!("../fixtures/admission/valid-route.json"))
+        .object
+        .unwrap();
+    assert_eq!(validate_route(&route, &ctx), RouteValidation::default());
+
+    let mut unmanaged = route.clone();
+    unmanaged
+        .annotations_mut()
+        .remove(CLUSTER_ISSUER_ANNOTATION_KEY);
+    assert_eq!(validate_route(&unmanaged, &ctx), RouteValidation::default());
+
+    let mut referenced = route.clone();
+    referenced.annotations_mut().insert(
+        CERTIFICATE_NAME_ANNOTATION_KEY.to_owned(),
+        "shared".to_owned(),
+    );
+    referenced.annotations_mut().insert(
+        crate::CERT_DURATION_ANNOTATION_KEY.to_owned(),
+        "invalid".to_owned(),
+    );
+    let validation = validate_route(&referenced, &ctx);
+    assert!(validation.errors.is_empty());
+    assert_eq!(validation.warnings.len(), 1);
+
+    let route = load_request(include_str!("../fixtures/admission/long-host.json"))
+        .object
+        .unwrap();
+    assert_eq!(validate_route(&route, &ctx).errors.len(), 1);
+}
+
+/// Whether the controller creates a `Certificate` for a [`Route`] using a [`TlsSource`].
+fn requests_certificate(route: &Route, source: &TlsSource) -> bool {
+    matches!(source, TlsSource::Certificate(_, _))
+        && !route
+            .annotations()
+            .contains_key(CERTIFICATE_NAME_ANNOTATION_KEY)
+}
+
+/// Get the annotations configuring how the controller handles a [`Route`],
+/// leaving out the ones the controller sets itself.
+fn get_controller_annotations(route: &Route) -> BTreeMap<&String, &String> {
+    route
+        .annotations()
+        .iter()
+        .filter(|(key, _)| {
+            (key.starts_with(CONTROLLER_ANNOTATION_PREFIX) || *key == TLS_ACME_ANNOTATION_KEY)
+                && *key != ISSUANCE_QUEUED_ANNOTATION_KEY
+                && *key != TLS_ACME_MIGRATED_ANNOTATION_KEY
+        })
+        .collect()
+}
+
+/// Check whether an [`AdmissionRequest`] was sent by the controller itself, see `service_account_name`.
+fn is_controller_request(request: &AdmissionRequest<Route>, ctx: &ContextData) -> bool {
+    match (
+        &ctx.config().service_account_name,
+        &request.user_info.username,
+    ) {
+        (Some(name), Some(username)) => {
+            *username
+                == format!(
+                    "system:serviceaccount:{}:{}",
+                    &ctx.cert_manager_namespace, name
+                )
+        }
+        _ => false,
+    }
+}
+
+/// Review the [`Route`] of an [`AdmissionRequest`].
+///
+/// Deleted [`Route`]s, [`Route`]s being deleted and the updates of the controller are always allowed.
+/// As the rules may change after a [`Route`] was created, an update is only denied if it changes
+/// the annotations of the [`Route`] configuring the controller, so that a [`Route`] breaking a new rule
+/// can still be edited otherwise.
+///
+/// ### Arguments
+///
+/// * `request` - The [`AdmissionRequest`] to review.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// The [`AdmissionResponse`] denying the request if the [`Route`] is invalid.
+///
+/// ### Example
+///
+/// ```rust
+/// let response = review_route(&request, &ctx);
+/// println!("Allowed: {}", response.allowed);
+/// ```
+pub fn review_route(request: &AdmissionRequest<Route>, ctx: &ContextData) -> AdmissionResponse {
+    let mut response = AdmissionResponse::from(request);
+    let route = match &request.object {
+        Some(route) if route.metadata.deletion_timestamp.is_none() => route,
+        _ => return response,
+    };
+    if is_controller_request(request, ctx) {
+        return response;
+    }
+    let validation = validate_route(route, ctx);
+    if !validation.warnings.is_empty() {
+        response.warnings = Some(validation.warnings);
+    }
+    let changed = match (&request.operation, &request.old_object) {
+        (Operation::Update, Some(old)) => {
+            get_controller_annotations(old) != get_controller_annotations(route)
+        }
+        _ => true,
+    };
+    if changed && !validation.errors.is_empty() {
+        response = response.deny(validation.errors.join("; "));
+    }
+    response
+}
+
+#[tokio::test]
+async fn test_review_route() {
+    let ctx = crate::types::test_context();
+    let review = |fixture| review_route(&load_request(fixture), &ctx);
+    assert!(review(include_str!("../fixtures/admission/valid-route.json")).allowed);
+    assert!(review(include_str!("../fixtures/admission/delete-route.json")).allowed);
+    assert!(!review(include_str!("../fixtures/admission/passthrough-route.json")).allowed);
+    assert!(!review(include_str!("../fixtures/admission/invalid-duration.json")).allowed);
+    assert!(!review(include_str!("../fixtures/admission/long-host.json")).allowed);
+    assert!(
+        !review(include_str!(
+            "../fixtures/admission/invalid-tls-secret.json"
+        ))
+        .allowed
+    );
+    assert!(
+        !review(include_str!(
+            "../fixtures/admission/update-invalid-annotation.json"
+        ))
+        .allowed
+    );
+    assert!(
+        review(include_str!(
+            "../fixtures/admission/update-unchanged-annotations.json"
+        ))
+        .allowed
+    );
+    assert!(
+        review(include_str!(
+            "../fixtures/admission/update-deleting-route.json"
+        ))
+        .allowed
+    );
+
+    let controller = include_str!("../fixtures/admission/update-by-controller.json");
+    assert!(!review(controller).allowed);
+    ctx.update_config(crate::config::Config {
+        service_account_name: Some("cert-manager-routes".to_owned()),
+        ..crate::config::Config::default()
+    });
+    assert!(review(controller).allowed);
+}
+
+/// Check whether a `ClusterIssuer` exists.
+///
+/// ### Arguments
+///
+/// * `issuer` - The name of the `ClusterIssuer`.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`bool`] indicating whether the `ClusterIssuer` exists.
+/// It is `true` when the `ClusterIssuer` couldn't be fetched, so that no spurious warning is reported.
+///
+/// ### Example
+///
+/// ```rust
+/// if !cluster_issuer_exists("letsencrypt-prod", &ctx).await {
+///     println!("Unknown ClusterIssuer");
+/// }
+/// ```
+pub async fn cluster_issuer_exists(issuer: &str, ctx: &ContextData) -> bool {
+    let resource = ApiResource::from_gvk(&GroupVersionKind::gvk(
+        "cert-manager.io",
+        "v1",
+        "ClusterIssuer",
+    ));
+    !matches!(
+        Api::<DynamicObject>::all_with(ctx.client.clone(), &resource)
+            .get_opt(issuer)
+            .await,
+        Ok(None)
+    )
+}
+
+/// Handle an HTTP request to the admission webhook.
+///
+/// Only `POST` requests on [`WEBHOOK_PATH`] with an [`AdmissionReview`] of a [`Route`] are accepted.
+/// The existence of the requested `ClusterIssuer` is checked on top of [`review_route()`] and reported as a warning.
+async fn handle_request(
+    request: Request<Body>,
+    ctx: Arc<ContextData>,
+) -> Result<Response<Body>, hyper::Error> {
+    if request.method() != Method::POST || request.uri().path() != WEBHOOK_PATH {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap());
+    }
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    let review = match serde_json::from_slice::<AdmissionReview<Route>>(&body) {
+        Ok(review) => review,
+        Err(e) => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Invalid AdmissionReview: {}", e)))
+                .unwrap())
+        }
+    };
+    let request: Result<AdmissionRequest<Route>, _> = review.try_into();
+    let response = match request {
+        Ok(request) => {
+            let mut response = review_route(&request, &ctx);
+            if let Some(route) = request.object.as_ref().filter(|_| response.allowed) {
+                let defaults = ctx.get_issuer_defaults(route);
+                let issuer =
+                    match get_route_tls_source(route, &ctx.cert_manager_namespace, &defaults) {
+                        Some(Ok(source)) if requests_certificate(route, &source) => {
+                            get_route_cluster_issuer(route, &defaults)
+                        }
+                        _ => None,
+                    };
+                if let Some(issuer) = issuer {
+                    if !cluster_issuer_exists(&issuer, &ctx).await {
+                        response.warnings.get_or_insert_with(Vec::new).push(format!(
+                            "ClusterIssuer `{}` doesn't exist, the Certificate won't be issued until it is created",
+                            issuer
+                        ));
+                    }
+                }
+            }
+            response
+        }
+        Err(e) => AdmissionResponse::invalid(e),
+    };
+    Ok(Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            serde_json::to_vec(&response.into_review()).unwrap(),
+        ))
+        .unwrap())
+}
+
+/// Build the TLS acceptor of the admission webhook from PEM files.
+fn build_acceptor(cert_file: &str, key_file: &str) -> Result<SslAcceptor, ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+    builder.set_certificate_chain_file(cert_file)?;
+    builder.set_private_key_file(key_file, SslFiletype::PEM)?;
+    builder.check_private_key()?;
+    Ok(builder.build())
+}
+
+/// Serve the validating admission webhook over HTTPS.
+///
+/// The certificate and key are read again for each connection, so that a renewed certificate is picked up
+/// without restarting the controller.
+///
+/// ### Arguments
+///
+/// * `port` - The port to listen on.
+/// * `cert_file` - The path of the PEM certificate chain.
+/// * `key_file` - The path of the PEM private key.
+/// * `ctx` - The [`ContextData`].
+///
+/// ### Returns
+///
+/// A [`Result`] containing `()` or a [`std::io::Error`] if the server couldn't listen.
+///
+/// ### Example
+///
+/// ```rust
+/// tokio::spawn(run_webhook(8443, cert_file, key_file, ctx.clone()));
+/// ```
+pub async fn run_webhook(
+    port: u16,
+    cert_file: String,
+    key_file: String,
+    ctx: Arc<ContextData>,
+) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let acceptor = match build_acceptor(&cert_file, &key_file) {
+            Ok(acceptor) => acceptor,
+            Err(e) => {
+                error!("Error loading the admission webhook certificate: {}", e);
+                continue;
+            }
+        };
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            let mut stream =
+                match Ssl::new(acceptor.context()).and_then(|ssl| SslStream::new(ssl, stream)) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        error!("Error setting up an admission webhook connection: {}", e);
+                        return;
+                    }
+                };
+            if let Err(e) = Pin::new(&mut stream).accept().await {
+                error!("Error accepting an admission webhook connection: {}", e);
+                return;
+            }
+            let service = service_fn(move |request| handle_request(request, ctx.clone()));
+            if let Err(e) = Http::new().serve_connection(stream, service).await {
+                error!("Error serving an admission webhook connection: {}", e);
+            }
+        });
+    }
+}